@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use tracing::*;
+use zenoh::{
+    Session,
+    key_expr::OwnedKeyExpr,
+    query::{ConsolidationMode, QueryTarget},
+};
+
+use crate::sources::{SourceMessage, SourceSender};
+
+/// Queries the zenoh storages for the history of the key expressions over the last `history`,
+/// the samples are queued as source messages, keeping their timestamps as publish times
+pub fn spawn(
+    session: Session,
+    key_exprs: Vec<OwnedKeyExpr>,
+    history: Duration,
+    sender: SourceSender,
+) {
+    tokio::spawn(async move {
+        for key_expr in key_exprs {
+            if let Err(error) = query(&session, &key_expr, history, &sender).await {
+                warn!(%error, %key_expr, "Failed to backfill history");
+            }
+        }
+    });
+}
+
+#[instrument(skip(session, sender))]
+async fn query(
+    session: &Session,
+    key_expr: &OwnedKeyExpr,
+    history: Duration,
+    sender: &SourceSender,
+) -> anyhow::Result<()> {
+    // Storages reply with every sample of the time range, see the zenoh selector `_time` parameter
+    let selector = format!("{key_expr}?_time=[now(-{}s)..]", history.as_secs());
+    let replies = session
+        .get(selector)
+        .target(QueryTarget::All)
+        .consolidation(ConsolidationMode::None)
+        .await
+        .map_err(|error| anyhow!("Failed to query storages: {error}"))?;
+
+    let mut samples = 0usize;
+    while let Ok(reply) = replies.recv_async().await {
+        let Ok(sample) = reply.into_result() else {
+            continue;
+        };
+        let Some(publish_time) = sample.timestamp().map(|ts| ts.get_time().as_nanos()) else {
+            continue;
+        };
+        let message = SourceMessage::Encoded {
+            topic: sample.key_expr().to_string(),
+            encoding: sample.encoding().clone(),
+            publish_time,
+            payload: sample.payload().to_bytes().into_owned(),
+        };
+        if sender.send(message).await.is_err() {
+            break;
+        }
+        samples += 1;
+    }
+    info!(samples, "Backfilled history");
+    Ok(())
+}
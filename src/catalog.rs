@@ -0,0 +1,453 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tracing::*;
+use zenoh::query::Parameters;
+
+use crate::{
+    cli, journal,
+    mavlink::{battery::BatterySummary, dive::DiveSummary, position::Position},
+    recording_session::{self, RecordingSession},
+};
+
+/// Key of the catalog queryable under the instance prefix, e.g:
+/// `recorder/catalog?vehicle=bluerov;from=2025-01-01T00:00:00Z;min_duration=600;tags=survey,dive`
+/// searches the recordings and
+/// `recorder/catalog/tag?file=recorder_0001_20250101_120000_part01.mcap;tags=thruster_failure;note=...`
+/// tags a recording, or all the parts of a session with `session_id=<ID>`. `verify` marks
+/// recordings safely copied to the shore and `delete` removes them
+const CATALOG_TOPIC: &str = "catalog";
+/// Number of recordings replied when the query does not set a limit
+const DEFAULT_LIMIT: usize = 100;
+
+/// Sidecar manifest of a recording file, `<file>.json`, the catalog is the set of manifests of
+/// the recorder directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub file: String,
+    pub session_id: String,
+    /// Persisted session counter, unset for the idle periods and the recordings made before it
+    /// existed
+    #[serde(default)]
+    pub session_number: u64,
+    pub part: u32,
+    pub vehicle: Option<String>,
+    pub start: String,
+    pub start_ns: u64,
+    /// Unset while the file is being written
+    pub end: Option<String>,
+    pub end_ns: Option<u64>,
+    pub bytes: u64,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Free-form notes of the operators, e.g: `good survey line`
+    #[serde(default)]
+    pub notes: Vec<String>,
+    /// Set once a verified copy of the file is held outside the vehicle
+    #[serde(default)]
+    pub verified: bool,
+    /// Set once the `watch-export` pipeline ran on the file
+    #[serde(default)]
+    pub exported: bool,
+    /// First valid vehicle position of the session, placing the recording on a map
+    #[serde(default)]
+    pub launch_position: Option<Position>,
+    /// Battery statistics of the file, set when it is finished
+    #[serde(default)]
+    pub battery: Option<BatterySummary>,
+    /// Dive profile of the file, set when it is finished
+    #[serde(default)]
+    pub dive: Option<DiveSummary>,
+    /// Shift of the system clock when the start was corrected, applied to the end as well while
+    /// the system clock is not synchronized
+    #[serde(default)]
+    pub clock_correction_ns: i64,
+}
+
+impl Manifest {
+    pub fn new(path: &Path, recording_session: &RecordingSession, vehicle: Option<String>) -> Self {
+        let start = recording_session::corrected_now(recording_session.clock_correction_ns);
+        Self {
+            file: path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            session_id: recording_session.id.to_string(),
+            session_number: recording_session.number.unwrap_or_default(),
+            part: recording_session.part,
+            vehicle,
+            start: start.to_rfc3339(),
+            start_ns: start.timestamp_nanos_opt().unwrap_or_default() as u64,
+            end: None,
+            end_ns: None,
+            bytes: 0,
+            tags: Vec::new(),
+            notes: Vec::new(),
+            verified: false,
+            exported: false,
+            launch_position: recording_session.launch_position,
+            battery: None,
+            dive: None,
+            clock_correction_ns: recording_session.clock_correction_ns,
+        }
+    }
+
+    /// Path of the manifest of a recording file
+    pub fn path(recording: &Path) -> PathBuf {
+        recording.with_extension("json")
+    }
+
+    pub fn load(recording: &Path) -> Result<Self> {
+        let path = Self::path(recording);
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read manifest {path:?}"))?;
+        serde_json::from_str(&content).with_context(|| format!("Failed to parse manifest {path:?}"))
+    }
+
+    pub fn save(&self, recording: &Path) -> Result<()> {
+        let path = Self::path(recording);
+        let content = serde_json::to_string_pretty(self).context("Failed to serialize manifest")?;
+        std::fs::write(&path, content).with_context(|| format!("Failed to write manifest {path:?}"))
+    }
+
+    /// Duration in seconds, up to now for the file being written
+    fn duration(&self) -> f64 {
+        let end_ns = self
+            .end_ns
+            .unwrap_or_else(|| chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default() as u64);
+        end_ns.saturating_sub(self.start_ns) as f64 / 1e9
+    }
+}
+
+/// Completes the manifest of a finished recording file with its end and size
+#[instrument(level = "debug")]
+pub fn finish(recording: &Path, battery: Option<BatterySummary>, dive: Option<DiveSummary>) {
+    let result = Manifest::load(recording).and_then(|mut manifest| {
+        manifest.battery = battery;
+        manifest.dive = dive;
+        let end = recording_session::corrected_now(manifest.clock_correction_ns);
+        manifest.end = Some(end.to_rfc3339());
+        manifest.end_ns = Some(end.timestamp_nanos_opt().unwrap_or_default() as u64);
+        manifest.bytes = std::fs::metadata(recording).map_or(0, |metadata| metadata.len());
+        manifest.save(recording)
+    });
+    if let Err(error) = result {
+        warn!(%error, "Failed to complete the recording manifest");
+    }
+}
+
+/// Moves a recording and its manifest, shifting the manifest times by `correction_ns` when the
+/// file was started with a wrong clock
+pub fn rename(from: &Path, to: &Path, correction_ns: i64) -> Result<()> {
+    let mut manifest = Manifest::load(from)?;
+    std::fs::rename(from, to).with_context(|| format!("Failed to rename {from:?}"))?;
+
+    let shift = |ns: u64| ns.saturating_add_signed(correction_ns);
+    let rfc3339 = |ns: u64| chrono::DateTime::from_timestamp_nanos(ns as i64).to_rfc3339();
+    manifest.file = to
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    manifest.start_ns = shift(manifest.start_ns);
+    manifest.start = rfc3339(manifest.start_ns);
+    manifest.end_ns = manifest.end_ns.map(shift);
+    manifest.end = manifest.end_ns.map(rfc3339);
+    manifest.clock_correction_ns += correction_ns;
+    manifest.save(to)?;
+    std::fs::remove_file(Manifest::path(from)).context("Failed to remove the previous manifest")
+}
+
+/// Search criteria of a catalog query
+#[derive(Debug, Default, PartialEq)]
+struct Filter {
+    from_ns: Option<u64>,
+    to_ns: Option<u64>,
+    tags: Vec<String>,
+    vehicle: Option<String>,
+    min_duration: Option<f64>,
+    offset: usize,
+    limit: usize,
+}
+
+impl Filter {
+    fn parse(parameters: &Parameters) -> Result<Self> {
+        let timestamp = |key: &str| {
+            parameters
+                .get(key)
+                .map(|value| {
+                    chrono::DateTime::parse_from_rfc3339(value)
+                        .map(|date| date.timestamp_nanos_opt().unwrap_or_default() as u64)
+                        .map_err(|error| anyhow!("Invalid {key} timestamp {value:?}: {error}"))
+                })
+                .transpose()
+        };
+        let number = |key: &str| {
+            parameters
+                .get(key)
+                .map(|value| {
+                    value
+                        .parse::<usize>()
+                        .map_err(|error| anyhow!("Invalid {key} {value:?}: {error}"))
+                })
+                .transpose()
+        };
+
+        Ok(Self {
+            from_ns: timestamp("from")?,
+            to_ns: timestamp("to")?,
+            tags: parameters
+                .get("tags")
+                .map(|tags| tags.split(',').map(str::to_owned).collect())
+                .unwrap_or_default(),
+            vehicle: parameters.get("vehicle").map(str::to_owned),
+            min_duration: number("min_duration")?.map(|seconds| seconds as f64),
+            offset: number("offset")?.unwrap_or(0),
+            limit: number("limit")?.unwrap_or(DEFAULT_LIMIT),
+        })
+    }
+
+    /// Recordings overlapping the time range and holding all the tags match
+    fn matches(&self, manifest: &Manifest) -> bool {
+        let end_ns = manifest.end_ns.unwrap_or(u64::MAX);
+        self.from_ns.is_none_or(|from_ns| end_ns >= from_ns)
+            && self.to_ns.is_none_or(|to_ns| manifest.start_ns <= to_ns)
+            && self.tags.iter().all(|tag| manifest.tags.contains(tag))
+            && self
+                .vehicle
+                .as_ref()
+                .is_none_or(|vehicle| manifest.vehicle.as_ref() == Some(vehicle))
+            && self
+                .min_duration
+                .is_none_or(|min_duration| manifest.duration() >= min_duration)
+    }
+}
+
+/// Manifests of the recorder directory, sorted by start
+pub fn manifests(directory: &Path) -> Vec<Manifest> {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return Vec::new();
+    };
+
+    let mut manifests: Vec<Manifest> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "mcap")
+        })
+        .filter_map(|path| Manifest::load(&path).ok())
+        .collect();
+    manifests.sort_by_key(|manifest| manifest.start_ns);
+    manifests
+}
+
+/// Key expression of the catalog queryable, e.g: `recorder/catalog/**`
+pub fn key() -> String {
+    format!("{}/**", prefix())
+}
+
+fn prefix() -> String {
+    format!("{}/{CATALOG_TOPIC}", cli::instance_prefix())
+}
+
+/// Replies to a catalog query, the command is the key suffix. `current` is the file being
+/// written, which cannot be deleted
+pub fn handle(
+    directory: &Path,
+    current: Option<&Path>,
+    key: &str,
+    parameters: &Parameters,
+) -> Result<Value> {
+    match key.strip_prefix(prefix().as_str()) {
+        Some("") => search(directory, parameters),
+        Some("/tag") => tag(directory, parameters),
+        Some("/verify") => verify(directory, parameters),
+        Some("/delete") => delete(directory, current, parameters),
+        _ => Err(anyhow!("Unknown catalog command: {key}")),
+    }
+}
+
+/// Whether a catalog command modifies the recordings, requiring a control token. Verifying is
+/// one, since it unlocks the deletion with --delete-requires-verified
+pub fn is_modifying(key: &str) -> bool {
+    matches!(
+        key.strip_prefix(prefix().as_str()),
+        Some("/tag") | Some("/verify") | Some("/delete")
+    )
+}
+
+/// Path of a recording of the directory, refusing anything else than a recording file name
+pub fn recording_path(directory: &Path, file: &str) -> Result<PathBuf> {
+    let path = Path::new(file);
+    if path.file_name() != Some(path.as_os_str())
+        || path.extension().is_none_or(|extension| extension != "mcap")
+    {
+        return Err(anyhow!("Invalid recording file name: {file:?}"));
+    }
+    Ok(directory.join(path))
+}
+
+/// Recordings designated by the `file` or `session_id` parameter
+fn select(directory: &Path, parameters: &Parameters) -> Result<Vec<PathBuf>> {
+    let recordings: Vec<PathBuf> = match (parameters.get("file"), parameters.get("session_id")) {
+        (Some(file), None) => vec![recording_path(directory, file)?],
+        (None, Some(session_id)) => manifests(directory)
+            .into_iter()
+            .filter(|manifest| manifest.session_id == session_id)
+            .map(|manifest| directory.join(manifest.file))
+            .collect(),
+        _ => return Err(anyhow!("Expected either a file or a session_id")),
+    };
+    if recordings.is_empty() || !recordings.iter().all(|recording| recording.exists()) {
+        return Err(anyhow!("No recording found"));
+    }
+    Ok(recordings)
+}
+
+/// Adds tags and a note to a recording, or to all the parts of a session
+fn tag(directory: &Path, parameters: &Parameters) -> Result<Value> {
+    let tags: Vec<String> = parameters
+        .get("tags")
+        .map(|tags| {
+            tags.split(',')
+                .filter(|tag| !tag.is_empty())
+                .map(str::to_owned)
+                .collect()
+        })
+        .unwrap_or_default();
+    let note = parameters.get("note").filter(|note| !note.is_empty());
+    if tags.is_empty() && note.is_none() {
+        return Err(anyhow!("Nothing to add, expected tags or note"));
+    }
+
+    let mut updated = Vec::new();
+    for recording in select(directory, parameters)? {
+        let mut manifest = Manifest::load(&recording)?;
+        for tag in &tags {
+            if !manifest.tags.contains(tag) {
+                manifest.tags.push(tag.clone());
+            }
+        }
+        manifest.notes.extend(note.map(str::to_owned));
+        manifest.save(&recording)?;
+        info!(file = %manifest.file, ?tags, "Tagged recording");
+        updated.push(json!(manifest));
+    }
+    Ok(Value::Array(updated))
+}
+
+/// Marks recordings as safely copied outside the vehicle, allowing their deletion
+fn verify(directory: &Path, parameters: &Parameters) -> Result<Value> {
+    let mut updated = Vec::new();
+    for recording in select(directory, parameters)? {
+        let mut manifest = Manifest::load(&recording)?;
+        manifest.verified = true;
+        manifest.save(&recording)?;
+        info!(file = %manifest.file, "Recording verified");
+        updated.push(json!(manifest));
+    }
+    Ok(Value::Array(updated))
+}
+
+/// Removes recordings and their manifest, all the safety checks are done before removing any
+fn delete(directory: &Path, current: Option<&Path>, parameters: &Parameters) -> Result<Value> {
+    let recordings = select(directory, parameters)?;
+    for recording in &recordings {
+        if current == Some(recording.as_path()) {
+            return Err(anyhow!(
+                "Refusing to delete the file being written: {recording:?}"
+            ));
+        }
+        if cli::delete_requires_verified()
+            && !Manifest::load(recording).is_ok_and(|manifest| manifest.verified)
+        {
+            return Err(anyhow!(
+                "Refusing to delete an unverified recording: {recording:?}"
+            ));
+        }
+    }
+
+    let mut deleted = Vec::new();
+    for recording in recordings {
+        std::fs::remove_file(&recording)
+            .with_context(|| format!("Failed to delete recording {recording:?}"))?;
+        // Recordings older than the catalog have no manifest
+        let _ = std::fs::remove_file(Manifest::path(&recording));
+        warn!(path = %recording.display(), "Recording deleted");
+        journal::record("delete", json!({ "file": recording }));
+        deleted.push(json!(
+            recording.file_name().map(|name| name.to_string_lossy())
+        ));
+    }
+    Ok(Value::Array(deleted))
+}
+
+/// Replies a page of the recordings matching the query parameters, with the total of matches
+fn search(directory: &Path, parameters: &Parameters) -> Result<Value> {
+    let filter = Filter::parse(parameters)?;
+    let matching: Vec<Manifest> = manifests(directory)
+        .into_iter()
+        .filter(|manifest| filter.matches(manifest))
+        .collect();
+
+    let page: Vec<Value> = matching
+        .iter()
+        .skip(filter.offset)
+        .take(filter.limit)
+        .map(|manifest| {
+            let mut value = json!(manifest);
+            value["duration_s"] = json!(manifest.duration());
+            value
+        })
+        .collect();
+    Ok(json!({
+        "total": matching.len(),
+        "offset": filter.offset,
+        "recordings": page,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalog_filter() {
+        let filter = Filter::parse(&Parameters::from(
+            "from=2025-01-01T00:00:00Z;vehicle=bluerov;tags=survey,dive;min_duration=600;limit=10",
+        ))
+        .unwrap();
+        assert_eq!(filter.from_ns, Some(1_735_689_600_000_000_000));
+        assert_eq!(filter.tags, ["survey", "dive"]);
+        assert_eq!((filter.offset, filter.limit), (0, 10));
+        assert!(Filter::parse(&Parameters::from("from=yesterday")).is_err());
+
+        let mut manifest = Manifest {
+            file: "recorder_0001_20250101_120000_part01.mcap".to_owned(),
+            session_id: String::new(),
+            session_number: 1,
+            part: 1,
+            vehicle: Some("bluerov".to_owned()),
+            start: String::new(),
+            start_ns: 1_735_732_800_000_000_000,
+            end: None,
+            end_ns: Some(1_735_732_800_000_000_000 + 900 * 1_000_000_000),
+            bytes: 0,
+            tags: vec!["dive".to_owned(), "survey".to_owned(), "good".to_owned()],
+            notes: Vec::new(),
+            verified: false,
+            exported: false,
+            launch_position: None,
+            battery: None,
+            dive: None,
+            clock_correction_ns: 0,
+        };
+        assert!(filter.matches(&manifest));
+
+        // Too short
+        manifest.end_ns = Some(manifest.start_ns + 60 * 1_000_000_000);
+        assert!(!filter.matches(&manifest));
+        assert!(Filter::default().matches(&manifest));
+    }
+}
@@ -0,0 +1,485 @@
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
+
+use anyhow::{Result, anyhow};
+use serde_json::{Map, Value};
+use tracing::*;
+
+use crate::channel_descriptor::load_cdr_schema;
+
+/// Separator used by the ros2msg schema encoding to concatenate dependent definitions
+const DEFINITION_SEPARATOR: &str =
+    "================================================================================";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Primitive {
+    Bool,
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Int64,
+    UInt64,
+    Float32,
+    Float64,
+    String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum FieldType {
+    Primitive(Primitive),
+    /// Fully qualified nested message name, e.g: `geometry_msgs/Point`
+    Nested(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArrayKind {
+    Single,
+    Fixed(usize),
+    Sequence,
+}
+
+#[derive(Debug, Clone)]
+struct Field {
+    name: String,
+    field_type: FieldType,
+    array: ArrayKind,
+}
+
+#[derive(Debug, Default)]
+struct MessageDefinition {
+    fields: Vec<Field>,
+}
+
+/// How CDR payloads are recorded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum CdrToJson {
+    /// Record CDR payloads as they are
+    #[default]
+    Off,
+    /// Record CDR payloads and their JSON decoding on a parallel `<topic>/json` channel
+    Parallel,
+    /// Record only the JSON decoding of CDR payloads
+    Replace,
+}
+
+/// How CDR payloads inconsistent with their schema are handled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum InvalidCdr {
+    /// Do not validate CDR payloads
+    #[default]
+    Ignore,
+    /// Log a warning and record the payload anyway
+    Warn,
+    /// Log a warning and record the payload on the diagnostics channel instead
+    Divert,
+}
+
+/// Decodes CDR payloads into JSON values using the ros2msg schemas
+pub struct CdrDecoder {
+    schema_path: Option<PathBuf>,
+    definitions: HashMap<String, Arc<MessageDefinition>>,
+}
+
+impl Primitive {
+    fn parse(name: &str) -> Option<Self> {
+        let primitive = match name {
+            "bool" => Self::Bool,
+            "int8" => Self::Int8,
+            "uint8" | "byte" | "char" => Self::UInt8,
+            "int16" => Self::Int16,
+            "uint16" => Self::UInt16,
+            "int32" => Self::Int32,
+            "uint32" => Self::UInt32,
+            "int64" => Self::Int64,
+            "uint64" => Self::UInt64,
+            "float32" => Self::Float32,
+            "float64" => Self::Float64,
+            "string" | "wstring" => Self::String,
+            _ => return None,
+        };
+        Some(primitive)
+    }
+}
+
+impl MessageDefinition {
+    fn parse(package: &str, content: &str) -> Result<Self> {
+        let mut fields = Vec::new();
+
+        for line in content.lines() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let mut tokens = line.split_whitespace();
+            let (Some(type_token), Some(name)) = (tokens.next(), tokens.next()) else {
+                return Err(anyhow!("Invalid field definition: {line}"));
+            };
+
+            // Constants are not part of the serialized payload
+            if name.contains('=') || tokens.next().is_some_and(|token| token.starts_with('=')) {
+                continue;
+            }
+
+            let (base_type, array) = match type_token.split_once('[') {
+                Some((base_type, bound)) => {
+                    let bound = bound.trim_end_matches(']');
+                    let array = if bound.is_empty() || bound.starts_with("<=") {
+                        ArrayKind::Sequence
+                    } else {
+                        ArrayKind::Fixed(bound.parse().map_err(|error| {
+                            anyhow!("Invalid array size in {type_token}: {error}")
+                        })?)
+                    };
+                    (base_type, array)
+                }
+                None => (type_token, ArrayKind::Single),
+            };
+
+            // Bounded strings, e.g: string<=10
+            let base_type = base_type.split("<=").next().unwrap_or(base_type);
+
+            let field_type = match Primitive::parse(base_type) {
+                Some(primitive) => FieldType::Primitive(primitive),
+                None => FieldType::Nested(qualified_name(package, base_type)),
+            };
+
+            fields.push(Field {
+                name: name.to_owned(),
+                field_type,
+                array,
+            });
+        }
+
+        Ok(Self { fields })
+    }
+}
+
+impl CdrDecoder {
+    pub fn new(schema_path: Option<PathBuf>) -> Self {
+        Self {
+            schema_path,
+            definitions: HashMap::new(),
+        }
+    }
+
+    /// Decodes a CDR payload, `schema_name` follows the zenoh encoding schema format: `package.Type`
+    #[instrument(skip(self, payload), level = "trace")]
+    pub fn decode(&mut self, schema_name: &str, payload: &[u8]) -> Result<Value> {
+        let root = self.resolve(schema_name)?;
+
+        let mut reader = CdrReader::new(payload)?;
+        let value = self.read_message(&mut reader, &root)?;
+        reader.finish()?;
+        Ok(value)
+    }
+
+    /// Parses the root definition and every nested definition it depends on, failing when a
+    /// schema cannot be loaded
+    pub fn resolve(&mut self, schema_name: &str) -> Result<String> {
+        let root = schema_name.replace('.', "/");
+        if self.definitions.contains_key(&root) {
+            return Ok(root);
+        }
+
+        let schema_content = load_cdr_schema(schema_name, self.schema_path.as_ref())?;
+        let package = package_of(&root);
+        let mut sections = schema_content.split(DEFINITION_SEPARATOR);
+        let main = sections.next().unwrap_or_default();
+        self.definitions.insert(
+            root.clone(),
+            Arc::new(MessageDefinition::parse(package, main)?),
+        );
+
+        // Dependencies embedded in the schema, e.g: "MSG: geometry_msgs/Point"
+        for section in sections {
+            let section = section.trim_start();
+            let Some((header, content)) = section.split_once('\n') else {
+                continue;
+            };
+            let Some(name) = header.trim().strip_prefix("MSG: ") else {
+                continue;
+            };
+            let name = qualified_name(package, name);
+            let definition = MessageDefinition::parse(package_of(&name), content)?;
+            self.definitions.entry(name).or_insert(Arc::new(definition));
+        }
+
+        if let Err(error) = self.resolve_dependencies(&root) {
+            self.definitions.remove(&root);
+            return Err(error);
+        }
+
+        Ok(root)
+    }
+
+    fn resolve_dependencies(&mut self, root: &str) -> Result<()> {
+        let mut pending = vec![root.to_owned()];
+        while let Some(name) = pending.pop() {
+            let definition = self.definitions[&name].clone();
+            for field in &definition.fields {
+                let FieldType::Nested(nested) = &field.field_type else {
+                    continue;
+                };
+                if self.definitions.contains_key(nested) {
+                    continue;
+                }
+                let definition = self.load(nested)?;
+                self.definitions
+                    .insert(nested.clone(), Arc::new(definition));
+                pending.push(nested.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn load(&self, name: &str) -> Result<MessageDefinition> {
+        let (package, type_name) = name
+            .rsplit_once('/')
+            .ok_or_else(|| anyhow!("Invalid nested type name: {name}"))?;
+
+        match load_cdr_schema(&format!("{package}.{type_name}"), self.schema_path.as_ref()) {
+            Ok(content) => MessageDefinition::parse(package, &content),
+            Err(error) => match name {
+                "builtin_interfaces/Time" | "builtin_interfaces/Duration" => {
+                    MessageDefinition::parse(package, "int32 sec\nuint32 nanosec")
+                }
+                _ => Err(error),
+            },
+        }
+    }
+
+    fn read_message(&self, reader: &mut CdrReader, name: &str) -> Result<Value> {
+        let definition = self
+            .definitions
+            .get(name)
+            .ok_or_else(|| anyhow!("Unknown message type: {name}"))?;
+
+        let mut map = Map::new();
+        for field in &definition.fields {
+            let value = match field.array {
+                ArrayKind::Single => self.read_field(reader, &field.field_type)?,
+                ArrayKind::Fixed(length) => self.read_array(reader, &field.field_type, length)?,
+                ArrayKind::Sequence => {
+                    let length = reader.read_u32()? as usize;
+                    self.read_array(reader, &field.field_type, length)?
+                }
+            };
+            map.insert(field.name.clone(), value);
+        }
+
+        Ok(Value::Object(map))
+    }
+
+    fn read_array(
+        &self,
+        reader: &mut CdrReader,
+        field_type: &FieldType,
+        length: usize,
+    ) -> Result<Value> {
+        // Guard against corrupted lengths before allocating
+        if length > reader.remaining() {
+            return Err(anyhow!(
+                "Array length {length} exceeds remaining payload ({} bytes)",
+                reader.remaining()
+            ));
+        }
+
+        (0..length)
+            .map(|_| self.read_field(reader, field_type))
+            .collect::<Result<Vec<_>>>()
+            .map(Value::Array)
+    }
+
+    fn read_field(&self, reader: &mut CdrReader, field_type: &FieldType) -> Result<Value> {
+        match field_type {
+            FieldType::Primitive(primitive) => reader.read_primitive(*primitive),
+            FieldType::Nested(name) => self.read_message(reader, name),
+        }
+    }
+}
+
+struct CdrReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    little_endian: bool,
+}
+
+impl<'a> CdrReader<'a> {
+    fn new(payload: &'a [u8]) -> Result<Self> {
+        let (header, data) = payload
+            .split_at_checked(4)
+            .ok_or_else(|| anyhow!("Payload too short for CDR encapsulation header"))?;
+
+        let little_endian = match header[1] {
+            0x00 => false,
+            0x01 => true,
+            kind => return Err(anyhow!("Unsupported CDR encapsulation kind: {kind:#04x}")),
+        };
+
+        Ok(Self {
+            data,
+            position: 0,
+            little_endian,
+        })
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len().saturating_sub(self.position)
+    }
+
+    /// Fails if more than the alignment padding is left unread
+    fn finish(&self) -> Result<()> {
+        if self.remaining() > 3 {
+            return Err(anyhow!(
+                "Payload has {} trailing bytes after decoding",
+                self.remaining()
+            ));
+        }
+        Ok(())
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+        self.position = self.position.next_multiple_of(N);
+        let bytes = self
+            .take_slice(N)
+            .ok_or_else(|| anyhow!("Payload ended unexpectedly at byte {}", self.position))?;
+        Ok(bytes.try_into().expect("Slice length matches N"))
+    }
+
+    /// Takes the next `length` bytes, `None` when the payload is shorter, including lengths
+    /// overflowing the position
+    fn take_slice(&mut self, length: usize) -> Option<&'a [u8]> {
+        let end = self.position.checked_add(length)?;
+        let bytes = self.data.get(self.position..end)?;
+        self.position = end;
+        Some(bytes)
+    }
+
+    fn read_u32(&mut self) -> Result<u32> {
+        let bytes = self.take::<4>()?;
+        Ok(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    fn read_primitive(&mut self, primitive: Primitive) -> Result<Value> {
+        macro_rules! read {
+            ($type:ty, $size:literal) => {{
+                let bytes = self.take::<$size>()?;
+                if self.little_endian {
+                    <$type>::from_le_bytes(bytes)
+                } else {
+                    <$type>::from_be_bytes(bytes)
+                }
+            }};
+        }
+
+        let value = match primitive {
+            Primitive::Bool => Value::from(self.take::<1>()?[0] != 0),
+            Primitive::Int8 => Value::from(read!(i8, 1)),
+            Primitive::UInt8 => Value::from(read!(u8, 1)),
+            Primitive::Int16 => Value::from(read!(i16, 2)),
+            Primitive::UInt16 => Value::from(read!(u16, 2)),
+            Primitive::Int32 => Value::from(read!(i32, 4)),
+            Primitive::UInt32 => Value::from(read!(u32, 4)),
+            Primitive::Int64 => Value::from(read!(i64, 8)),
+            Primitive::UInt64 => Value::from(read!(u64, 8)),
+            Primitive::Float32 => Value::from(read!(f32, 4) as f64),
+            Primitive::Float64 => Value::from(read!(f64, 8)),
+            Primitive::String => {
+                let length = self.read_u32()? as usize;
+                let bytes = self
+                    .take_slice(length)
+                    .ok_or_else(|| anyhow!("String of {length} bytes exceeds payload"))?;
+                // Strings are serialized with their null terminator
+                let bytes = bytes.strip_suffix(&[0]).unwrap_or(bytes);
+                Value::from(String::from_utf8_lossy(bytes).into_owned())
+            }
+        };
+
+        Ok(value)
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut in_quotes = None;
+    for (index, character) in line.char_indices() {
+        match (character, in_quotes) {
+            ('"' | '\'', None) => in_quotes = Some(character),
+            (quote, Some(open)) if quote == open => in_quotes = None,
+            ('#', None) => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn package_of(name: &str) -> &str {
+    name.split('/').next().unwrap_or_default()
+}
+
+/// Qualifies a type name with its package, e.g: `Point` -> `geometry_msgs/Point`
+/// Note: handles the ROS 2 interface format too, e.g: `geometry_msgs/msg/Point`
+fn qualified_name(package: &str, name: &str) -> String {
+    let name = name.replace("/msg/", "/");
+    if name.contains('/') {
+        name
+    } else if name == "Header" {
+        "std_msgs/Header".to_owned()
+    } else {
+        debug!(
+            name,
+            package, "Qualifying nested type with the parent package"
+        );
+        format!("{package}/{name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_decode() {
+        let schema_path = std::env::temp_dir().join("blueos-recorder-cdr-test");
+        std::fs::create_dir_all(schema_path.join("test_msgs")).unwrap();
+        let schema = "# A comment\nint32 CONSTANT=4\nuint8 id\nfloat64[2] values\nstring name\nPoint[] points\n================================================================================\nMSG: test_msgs/Point\nint16 x\nint16 y\n";
+        std::fs::write(schema_path.join("test_msgs").join("Sample.msg"), schema).unwrap();
+        let mut decoder = CdrDecoder::new(Some(schema_path));
+
+        let mut payload = vec![0x00, 0x01, 0x00, 0x00];
+        payload.push(7); // id
+        payload.extend([0; 7]); // padding to 8
+        payload.extend(1.5f64.to_le_bytes());
+        payload.extend(2.5f64.to_le_bytes());
+        payload.extend(4u32.to_le_bytes());
+        payload.extend(b"abc\0");
+        payload.extend(1u32.to_le_bytes());
+        payload.extend(3i16.to_le_bytes());
+        payload.extend((-4i16).to_le_bytes());
+
+        let value = decoder.decode("test_msgs.Sample", &payload).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "id": 7,
+                "values": [1.5, 2.5],
+                "name": "abc",
+                "points": [{ "x": 3, "y": -4 }],
+            })
+        );
+
+        assert!(
+            decoder
+                .decode("test_msgs.Sample", &payload[..payload.len() - 3])
+                .is_err()
+        );
+    }
+}
@@ -1,97 +1,150 @@
-use std::{borrow::Cow, collections::BTreeMap, fmt, path::PathBuf};
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+};
 
-use anyhow::Result;
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 use tracing::*;
 
+use crate::schema_fetch;
+
+/// Largest integer a double holds exactly, JavaScript readers such as Foxglove parse every JSON
+/// number as one
+pub(crate) const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+#[derive(Clone, Deserialize, Serialize)]
 pub struct ChannelDescriptor {
     pub topic: String,
     pub schema_name: String,
     pub schema_encoding: SchemaEncoding,
     pub schema_content: String,
     pub message_encoding: MessageEncoding,
+    /// Channel metadata, e.g: the recording policies applied to the topic
+    #[serde(skip)]
+    pub metadata: BTreeMap<String, String>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum SchemaEncoding {
+    /// Schemaless channels, e.g: raw MAVLink frames
+    None,
     Ros2Msg,
     JsonSchema,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum MessageEncoding {
     Cdr,
     Json,
+    Mavlink,
 }
 
 impl ChannelDescriptor {
+    /// Describes the channel of a topic from the encoding of its samples, the errors are left to
+    /// the caller as they repeat for every sample of the topic
     #[instrument(skip_all)]
     pub fn new(
         topic: &str,
         encoding: &zenoh::bytes::Encoding,
-        payload: &zenoh::bytes::ZBytes,
+        payload: &[u8],
         schema_path: Option<&PathBuf>,
-    ) -> Option<Self> {
+    ) -> Result<Self> {
         let encoding = Cow::from(encoding);
         let mut parts = encoding.split(';');
-        let mime = parts.next()?;
+        let mime = parts.next().unwrap_or_default();
         let mime_schema = parts.next();
 
         // For more information: https://mcap.dev/spec/registry#well-known-schema-encodings
         match (mime, mime_schema) {
             ("application/cdr", Some(schema_name)) => {
-                let schema_content = match load_cdr_schema(schema_name, schema_path) {
-                    Ok(schema) => schema,
-                    Err(error) => {
-                        error!(%error, "Failed to load schema");
-                        return None;
-                    }
-                };
-                Some(ChannelDescriptor {
+                let schema_content = load_cdr_schema(schema_name, schema_path)
+                    .with_context(|| format!("Failed to load schema {schema_name}"))?;
+                Ok(ChannelDescriptor {
                     topic: topic.to_owned(),
                     schema_name: schema_name.to_owned(),
                     schema_encoding: SchemaEncoding::Ros2Msg,
                     schema_content,
                     message_encoding: MessageEncoding::Cdr,
+                    metadata: BTreeMap::new(),
                 })
             }
             ("application/json", _) => {
-                let Ok(string) = payload.try_to_string() else {
-                    warn!("Failed to decode payload as UTF-8 string");
-                    return None;
-                };
-                let Ok(value) = serde_json5::from_str::<Value>(&string) else {
-                    warn!(payload = %string, "Failed to parse payload as JSON5");
-                    return None;
-                };
-                // Foxglove does not support non-object messages
-                if !value.is_object() {
-                    return None;
-                }
-                let schema_name = match mime_schema {
-                    Some(name) => name.to_owned(),
-                    None => topic.replace('/', "."),
-                };
-                let schema_content = create_schema(&value).to_string();
-                Some(ChannelDescriptor {
-                    topic: topic.to_owned(),
-                    schema_name,
-                    schema_encoding: SchemaEncoding::JsonSchema,
-                    schema_content,
-                    message_encoding: MessageEncoding::Json,
-                })
-            }
-            _ => {
-                warn!(mime_schema, "Received unknown encoding");
-                None
+                let string = std::str::from_utf8(payload)
+                    .context("Failed to decode payload as UTF-8 string")?;
+                // JSON5 numbers are parsed as i64 or f64, plain JSON keeps the u64 above i64::MAX
+                let value = serde_json::from_str::<Value>(string)
+                    .or_else(|_| serde_json5::from_str::<Value>(string))
+                    .context("Failed to parse payload as JSON5")?;
+                Self::from_json(topic, mime_schema, &value)
+                    .ok_or_else(|| anyhow!("JSON payload is not an object"))
             }
+            _ => Err(anyhow!("Received unknown encoding {encoding}")),
         }
     }
+
+    /// Creates a JSON channel descriptor with a schema inferred from `value`
+    pub fn from_json(topic: &str, schema_name: Option<&str>, value: &Value) -> Option<Self> {
+        // Foxglove does not support non-object messages
+        if !value.is_object() {
+            return None;
+        }
+        let schema_name = match schema_name {
+            Some(name) => name.to_owned(),
+            None => topic.replace('/', "."),
+        };
+        let schema_content = create_schema(value).to_string();
+        Some(ChannelDescriptor {
+            topic: topic.to_owned(),
+            schema_name,
+            schema_encoding: SchemaEncoding::JsonSchema,
+            schema_content,
+            message_encoding: MessageEncoding::Json,
+            metadata: BTreeMap::new(),
+        })
+    }
+
+    /// Creates a schemaless channel descriptor for raw MAVLink frames
+    pub fn raw_mavlink(topic: &str) -> Self {
+        ChannelDescriptor {
+            topic: topic.to_owned(),
+            schema_name: String::new(),
+            schema_encoding: SchemaEncoding::None,
+            schema_content: String::new(),
+            message_encoding: MessageEncoding::Mavlink,
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    /// Creates a JSON channel descriptor with a known schema, e.g: Foxglove well-known types
+    pub fn with_json_schema(topic: &str, schema_name: &str, schema: &Value) -> Self {
+        ChannelDescriptor {
+            topic: topic.to_owned(),
+            schema_name: schema_name.to_owned(),
+            schema_encoding: SchemaEncoding::JsonSchema,
+            schema_content: schema.to_string(),
+            message_encoding: MessageEncoding::Json,
+            metadata: BTreeMap::new(),
+        }
+    }
+}
+
+/// Returns the schema name of CDR encoded samples, e.g: `application/cdr;std_msgs.String`
+pub fn cdr_schema_name(encoding: &zenoh::bytes::Encoding) -> Option<String> {
+    let encoding = Cow::from(encoding);
+    let (mime, schema_name) = encoding.split_once(';')?;
+    (mime == "application/cdr").then(|| schema_name.to_owned())
 }
 
 impl SchemaEncoding {
     pub const fn as_str(self) -> &'static str {
         match self {
+            Self::None => "",
             Self::Ros2Msg => "ros2msg",
             Self::JsonSchema => "jsonschema",
         }
@@ -103,6 +156,7 @@ impl MessageEncoding {
         match self {
             Self::Cdr => "cdr",
             Self::Json => "json",
+            Self::Mavlink => "mavlink",
         }
     }
 }
@@ -121,8 +175,15 @@ impl fmt::Display for MessageEncoding {
 
 static MSGS_DIR: include_dir::Dir = include_dir::include_dir!("src/external/zBlueberry/msgs");
 
+/// Loads the definition of a `package.Type` from the schema directory, or the embedded schemas,
+/// falling back to the definition served by its publishers over zenoh
 #[instrument(skip_all)]
-fn load_cdr_schema(schema: &str, schema_path: Option<&PathBuf>) -> Result<String> {
+pub(crate) fn load_cdr_schema(schema: &str, schema_path: Option<&PathBuf>) -> Result<String> {
+    load_local_cdr_schema(schema, schema_path)
+        .or_else(|error| schema_fetch::get(schema).ok_or(error))
+}
+
+fn load_local_cdr_schema(schema: &str, schema_path: Option<&PathBuf>) -> Result<String> {
     let mut schema_splitted = schema.split(".");
     let schema_package = schema_splitted.next().ok_or(anyhow::anyhow!(
         "Failed to get schema package from {schema}"
@@ -131,17 +192,17 @@ fn load_cdr_schema(schema: &str, schema_path: Option<&PathBuf>) -> Result<String
         .next()
         .ok_or(anyhow::anyhow!("Failed to get schema name from {schema}"))?;
 
+    let schema_file = Path::new(schema_package).join(format!("{schema_name}.msg"));
     if let Some(schema_path) = schema_path {
-        let schema_path = schema_path.join(format!("{schema_package}/{schema_name}.msg"));
+        let schema_path = schema_path.join(schema_file);
         std::fs::read_to_string(&schema_path)
             .map_err(|error| anyhow::anyhow!("Failed to read schema: {error}, ({schema_path:?})"))
     } else {
-        let schema_path = format!("{schema_package}/{schema_name}.msg");
-        let schema = MSGS_DIR.get_file(&schema_path).ok_or(anyhow::anyhow!(
-            "Failed to get schema file from {schema_path}"
+        let schema = MSGS_DIR.get_file(&schema_file).ok_or(anyhow::anyhow!(
+            "Failed to get schema file from {schema_file:?}"
         ))?;
         let schema = schema.contents_utf8().ok_or(anyhow::anyhow!(
-            "Failed to get schema contents from {schema_path}"
+            "Failed to get schema contents from {schema_file:?}"
         ))?;
         Ok(schema.to_string())
     }
@@ -151,8 +212,7 @@ fn create_schema(value: &Value) -> Value {
     match value {
         Value::Null => json!({ "type": "null" }),
         Value::Bool(_) => json!({ "type": "boolean" }),
-        Value::Number(n) if n.is_i64() => json!({ "type": "integer" }),
-        Value::Number(_) => json!({ "type": "number" }),
+        Value::Number(n) => number_schema(n),
         Value::String(_) => json!({ "type": "string" }),
         Value::Array(arr) => {
             let items = if let Some(first) = arr.first() {
@@ -171,3 +231,21 @@ fn create_schema(value: &Value) -> Value {
         }
     }
 }
+
+/// Integers beyond the exact range of a double are marked with their width, so readers can tell
+/// a u64 counter from a float and avoid rounding it
+fn number_schema(number: &serde_json::Number) -> Value {
+    if let Some(unsigned) = number.as_u64() {
+        if unsigned > MAX_SAFE_INTEGER {
+            return json!({ "type": "integer", "minimum": 0, "format": "uint64" });
+        }
+        return json!({ "type": "integer" });
+    }
+    match number.as_i64() {
+        Some(signed) if signed.unsigned_abs() > MAX_SAFE_INTEGER => {
+            json!({ "type": "integer", "format": "int64" })
+        }
+        Some(_) => json!({ "type": "integer" }),
+        None => json!({ "type": "number" }),
+    }
+}
@@ -3,6 +3,17 @@ use once_cell::sync::OnceCell;
 use std::collections::HashMap;
 use tracing::*;
 
+use crate::{
+    cdr::{CdrToJson, InvalidCdr},
+    deletes::DeleteSamples,
+    mcap::{McapOptions, Profile},
+    recording_session::FilenameTimezone,
+    rotation::SplitAt,
+    sink::SinkConfig,
+    storage::{DEFAULT_EMERGENCY_PATH, ReadOnlyPolicy},
+    transform::NonObjectJson,
+};
+
 static MANAGER: OnceCell<Manager> = OnceCell::new();
 
 struct Manager {
@@ -16,24 +27,431 @@ struct Manager {
     about = env!("CARGO_PKG_DESCRIPTION")
 )]
 pub struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Turns all log categories up to Debug, for more information check RUST_LOG env variable.
     #[arg(short, long)]
     verbose: bool,
 
-    /// Sets the path where recordings will be stored.
-    #[arg(long, default_value = "/tmp")]
+    /// Sets the log output format, json produces structured logs for log aggregators.
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    /// Also writes logs to this file, rotating it by size.
+    #[arg(long)]
+    log_file: Option<String>,
+
+    /// Sets the size in bytes at which the log file is rotated.
+    #[arg(long, value_name = "BYTES", default_value_t = 10 * 1024 * 1024)]
+    log_file_max_size: u64,
+
+    /// Sets how many rotated log files are kept.
+    #[arg(long, default_value_t = 5)]
+    log_file_max_files: usize,
+
+    /// Names this recorder instance, prefixing its keys and file names, e.g: video.
+    #[arg(long, value_name = "NAME", value_parser = parse_instance_name)]
+    instance_name: Option<String>,
+
+    /// Sets the time zone of the timestamp in the file names: utc, local or a fixed offset, e.g: -03:00. The metadata always holds UTC times.
+    #[arg(
+        long,
+        value_name = "TIMEZONE",
+        default_value = "utc",
+        allow_hyphen_values = true
+    )]
+    filename_timezone: FilenameTimezone,
+
+    /// Sets the path where recordings will be stored, `-` writes them to stdout, one after another, for piping into other tools.
+    #[arg(long, default_value_t = default_recorder_path())]
     recorder_path: String,
 
+    /// Records immediately into exactly this file until stopped, e.g: for bench captures.
+    #[arg(long, value_name = "FILE")]
+    output: Option<std::path::PathBuf>,
+
+    /// Sets what happens when the recorder path becomes read-only, e.g: an SD card remounted read-only after errors.
+    #[arg(long, value_enum, default_value_t = ReadOnlyPolicy::Buffer)]
+    on_read_only: ReadOnlyPolicy,
+
+    /// Sets the tmpfs path continuing the recording when the recorder path becomes read-only, its content is lost on reboot.
+    #[arg(long, value_name = "PATH", default_value = DEFAULT_EMERGENCY_PATH)]
+    emergency_path: std::path::PathBuf,
+
+    /// Splits recordings into a new file at clean clock boundaries (UTC).
+    #[arg(long, value_enum)]
+    split_at: Option<SplitAt>,
+
+    /// Finalizes the file and continues on a new part every this many seconds while recording.
+    #[arg(long, value_name = "SECONDS")]
+    checkpoint_interval: Option<u64>,
+
+    /// Hard-stops a recording session once it has written this many bytes, until the next session starts.
+    #[arg(long, value_name = "BYTES")]
+    max_session_bytes: Option<u64>,
+
+    /// Hard-stops a recording session after this many seconds, until the next session starts.
+    #[arg(long, value_name = "SECONDS")]
+    max_session_duration: Option<u64>,
+
+    /// Continues the previous session when the recording restarts within this many seconds.
+    #[arg(long, value_name = "SECONDS")]
+    session_gap_timeout: Option<u64>,
+
+    /// Name of the vehicle, stored in the recordings catalog for fleet searches.
+    #[arg(long, value_name = "NAME")]
+    vehicle: Option<String>,
+
+    /// Refuses to delete recordings through the catalog until they are marked as verified, i.e: safely copied to the shore.
+    #[arg(long)]
+    delete_requires_verified: bool,
+
     /// Sets the path for message schemas. E.g: src/external/zBlueberry/msgs
+    /// Schemas missing from it are queried from their publishers on `schemas/<package>/<Type>`
     #[arg(long)]
     schema_path: Option<String>,
 
+    /// Sets the path of the TOML configuration file, used for per-topic settings like transforms.
+    #[arg(long)]
+    config: Option<String>,
+
+    /// Decodes CDR payloads to JSON using the message schemas, for tools without ros2msg support.
+    #[arg(long, value_enum, default_value_t = CdrToJson::Off)]
+    cdr_to_json: CdrToJson,
+
+    /// Sets how JSON payloads that are not objects are recorded, e.g: a bare float depth or a string status.
+    #[arg(long, value_enum, default_value_t = NonObjectJson::Drop)]
+    non_object_json: NonObjectJson,
+
+    /// Writes the JSON integers a double cannot hold exactly, e.g: u64 counters, as decimal strings.
+    #[arg(long)]
+    json_large_integers_as_strings: bool,
+
+    /// Validates CDR payloads against their schema, optionally diverting invalid ones to recorder/diagnostics.
+    #[arg(long, value_enum, default_value_t = InvalidCdr::Ignore)]
+    invalid_cdr: InvalidCdr,
+
+    /// Sets how deleted keys are recorded, tombstones are written on the recorder/deletes channel.
+    #[arg(long, value_enum, default_value_t = DeleteSamples::Tombstone)]
+    delete_samples: DeleteSamples,
+
+    /// Sets the MCAP header profile, auto uses ros2 only when all the expected channels are CDR: the channels of the previous file, or of the previous runs for the first file.
+    #[arg(long, value_enum, default_value_t = Profile::Auto)]
+    mcap_profile: Profile,
+
+    /// Sets the target uncompressed MCAP chunk size in bytes.
+    #[arg(long, value_name = "BYTES")]
+    mcap_chunk_size: Option<u64>,
+
+    /// Sets the zstd level of the MCAP chunks, higher levels trade CPU for smaller files. E.g: the level suggested by suggest-compression.
+    #[arg(long, value_name = "LEVEL", value_parser = clap::value_parser!(u32).range(1..=22))]
+    mcap_compression_level: Option<u32>,
+
+    /// Writes messages without chunks, minimizing write overhead but disabling compression and indexes.
+    #[arg(long)]
+    mcap_no_chunks: bool,
+
+    /// Skips the message index records written after each chunk, making random-access playback slower.
+    #[arg(long)]
+    mcap_no_message_indexes: bool,
+
+    /// Buffers chunks in memory instead of seeking back in the file to patch their headers.
+    #[arg(long)]
+    mcap_disable_seeking: bool,
+
+    /// Mirrors the file being written to the TCP clients connected to this address, e.g: tcp://0.0.0.0:9000.
+    /// Each connection receives a whole file from its beginning and is closed once it is finished, implies --mcap-disable-seeking
+    #[arg(long, value_name = "ADDRESS")]
+    stream: Option<String>,
+
+    /// Keeps recording when the recorder path fails as long as one of the sinks, e.g: a USB drive copy or a stream, is still written.
+    /// By default only the sinks may fail, this writes the recording to two equal destinations for operations where losing it is unacceptable
+    #[arg(long)]
+    redundant: bool,
+
+    /// Writes the channels under valid ROS 2 topic names, e.g: /mavlink/_1/_1/HEARTBEAT for mavlink/1/1/HEARTBEAT, for exported rosbags and ros2 profile files checked by strict validators.
+    /// The zenoh key of each channel is kept in its key_expr metadata
+    #[arg(long)]
+    ros_topic_names: bool,
+
+    /// Skips computing chunk CRCs, saving CPU at the cost of not detecting corrupted chunks.
+    #[arg(long)]
+    mcap_no_chunk_crcs: bool,
+
+    /// Skips computing the data section CRC, saving CPU at the cost of not detecting corrupted files.
+    #[arg(long)]
+    mcap_no_data_section_crc: bool,
+
+    /// Sets the capacity in bytes of the buffer between the MCAP writer and the file.
+    #[arg(long, value_name = "BYTES")]
+    mcap_buffer_size: Option<usize>,
+
+    /// Coalesces this many messages before handing them to the MCAP writer. Left at 1 with --write-batch-interval-ms, the batches are bounded by their age only.
+    #[arg(long, value_name = "MESSAGES", default_value_t = 1)]
+    write_batch_messages: usize,
+
+    /// Hands coalesced messages to the MCAP writer once the batch is older than this.
+    #[arg(long, value_name = "MILLISECONDS")]
+    write_batch_interval_ms: Option<u64>,
+
+    /// Sets the number of samples the subscriber queue holds before blocking zenoh, defaults to the zenoh data reception channel size.
+    #[arg(long, value_name = "SAMPLES")]
+    subscriber_queue_size: Option<usize>,
+
+    /// Raises an alarm on the events channel and in the status when the subscriber queue fills above these percentages, and when samples start being dropped.
+    /// The alarm clears once the queue drains below half the lowest watermark
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        num_args = 1..,
+        default_values_t = [80],
+        value_parser = clap::value_parser!(u8).range(1..=100)
+    )]
+    queue_watermarks: Vec<u8>,
+
+    /// Enables the low power mode when the battery remaining percentage reported by the autopilot drops to this value.
+    #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u8).range(0..=100))]
+    low_power_battery_percent: Option<u8>,
+
+    /// Finalizes the current file and continues on a new part when the battery remaining percentage drops to this value.
+    #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u8).range(0..=100))]
+    failsafe_battery_percent: Option<u8>,
+
+    /// Finalizes the current file and continues on a new part when the battery voltage drops to this value.
+    #[arg(long, value_name = "VOLTS")]
+    failsafe_battery_voltage: Option<f64>,
+
+    /// Keeps recording this many seconds after the last vehicle failsafe indication, even while disarmed.
+    #[arg(long, value_name = "SECONDS", default_value_t = 300)]
+    failsafe_hold: u64,
+
+    /// Stores the first JPEG frame published on this key expression after arming as the `arm_snapshot.jpg` attachment.
+    #[arg(long, value_name = "KEY_EXPR")]
+    arm_snapshot_topic: Option<zenoh::key_expr::OwnedKeyExpr>,
+
+    /// Finalizes the recording and stops the recorder when a sample is published on this key expression, e.g: the shutdown notification of the vehicle.
+    /// The `recorder/control/shutdown` query does the same and replies once the file is synced, for a pre-stop call
+    #[arg(long, value_name = "KEY_EXPR")]
+    shutdown_key: Option<zenoh::key_expr::OwnedKeyExpr>,
+
+    /// Reads MAVLink directly from a serial port or UDP endpoint, for setups without the zenoh MAVLink bridge.
+    /// Format: serial:<PORT>:<BAUDRATE> or udpin:<IP>:<PORT>
+    #[arg(long, value_name = "ADDRESS")]
+    mavlink_input: Option<String>,
+
+    /// Reads NMEA 0183 sentences from a serial port or TCP endpoint, e.g: standalone GPS and compass units.
+    /// Format: serial:<PORT>:<BAUDRATE> or tcp:<HOST>:<PORT>
+    #[arg(long, value_name = "ADDRESS")]
+    nmea_input: Option<String>,
+
+    /// Probes the topside computer over the tether and records the round-trip times on the recorder/ping channel, telling tether issues apart from software ones.
+    /// The probe times a TCP connection, e.g: 192.168.2.1:22, a refused connection still answers
+    #[arg(long, value_name = "HOST:PORT")]
+    ping_target: Option<String>,
+
+    /// Time between two tether probes, also their timeout, e.g: 1s.
+    #[arg(long, default_value = "1s", value_parser = humantime::parse_duration)]
+    ping_interval: std::time::Duration,
+
+    /// Records the zenoh queries matching this key expression on the `recorder/queries` channel.
+    /// Only queries reaching the recorder are seen, i.e: targeting all queryables or without a complete one.
+    /// The replies are not recorded: zenoh routes them to the querier only, and querying again to observe them would repeat the side effects of the query
+    #[arg(long, value_name = "KEY_EXPR")]
+    record_queries: Option<zenoh::key_expr::OwnedKeyExpr>,
+
+    /// Queries the zenoh storages for the recent history of these key expressions when a recording starts, e.g: topics missed while restarting.
+    /// The samples keep their original timestamps as publish times
+    #[arg(long, value_name = "KEY_EXPR", num_args = 1..)]
+    backfill: Vec<zenoh::key_expr::OwnedKeyExpr>,
+
+    /// History queried from the zenoh storages by --backfill.
+    #[arg(long, value_name = "SECONDS", default_value_t = 300)]
+    backfill_history: u64,
+
+    /// Runs the whole recording pipeline without writing any file, printing a table of the topics that would be recorded.
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Records the keys published by the recorder itself, e.g: recorder/status, which are skipped by default.
+    #[arg(long)]
+    record_own_keys: bool,
+
+    /// Announces the recorder state to the GCS through the MAVLink bridge: a REC named value, 1 while recording, and STATUSTEXT messages when a recording starts, rotates or fails.
+    #[arg(long)]
+    announce_mavlink: bool,
+
+    /// Records raw MAVLink frames published by the bridge on schemaless `mavlink` channels, allowing .tlog reconstruction.
+    #[arg(long)]
+    record_raw_mavlink: bool,
+
+    /// Zenoh endpoints in priority order, e.g: the local router then the topside one over the tether.
+    /// The session connects to the first reachable one and fails over to the others when it is lost
+    #[arg(
+        long,
+        alias = "zenoh-endpoint",
+        value_name = "ENDPOINT",
+        num_args = 1..,
+        default_value = "tcp/127.0.0.1:7447"
+    )]
+    connect: Vec<String>,
+
+    /// Records topside a redundant copy of the vehicle topics, with --connect pointing to the vehicle router over the tether, e.g: tcp/192.168.2.2:7447.
+    /// Tunes the session for a lossy link: larger subscriber queue and link lease, the dropouts are recorded as events and, with --backfill, backfilled from the vehicle storages once the link is back
+    #[arg(long)]
+    mirror: bool,
+
     /// Zenoh configuration key-value pairs. Can be used multiple times.
     /// Format: --zkey key=value
     #[arg(long, value_name = "KEY=VALUE", num_args = 1..)]
     zkey: Vec<String>,
 }
 
+/// One-shot tools, running instead of the recorder service
+#[derive(Debug, clap::Subcommand)]
+pub enum Command {
+    /// Records the bus for a fixed time into a file, then exits with a summary of the captured
+    /// topics, e.g: to grab an ad-hoc snapshot without running the recorder service
+    Capture {
+        /// Capture time, e.g: `60s` or `5m`
+        #[arg(long, default_value = "60s", value_parser = humantime::parse_duration)]
+        duration: std::time::Duration,
+        /// Key expressions of the captured topics, e.g: `mavlink/**`
+        #[arg(long, value_name = "KEY_EXPR", num_args = 1.., default_value = "**")]
+        topics: Vec<zenoh::key_expr::OwnedKeyExpr>,
+        /// Captured recording
+        #[arg(short, long)]
+        output: std::path::PathBuf,
+    },
+    /// Compares the channels, schemas and rates of two recordings, e.g: before and after a
+    /// firmware update
+    Diff {
+        /// Reference recording
+        a: std::path::PathBuf,
+        /// Compared recording
+        b: std::path::PathBuf,
+    },
+    /// Reconstructs a MAVLink telemetry log (.tlog) from the MAVLink channels of a recording
+    ExportTlog {
+        /// Recording to export
+        input: std::path::PathBuf,
+        /// Output tlog file, defaults to the input path with a .tlog extension
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Cuts windows around the occurrences of a recorded event into small files, e.g: to share
+    /// the moments around each arming
+    ExtractEvent {
+        /// Recording to cut
+        input: std::path::PathBuf,
+        /// Name of the event of the `recorder/events` channel, e.g: `arm`, `leak` or `trigger_on`
+        #[arg(long)]
+        event: String,
+        /// Time kept before each occurrence, e.g: `30s`
+        #[arg(long, default_value = "30s", value_parser = humantime::parse_duration)]
+        before: std::time::Duration,
+        /// Time kept after each occurrence, e.g: `2m`
+        #[arg(long, default_value = "120s", value_parser = humantime::parse_duration)]
+        after: std::time::Duration,
+        /// Directory of the extracted files, defaults to the directory of the input
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Renders a standalone HTML summary of a recording, e.g: to review a dive without Foxglove
+    Report {
+        /// Recording to summarize
+        input: std::path::PathBuf,
+        /// Output HTML file, defaults to the input path with a .html extension
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+        /// Numeric JSON fields plotted as sparklines, defaults to the altitude and battery voltage.
+        /// Format: KEY_EXPR:POINTER, e.g: `mavlink/**/ATTITUDE:/message/roll`
+        #[arg(long, value_name = "FIELD", num_args = 1..)]
+        fields: Vec<String>,
+    },
+    /// Writes a copy of a recording without its location data or selected topics and fields,
+    /// e.g: before sharing it publicly
+    Redact {
+        /// Recording to redact
+        input: std::path::PathBuf,
+        /// Redacted recording
+        #[arg(short, long)]
+        output: std::path::PathBuf,
+        /// Drops the channels and metadata revealing the vehicle location: GPS and global
+        /// position MAVLink messages, raw MAVLink frames, NMEA and foxglove.LocationFix channels
+        #[arg(long)]
+        drop_gps: bool,
+        /// Drops the topics matching these key expressions, e.g: `video/**`
+        #[arg(long, value_name = "KEY_EXPR", num_args = 1..)]
+        drop_topics: Vec<zenoh::key_expr::OwnedKeyExpr>,
+        /// Removes these JSON pointers from the JSON messages, e.g: `/message/lat`
+        #[arg(long, value_name = "POINTER", num_args = 1..)]
+        drop_fields: Vec<String>,
+    },
+    /// Writes an indexed copy of a recording cut short, e.g: by a power loss before its summary
+    /// was written, keeping the records up to the first damaged one
+    Recover {
+        /// Recording to recover
+        input: std::path::PathBuf,
+        /// Recovered recording
+        #[arg(short, long)]
+        output: std::path::PathBuf,
+    },
+    /// Checks the zenoh router, its adminspace, the schema and recorder paths and the free space,
+    /// printing what to fix instead of failing at startup
+    Doctor,
+    /// Exits successfully only if the running recorder answers with a live zenoh session and
+    /// recently received samples, e.g: for container health checks
+    Healthcheck,
+    /// Observes the bus and writes a suggested configuration grouping the seen topics with their
+    /// rates, e.g: to discover the topics of a vehicle before tuning the recording
+    Learn {
+        /// Observation time, e.g: `60s` or `5m`
+        #[arg(long, default_value = "60s", value_parser = humantime::parse_duration)]
+        duration: std::time::Duration,
+        /// Suggested configuration file
+        #[arg(short, long)]
+        output: std::path::PathBuf,
+    },
+    /// Publishes known JSON, CDR and synthetic HEARTBEAT samples through the connected router,
+    /// records them and verifies the file, e.g: to check the router, schemas and writer of a
+    /// vehicle. The samples are written as they are, the topics configuration, transforms, CDR to
+    /// JSON conversion and channel policies of the service are not exercised
+    Selftest {
+        /// Recording written by the self-test, defaults to selftest.mcap in the recorder path
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+        /// Time given to the router to route the samples back, e.g: `5s`
+        #[arg(long, default_value = "5s", value_parser = humantime::parse_duration)]
+        timeout: std::time::Duration,
+    },
+    /// Watches the recorder path and runs the `exports` pipeline of the configuration on every
+    /// finished recording, decoupling the post-processing from the recording
+    WatchExport {
+        /// Time between two scans of the recorder path, e.g: `10s`
+        #[arg(long, default_value = "10s", value_parser = humantime::parse_duration)]
+        interval: std::time::Duration,
+        /// Recordings exported at once
+        #[arg(long, default_value_t = 1)]
+        concurrency: usize,
+    },
+    /// Measures the zstd levels and chunk sizes on the JSON messages of recordings and suggests
+    /// --mcap-compression-level and --mcap-chunk-size
+    SuggestCompression {
+        /// Recordings to measure
+        #[arg(required = true)]
+        inputs: Vec<std::path::PathBuf>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+    /// Human-oriented log lines
+    Text,
+    /// One JSON object per event, with timestamp, level, span fields and event fields
+    Json,
+}
+
 /// Constructs our manager, Should be done inside main
 pub fn init() {
     let expanded_args = std::env::args()
@@ -64,11 +482,32 @@ fn args() -> &'static Args {
     &MANAGER.get().unwrap().clap_matches
 }
 
+/// Returns the subcommand to run instead of the recorder service, if any
+pub fn command() -> Option<&'static Command> {
+    args().command.as_ref()
+}
+
 /// Checks if the verbosity parameter was used
 pub fn is_verbose() -> bool {
     args().verbose
 }
 
+pub fn log_format() -> LogFormat {
+    args().log_format
+}
+
+pub fn log_file() -> Option<std::path::PathBuf> {
+    args().log_file.as_ref().map(std::path::PathBuf::from)
+}
+
+pub fn log_file_max_size() -> u64 {
+    args().log_file_max_size
+}
+
+pub fn log_file_max_files() -> usize {
+    args().log_file_max_files
+}
+
 pub fn path_dir_from_arg(arg: &str, create_if_not_exists: bool) -> std::path::PathBuf {
     let path = std::path::PathBuf::from(arg);
 
@@ -97,10 +536,109 @@ pub fn path_dir_from_arg(arg: &str, create_if_not_exists: bool) -> std::path::Pa
     pathbuf
 }
 
+/// Name announced in the zenoh session metadata
+pub fn instance_name() -> String {
+    args()
+        .instance_name
+        .clone()
+        .unwrap_or_else(|| "blueos-recorder".to_owned())
+}
+
+/// Prefix of the keys of the recorder and of the file names, `recorder` by default
+pub fn instance_prefix() -> String {
+    args()
+        .instance_name
+        .clone()
+        .unwrap_or_else(|| "recorder".to_owned())
+}
+
+/// Instance names are spliced in key expressions and file names, so they are limited to a single
+/// key expression chunk without special characters
+fn parse_instance_name(name: &str) -> Result<String, String> {
+    if !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        Ok(name.to_owned())
+    } else {
+        Err("expected only letters, digits, `_` and `-`".to_owned())
+    }
+}
+
+pub fn filename_timezone() -> FilenameTimezone {
+    args().filename_timezone
+}
+
+/// Temporary directory of the platform, e.g: `/tmp` on Linux, for bench use on laptops
+fn default_recorder_path() -> String {
+    std::env::temp_dir().to_string_lossy().into_owned()
+}
+
+/// Directory of the recordings, or of the side files like the trigger history when writing to stdout
 pub fn recorder_path() -> std::path::PathBuf {
+    if is_stdout_output() {
+        return std::env::temp_dir();
+    }
     path_dir_from_arg(&args().recorder_path, true)
 }
 
+/// Explicit recording file, replacing the generated filenames
+pub fn output() -> Option<&'static std::path::Path> {
+    args().output.as_deref()
+}
+
+/// Recorder path as given, without creating it
+pub fn recorder_path_arg() -> &'static str {
+    &args().recorder_path
+}
+
+pub fn on_read_only() -> ReadOnlyPolicy {
+    args().on_read_only
+}
+
+pub fn emergency_path() -> std::path::PathBuf {
+    args().emergency_path.clone()
+}
+
+pub fn is_stdout_output() -> bool {
+    args().recorder_path == "-"
+}
+
+pub fn checkpoint_interval() -> Option<std::time::Duration> {
+    args()
+        .checkpoint_interval
+        .map(std::time::Duration::from_secs)
+}
+
+pub fn vehicle() -> Option<String> {
+    args().vehicle.clone()
+}
+
+pub fn delete_requires_verified() -> bool {
+    args().delete_requires_verified
+}
+
+pub fn max_session_bytes() -> Option<u64> {
+    args().max_session_bytes
+}
+
+pub fn max_session_duration() -> Option<std::time::Duration> {
+    args()
+        .max_session_duration
+        .map(std::time::Duration::from_secs)
+}
+
+pub fn session_gap_timeout() -> Option<std::time::Duration> {
+    args()
+        .session_gap_timeout
+        .map(std::time::Duration::from_secs)
+}
+
+pub fn split_at() -> Option<SplitAt> {
+    args().split_at
+}
+
 pub fn schema_path() -> Option<std::path::PathBuf> {
     args()
         .schema_path
@@ -108,6 +646,159 @@ pub fn schema_path() -> Option<std::path::PathBuf> {
         .map(|schema_path| path_dir_from_arg(schema_path, false))
 }
 
+/// Schema path as given, without checking it
+pub fn schema_path_arg() -> Option<&'static str> {
+    args().schema_path.as_deref()
+}
+
+pub fn config_path() -> Option<std::path::PathBuf> {
+    args().config.as_ref().map(std::path::PathBuf::from)
+}
+
+pub fn cdr_to_json() -> CdrToJson {
+    args().cdr_to_json
+}
+
+pub fn invalid_cdr() -> InvalidCdr {
+    args().invalid_cdr
+}
+
+pub fn delete_samples() -> DeleteSamples {
+    args().delete_samples
+}
+
+pub fn mcap_options() -> McapOptions {
+    McapOptions {
+        profile: args().mcap_profile,
+        chunk_size: args().mcap_chunk_size,
+        compression_level: args().mcap_compression_level,
+        use_chunks: !args().mcap_no_chunks,
+        emit_message_indexes: !args().mcap_no_message_indexes,
+        disable_seeking: args().mcap_disable_seeking || is_stdout_output(),
+        chunk_crcs: !args().mcap_no_chunk_crcs,
+        data_section_crc: !args().mcap_no_data_section_crc,
+        buffer_size: args().mcap_buffer_size,
+        batch_messages: args().write_batch_messages,
+        batch_interval: args()
+            .write_batch_interval_ms
+            .map(std::time::Duration::from_millis),
+        sinks: args()
+            .stream
+            .iter()
+            .map(|address| SinkConfig::Stream {
+                address: address.clone(),
+            })
+            .collect(),
+        redundant: args().redundant,
+        ros_topic_names: args().ros_topic_names,
+        schema_path: schema_path(),
+    }
+}
+
+pub fn subscriber_queue_size() -> Option<usize> {
+    args()
+        .subscriber_queue_size
+        .or(is_mirror().then_some(crate::mirror::QUEUE_SIZE))
+}
+
+pub fn queue_watermarks() -> Vec<u8> {
+    args().queue_watermarks.clone()
+}
+
+pub fn low_power_battery_percent() -> Option<u8> {
+    args().low_power_battery_percent
+}
+
+pub fn failsafe_battery_percent() -> Option<u8> {
+    args().failsafe_battery_percent
+}
+
+pub fn failsafe_battery_voltage() -> Option<f64> {
+    args().failsafe_battery_voltage
+}
+
+pub fn failsafe_hold() -> std::time::Duration {
+    std::time::Duration::from_secs(args().failsafe_hold)
+}
+
+pub fn arm_snapshot_topic() -> Option<zenoh::key_expr::OwnedKeyExpr> {
+    args().arm_snapshot_topic.clone()
+}
+
+pub fn shutdown_key() -> Option<zenoh::key_expr::OwnedKeyExpr> {
+    args().shutdown_key.clone()
+}
+
+pub fn mavlink_input() -> Option<String> {
+    args().mavlink_input.clone()
+}
+
+pub fn nmea_input() -> Option<String> {
+    args().nmea_input.clone()
+}
+
+pub fn ping_target() -> Option<String> {
+    args().ping_target.clone()
+}
+
+pub fn ping_interval() -> std::time::Duration {
+    args().ping_interval
+}
+
+pub fn record_queries() -> Option<zenoh::key_expr::OwnedKeyExpr> {
+    args().record_queries.clone()
+}
+
+pub fn backfill() -> Vec<zenoh::key_expr::OwnedKeyExpr> {
+    args().backfill.clone()
+}
+
+pub fn backfill_history() -> std::time::Duration {
+    std::time::Duration::from_secs(args().backfill_history)
+}
+
+pub fn is_dry_run() -> bool {
+    args().dry_run
+}
+
+pub fn record_own_keys() -> bool {
+    args().record_own_keys
+}
+
+/// Destination of the samples published by the recorder, restricted to the remote subscribers to
+/// keep them out of its own recording unless --record-own-keys is set
+pub fn own_keys_destination() -> zenoh::sample::Locality {
+    if record_own_keys() {
+        zenoh::sample::Locality::Any
+    } else {
+        zenoh::sample::Locality::Remote
+    }
+}
+
+pub fn non_object_json() -> NonObjectJson {
+    args().non_object_json
+}
+
+pub fn json_large_integers_as_strings() -> bool {
+    args().json_large_integers_as_strings
+}
+
+pub fn announce_mavlink() -> bool {
+    args().announce_mavlink
+}
+
+pub fn record_raw_mavlink() -> bool {
+    args().record_raw_mavlink
+}
+
+pub fn connect() -> Vec<String> {
+    args().connect.clone()
+}
+
+pub fn is_mirror() -> bool {
+    args().mirror
+}
+
 /// Returns the zenoh configuration key-value pairs as a HashMap
 pub fn zkey_config() -> HashMap<String, String> {
     let mut config = HashMap::new();
@@ -177,4 +868,32 @@ mod tests {
         assert_eq!(config.get("potato.coiso"), Some(&"fifi".to_string()));
         assert_eq!(config.len(), 2);
     }
+
+    #[test]
+    fn test_default_recorder_path() {
+        let args = Args::parse_from(vec!["program_name"]);
+        assert_eq!(
+            std::path::Path::new(&args.recorder_path),
+            std::env::temp_dir()
+        );
+    }
+
+    #[test]
+    fn test_filename_timezone_parsing() {
+        let args = Args::parse_from(vec!["program_name", "--filename-timezone", "-03:00"]);
+        assert_eq!(
+            args.filename_timezone,
+            FilenameTimezone::Fixed(chrono::FixedOffset::west_opt(3 * 3600).unwrap())
+        );
+        assert!(Args::try_parse_from(vec!["program_name", "--filename-timezone", "mars"]).is_err());
+    }
+
+    #[test]
+    fn test_instance_name_parsing() {
+        let args = Args::parse_from(vec!["program_name", "--instance-name", "video_2"]);
+        assert_eq!(args.instance_name.as_deref(), Some("video_2"));
+        for name in ["", "a/b", "a*", "$a", "a?", "a#", "a b", ".."] {
+            assert!(Args::try_parse_from(vec!["program_name", "--instance-name", name]).is_err());
+        }
+    }
 }
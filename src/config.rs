@@ -0,0 +1,86 @@
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::{Context, Result};
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+use tracing::*;
+
+use crate::{
+    hooks::Hooks,
+    priority::PriorityRule,
+    sink::SinkConfig,
+    sources::{command::CommandSource, mqtt::MqttSource, rest::RestEndpoint},
+    subscriber::SubscriberOptions,
+    tools::watch_export::Export,
+    topics::{self, TopicConfig},
+    transform::TransformRule,
+    trigger::{Condition, Expression, Trigger},
+};
+
+static CONFIG: OnceCell<Config> = OnceCell::new();
+
+/// Recorder configuration file, loaded from the path given by `--config`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Per-topic JSON transforms, the first rule matching a topic is applied
+    pub transforms: Vec<TransformRule>,
+    /// Per-topic priority classes used to shed load, the first rule matching a topic is applied
+    pub priorities: Vec<PriorityRule>,
+    /// REST endpoints polled periodically and recorded, e.g: BlueOS services health
+    pub rest_endpoints: Vec<RestEndpoint>,
+    /// MQTT broker whose messages are recorded, e.g: shipboard sensors
+    pub mqtt: Option<MqttSource>,
+    /// External commands whose stdout JSON lines are recorded
+    pub commands: Vec<CommandSource>,
+    /// Condition driving the recording instead of the vehicle armed state
+    pub trigger: Option<Condition>,
+    /// Named conditions, combined by `record_when`
+    pub conditions: BTreeMap<String, Condition>,
+    /// Expression of the named conditions driving the recording, e.g: `(armed and depth) or manual`
+    pub record_when: Option<Expression>,
+    /// Options of the subscriber receiving the recorded samples
+    pub subscriber: SubscriberOptions,
+    /// Additional destinations of the recordings, written along the recorder path
+    pub sinks: Vec<SinkConfig>,
+    /// STATUSTEXT patterns forcing the recording as failsafes, e.g: `["failsafe", "leak"]`
+    pub failsafe_statustext: Vec<String>,
+    /// Leak sensor condition, flushing the recording and accelerating its flushes when it holds
+    pub leak: Option<Condition>,
+    /// Tokens accepted in the `token` parameter of the control, tag and delete queries,
+    /// these are open to anyone on the network when empty
+    pub control_tokens: Vec<String>,
+    /// Per-topic settings keyed by key expression: rate limit, priority, rename, transform and
+    /// whether they are recorded
+    pub topics: BTreeMap<String, TopicConfig>,
+    /// Commands run when a recording file starts, finishes or on errors
+    pub hooks: Hooks,
+    /// Pipeline run by `watch-export` on every finished recording
+    pub exports: Vec<Export>,
+}
+
+/// Loads the configuration file, should be done inside main after cli::init()
+/// Note: if no path is provided the default (empty) configuration is used
+#[instrument(level = "debug")]
+pub fn init(path: Option<&Path>) -> Result<()> {
+    let config = match path {
+        Some(path) => {
+            info!(path = ?path, "Loading configuration file");
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read configuration file {path:?}"))?;
+            toml::from_str(&content)
+                .with_context(|| format!("Failed to parse configuration file {path:?}"))?
+        }
+        None => Config::default(),
+    };
+    Trigger::from_config(&config).context("Invalid recording trigger")?;
+    topics::validate(&config.topics).context("Invalid topics table")?;
+
+    CONFIG.get_or_init(|| config);
+    Ok(())
+}
+
+/// Accessor to the loaded configuration
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
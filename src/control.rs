@@ -0,0 +1,80 @@
+use anyhow::anyhow;
+use serde_json::Value;
+use tracing::*;
+use zenoh::{bytes::Encoding, query::Query};
+
+use crate::{cli, config};
+
+/// Key of the control queryable under the instance prefix, commands are the last chunk of the
+/// key, e.g: `z_get -s recorder/control/pause`
+const CONTROL_TOPIC: &str = "control";
+
+/// Key expression of the control queryable, e.g: `recorder/control/**`
+pub fn key() -> String {
+    format!("{}/{CONTROL_TOPIC}/**", cli::instance_prefix())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlCommand {
+    /// Stops writing messages while keeping the file open
+    Pause,
+    Resume,
+    /// Enables or disables the low power mode
+    LowPower(bool),
+    /// Sets the `manual` trigger condition
+    Manual(bool),
+    /// Finalizes and syncs the recording then stops the recorder, before the vehicle powers down
+    Shutdown,
+}
+
+impl ControlCommand {
+    pub fn from_key(key: &str) -> Option<Self> {
+        let prefix = format!("{}/{CONTROL_TOPIC}/", cli::instance_prefix());
+        match key.strip_prefix(prefix.as_str())? {
+            "pause" => Some(Self::Pause),
+            "resume" => Some(Self::Resume),
+            "low_power/on" => Some(Self::LowPower(true)),
+            "low_power/off" => Some(Self::LowPower(false)),
+            "manual/on" => Some(Self::Manual(true)),
+            "manual/off" => Some(Self::Manual(false)),
+            "shutdown" => Some(Self::Shutdown),
+            _ => None,
+        }
+    }
+}
+
+/// Checks the `token` parameter of a query against the configured control tokens,
+/// e.g: `z_get -s 'recorder/control/pause?token=secret'`
+pub fn authorize(query: &Query) -> anyhow::Result<()> {
+    let tokens = &config::get().control_tokens;
+    if tokens.is_empty() {
+        return Ok(());
+    }
+    let authorized = query
+        .parameters()
+        .get("token")
+        .is_some_and(|token| tokens.iter().any(|allowed| allowed == token));
+    if !authorized {
+        warn!(key = %query.key_expr(), "Rejected unauthorized query");
+        return Err(anyhow!("Missing or invalid control token"));
+    }
+    Ok(())
+}
+
+/// Replies to a control query with a JSON value, or an error message
+#[instrument(skip_all, fields(key = %query.key_expr()))]
+pub async fn reply(query: &Query, result: anyhow::Result<Value>) {
+    let reply = match result {
+        Ok(value) => {
+            query
+                .reply(query.key_expr().clone(), value.to_string())
+                .encoding(Encoding::APPLICATION_JSON)
+                .await
+        }
+        Err(error) => query.reply_err(error.to_string()).await,
+    };
+
+    if let Err(error) = reply {
+        warn!(%error, "Failed to reply to control query");
+    }
+}
@@ -0,0 +1,61 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tracing::*;
+
+/// File persisting the counters in the recorder directory
+const COUNTERS_FILE: &str = "counters.json";
+
+/// Counters increasing across restarts, numbering the recordings even when the vehicle clock
+/// resets between boots
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Counters {
+    #[serde(skip)]
+    path: PathBuf,
+    /// Recording sessions started
+    pub sessions: u64,
+    /// Times the vehicle was armed
+    pub arms: u64,
+}
+
+impl Counters {
+    /// Loads the counters of the recorder directory, starting from zero when missing
+    #[instrument(level = "debug")]
+    pub fn load(directory: &Path) -> Self {
+        let path = directory.join(COUNTERS_FILE);
+        let mut counters: Self = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        counters.path = path;
+        counters
+    }
+
+    /// Counts a new session, returns its number
+    pub fn next_session(&mut self) -> u64 {
+        self.sessions += 1;
+        self.save();
+        self.sessions
+    }
+
+    /// Counts a vehicle arming, returns the arm count
+    pub fn record_arm(&mut self) -> u64 {
+        self.arms += 1;
+        self.save();
+        self.arms
+    }
+
+    /// Writes the counters to a temporary file renamed over the previous one, a power loss
+    /// never leaves them truncated
+    fn save(&self) {
+        let temporary = self.path.with_extension("json.tmp");
+        let result = serde_json::to_vec_pretty(self)
+            .map_err(std::io::Error::from)
+            .and_then(|content| std::fs::write(&temporary, content))
+            .and_then(|()| std::fs::rename(&temporary, &self.path));
+        if let Err(error) = result {
+            warn!(%error, path = %self.path.display(), "Failed to persist counters");
+        }
+    }
+}
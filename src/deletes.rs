@@ -0,0 +1,22 @@
+use serde_json::{Value, json};
+
+/// Channel holding the tombstones of the deleted keys
+pub const DELETES_TOPIC: &str = "recorder/deletes";
+
+/// How samples of `SampleKind::Delete` are handled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum DeleteSamples {
+    /// Record a tombstone on the deletes channel, keeping the key lifecycle for replays
+    #[default]
+    Tombstone,
+    /// Do not record deletes
+    Ignore,
+}
+
+/// Tombstone of a deleted key, `publish_time` is the timestamp of the delete in nanoseconds
+pub fn tombstone(key: &str, publish_time: u64) -> Value {
+    json!({
+        "key": key,
+        "publish_time": publish_time,
+    })
+}
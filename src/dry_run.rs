@@ -0,0 +1,98 @@
+use std::{collections::BTreeMap, time::Instant};
+
+use crate::channel_descriptor::ChannelDescriptor;
+
+/// Interval between two reports of the dry run table
+pub const DRY_RUN_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Why a sample reached, or not, the writer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Recorded,
+    /// Dropped by the arm or pause gates
+    Gated,
+}
+
+struct TopicReport {
+    status: Status,
+    messages: u64,
+    bytes: u64,
+    transform: Option<String>,
+    schema: Option<String>,
+}
+
+/// Per-topic view of what a recording would contain, printed instead of writing a file
+pub struct DryRun {
+    window_start: Instant,
+    topics: BTreeMap<String, TopicReport>,
+}
+
+impl DryRun {
+    pub fn new() -> Self {
+        Self {
+            window_start: Instant::now(),
+            topics: BTreeMap::new(),
+        }
+    }
+
+    /// Accounts a sample, `transform` is the key expression of the rule matching the topic
+    pub fn record(&mut self, topic: &str, status: Status, bytes: usize, transform: Option<&str>) {
+        let report = self
+            .topics
+            .entry(topic.to_owned())
+            .or_insert_with(|| TopicReport {
+                status,
+                messages: 0,
+                bytes: 0,
+                transform: None,
+                schema: None,
+            });
+        report.status = status;
+        report.messages += 1;
+        report.bytes += bytes as u64;
+        report.transform = transform.map(str::to_owned);
+    }
+
+    /// Stores the schema resolution result of a topic, `None` when it failed
+    pub fn resolve(&mut self, topic: &str, descriptor: Option<&ChannelDescriptor>) {
+        let Some(report) = self.topics.get_mut(topic) else {
+            return;
+        };
+        report.schema = Some(match descriptor {
+            Some(descriptor) => format!(
+                "{} ({}/{})",
+                descriptor.schema_name, descriptor.schema_encoding, descriptor.message_encoding
+            ),
+            None => "unresolved".to_owned(),
+        });
+    }
+
+    /// Renders the table of the current window and starts a new one
+    pub fn table(&mut self) -> String {
+        let elapsed = self.window_start.elapsed().as_secs_f64().max(f64::EPSILON);
+        self.window_start = Instant::now();
+
+        let mut table = format!(
+            "{:<48} {:>9} {:>10} {:<9} {:<24} SCHEMA\n",
+            "TOPIC", "MSG/S", "KB/S", "STATUS", "TRANSFORM"
+        );
+        for (topic, report) in &mut self.topics {
+            let status = match report.status {
+                Status::Recorded => "recorded",
+                Status::Gated => "gated",
+            };
+            table.push_str(&format!(
+                "{:<48} {:>9.1} {:>10.1} {:<9} {:<24} {}\n",
+                topic,
+                report.messages as f64 / elapsed,
+                report.bytes as f64 / elapsed / 1e3,
+                status,
+                report.transform.as_deref().unwrap_or("-"),
+                report.schema.as_deref().unwrap_or("-"),
+            ));
+            report.messages = 0;
+            report.bytes = 0;
+        }
+        table
+    }
+}
@@ -0,0 +1,40 @@
+use serde_json::{Value, json};
+
+use crate::cli;
+
+/// Channel holding the recorder lifecycle events, e.g: pause and resume, under the instance prefix
+const EVENTS_TOPIC: &str = "events";
+pub const EVENT_SCHEMA: &str = "blueos_recorder.Event";
+
+/// Key of the lifecycle events, e.g: `recorder/events`
+pub fn key() -> String {
+    format!("{}/{EVENTS_TOPIC}", cli::instance_prefix())
+}
+
+/// Whether a recorded channel holds lifecycle events, whichever instance recorded them
+pub fn is_events_channel(channel: &mcap::Channel) -> bool {
+    channel
+        .schema
+        .as_ref()
+        .is_some_and(|schema| schema.name == EVENT_SCHEMA)
+}
+
+pub fn schema() -> Value {
+    json!({
+        "title": EVENT_SCHEMA,
+        "type": "object",
+        "properties": {
+            "event": { "type": "string" },
+            "message": { "type": "string" },
+            "details": { "type": "object" },
+        },
+    })
+}
+
+pub fn event(name: &str, message: &str, details: Value) -> Value {
+    json!({
+        "event": name,
+        "message": message,
+        "details": details,
+    })
+}
@@ -0,0 +1,92 @@
+use std::time::{Duration, Instant};
+
+/// Cause of the recordings forced by a vehicle failsafe
+pub const FAILSAFE: &str = "failsafe";
+
+/// Battery recovery needed to re-arm the failsafe, avoiding repeated triggers on noisy readings
+const HYSTERESIS_PERCENT: u8 = 5;
+const HYSTERESIS_VOLTAGE: f64 = 0.5;
+
+/// Finalizes the recording file when the battery gets low, so a brown-out only affects the
+/// part being recorded after it
+pub struct BatteryFailsafe {
+    percent: Option<u8>,
+    voltage: Option<f64>,
+    triggered: bool,
+}
+
+impl BatteryFailsafe {
+    pub fn new(percent: Option<u8>, voltage: Option<f64>) -> Self {
+        Self {
+            percent,
+            voltage,
+            triggered: false,
+        }
+    }
+
+    /// Returns true once when the battery crosses one of the thresholds,
+    /// it is triggered again only after the battery recovers
+    pub fn update(&mut self, remaining_percent: Option<u8>, voltage: Option<f64>) -> bool {
+        let low_percent = self
+            .percent
+            .zip(remaining_percent)
+            .is_some_and(|(threshold, remaining)| remaining <= threshold);
+        let low_voltage = self
+            .voltage
+            .zip(voltage)
+            .is_some_and(|(threshold, voltage)| voltage <= threshold);
+        if low_percent || low_voltage {
+            let trigger = !self.triggered;
+            self.triggered = true;
+            return trigger;
+        }
+
+        let recovered_percent =
+            self.percent
+                .zip(remaining_percent)
+                .is_some_and(|(threshold, remaining)| {
+                    remaining > threshold.saturating_add(HYSTERESIS_PERCENT)
+                });
+        let recovered_voltage = self
+            .voltage
+            .zip(voltage)
+            .is_some_and(|(threshold, voltage)| voltage > threshold + HYSTERESIS_VOLTAGE);
+        if recovered_percent || recovered_voltage {
+            self.triggered = false;
+        }
+        false
+    }
+}
+
+/// Forces the recording while the vehicle reports failsafes, and for `hold` after the last one,
+/// so anomalies happening while disarmed are still captured
+pub struct FailsafeTrigger {
+    hold: Duration,
+    until: Option<Instant>,
+}
+
+impl FailsafeTrigger {
+    pub fn new(hold: Duration) -> Self {
+        Self { hold, until: None }
+    }
+
+    /// Extends the forced recording, returns true when it starts
+    pub fn indicate(&mut self) -> bool {
+        let started = !self.is_active();
+        self.until = Some(Instant::now() + self.hold);
+        started
+    }
+
+    /// Returns true once when the forced recording expires
+    pub fn update(&mut self) -> bool {
+        let expired = self.until.is_some_and(|until| Instant::now() >= until);
+        if expired {
+            self.until = None;
+        }
+        expired
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.until.is_some_and(|until| Instant::now() < until)
+    }
+}
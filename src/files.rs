@@ -0,0 +1,100 @@
+use std::{
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow};
+use serde_json::json;
+use tokio_graceful_shutdown::SubsystemHandle;
+use tracing::*;
+use zenoh::{Session, bytes::Encoding, query::Query};
+
+use crate::{catalog, cli};
+
+/// Key of the queryable serving the recordings by chunks under the instance prefix, e.g:
+/// `z_get -s "recorder/files/recorder_0001_20250101_120000_part01.mcap?offset=0;length=1048576"`
+/// The reply attachment holds the JSON `{"offset", "length", "size"}` of the chunk, the file is
+/// complete once `offset + length == size`
+const FILES_TOPIC: &str = "files";
+/// Chunk length when the query does not set one
+const DEFAULT_CHUNK: u64 = 1024 * 1024;
+/// Bounds the chunks held in memory and sent in a single reply
+const MAX_CHUNK: u64 = 8 * 1024 * 1024;
+
+/// Serves the recordings over zenoh, for topside tools without an HTTP route to the vehicle
+#[instrument(skip(session, subsystem))]
+pub async fn run(
+    session: Session,
+    directory: PathBuf,
+    subsystem: &mut SubsystemHandle,
+) -> anyhow::Result<()> {
+    let queryable = session
+        .declare_queryable(format!("{}/{FILES_TOPIC}/*", cli::instance_prefix()))
+        .await
+        .map_err(|error| anyhow!("Failed to declare files queryable: {error}"))?;
+
+    loop {
+        tokio::select! {
+            Ok(query) = queryable.recv_async() => reply(&directory, &query).await,
+            _ = subsystem.on_shutdown_requested() => break,
+        }
+    }
+    Ok(())
+}
+
+#[instrument(skip_all, fields(key = %query.key_expr()))]
+async fn reply(directory: &Path, query: &Query) {
+    let reply = match read_chunk(directory, query) {
+        Ok((chunk, offset, size)) => {
+            let attachment = json!({
+                "offset": offset,
+                "length": chunk.len(),
+                "size": size,
+            });
+            query
+                .reply(query.key_expr().clone(), chunk)
+                .encoding(Encoding::APPLICATION_OCTET_STREAM)
+                .attachment(attachment.to_string())
+                .await
+        }
+        Err(error) => {
+            debug!(%error, "Failed to read recording chunk");
+            query.reply_err(error.to_string()).await
+        }
+    };
+
+    if let Err(error) = reply {
+        warn!(%error, "Failed to reply to file query");
+    }
+}
+
+/// Reads the chunk of the recording requested by the query, with its offset and the file size
+fn read_chunk(directory: &Path, query: &Query) -> Result<(Vec<u8>, u64, u64)> {
+    let name = query
+        .key_expr()
+        .as_str()
+        .strip_prefix(format!("{}/{FILES_TOPIC}/", cli::instance_prefix()).as_str())
+        .ok_or_else(|| anyhow!("Missing file name"))?;
+    let path = catalog::recording_path(directory, name)?;
+
+    let parameter = |key: &str, default: u64| {
+        query
+            .parameters()
+            .get(key)
+            .map_or(Ok(default), |value| value.parse::<u64>())
+            .with_context(|| format!("Invalid {key}"))
+    };
+    let offset = parameter("offset", 0)?;
+    let length = parameter("length", DEFAULT_CHUNK)?.min(MAX_CHUNK);
+
+    let mut file = std::fs::File::open(&path).with_context(|| format!("Failed to open {name}"))?;
+    let size = file.metadata().context("Failed to read file size")?.len();
+    file.seek(SeekFrom::Start(offset.min(size)))
+        .context("Failed to seek file")?;
+
+    let mut chunk = Vec::with_capacity(length.min(size.saturating_sub(offset)) as usize);
+    file.take(length)
+        .read_to_end(&mut chunk)
+        .context("Failed to read file")?;
+    Ok((chunk, offset.min(size), size))
+}
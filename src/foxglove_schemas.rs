@@ -0,0 +1,103 @@
+//! JSON schemas of Foxglove well-known message types, for more information:
+//! https://docs.foxglove.dev/docs/visualization/message-schemas/introduction
+
+use serde_json::{Value, json};
+
+pub const LOG: &str = "foxglove.Log";
+pub const LOCATION_FIX: &str = "foxglove.LocationFix";
+
+/// foxglove.Log levels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum LogLevel {
+    Unknown = 0,
+    Debug = 1,
+    Info = 2,
+    Warning = 3,
+    Error = 4,
+    Fatal = 5,
+}
+
+fn time_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "sec": { "type": "integer", "minimum": 0 },
+            "nsec": { "type": "integer", "minimum": 0, "maximum": 999_999_999 },
+        },
+    })
+}
+
+/// Converts nanoseconds since the epoch into a foxglove time object
+pub fn time(nanoseconds: u64) -> Value {
+    json!({
+        "sec": nanoseconds / 1_000_000_000,
+        "nsec": nanoseconds % 1_000_000_000,
+    })
+}
+
+pub fn log_schema() -> Value {
+    json!({
+        "title": LOG,
+        "type": "object",
+        "properties": {
+            "timestamp": time_schema(),
+            "level": { "type": "integer", "enum": [0, 1, 2, 3, 4, 5] },
+            "message": { "type": "string" },
+            "name": { "type": "string" },
+            "file": { "type": "string" },
+            "line": { "type": "integer", "minimum": 0 },
+        },
+    })
+}
+
+pub fn log(timestamp: u64, level: LogLevel, name: &str, message: &str) -> Value {
+    json!({
+        "timestamp": time(timestamp),
+        "level": level as u8,
+        "message": message,
+        "name": name,
+        "file": "",
+        "line": 0,
+    })
+}
+
+pub fn location_fix_schema() -> Value {
+    json!({
+        "title": LOCATION_FIX,
+        "type": "object",
+        "properties": {
+            "timestamp": time_schema(),
+            "frame_id": { "type": "string" },
+            "latitude": { "type": "number" },
+            "longitude": { "type": "number" },
+            "altitude": { "type": "number" },
+            "position_covariance": {
+                "type": "array",
+                "items": { "type": "number" },
+                "minItems": 9,
+                "maxItems": 9,
+            },
+            "position_covariance_type": { "type": "integer", "enum": [0, 1, 2, 3] },
+        },
+    })
+}
+
+/// Creates a foxglove.LocationFix with an unknown covariance
+pub fn location_fix(
+    timestamp: u64,
+    frame_id: &str,
+    latitude: f64,
+    longitude: f64,
+    altitude: f64,
+) -> Value {
+    json!({
+        "timestamp": time(timestamp),
+        "frame_id": frame_id,
+        "latitude": latitude,
+        "longitude": longitude,
+        "altitude": altitude,
+        "position_covariance": vec![0.0; 9],
+        "position_covariance_type": 0,
+    })
+}
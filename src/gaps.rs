@@ -0,0 +1,86 @@
+use std::{collections::HashMap, time::Instant};
+
+use serde_json::{Value, json};
+
+/// Channel holding the detected gaps, qualifying the data quality of each topic
+pub const GAPS_TOPIC: &str = "recorder/gaps";
+/// Inter-arrival time, relative to the average one, above which a gap is reported
+const GAP_FACTOR: f64 = 5.0;
+/// Samples needed before the average inter-arrival time is trusted
+const WARMUP_SAMPLES: u64 = 10;
+/// Weight of a new inter-arrival time in the moving average
+const SMOOTHING: f64 = 0.1;
+
+struct TopicArrival {
+    last_arrival: Instant,
+    mean_interval: f64,
+    samples: u64,
+    last_sequence: Option<u32>,
+}
+
+/// Tracks the inter-arrival times and sequence numbers of every topic
+pub struct GapDetector {
+    topics: HashMap<String, TopicArrival>,
+}
+
+impl GapDetector {
+    pub fn new() -> Self {
+        Self {
+            topics: HashMap::new(),
+        }
+    }
+
+    /// Accounts a sample, returning the gaps it reveals: the rate dropping well below the usual
+    /// one, and sequence numbers skipped by the publisher
+    pub fn update(&mut self, topic: &str, sequence: Option<u32>) -> Vec<Value> {
+        let now = Instant::now();
+        let Some(arrival) = self.topics.get_mut(topic) else {
+            self.topics.insert(
+                topic.to_owned(),
+                TopicArrival {
+                    last_arrival: now,
+                    mean_interval: 0.0,
+                    samples: 1,
+                    last_sequence: sequence,
+                },
+            );
+            return Vec::new();
+        };
+
+        let mut gaps = Vec::new();
+        let interval = now.duration_since(arrival.last_arrival).as_secs_f64();
+        if arrival.samples > WARMUP_SAMPLES && interval > arrival.mean_interval * GAP_FACTOR {
+            gaps.push(json!({
+                "topic": topic,
+                "kind": "interval",
+                "gap_s": interval,
+                "expected_interval_s": arrival.mean_interval,
+                "missing": (interval / arrival.mean_interval.max(f64::EPSILON)) as u64,
+            }));
+        }
+
+        if let Some((last, sequence)) = arrival.last_sequence.zip(sequence) {
+            let missing = sequence.wrapping_sub(last).wrapping_sub(1);
+            // Small backward jumps are reordering or a restarted publisher, not gaps
+            if missing > 0 && missing < u32::MAX / 2 {
+                gaps.push(json!({
+                    "topic": topic,
+                    "kind": "sequence",
+                    "gap_s": interval,
+                    "expected_interval_s": arrival.mean_interval,
+                    "missing": missing,
+                }));
+            }
+        }
+
+        arrival.mean_interval = if arrival.samples == 1 {
+            interval
+        } else {
+            arrival.mean_interval + SMOOTHING * (interval - arrival.mean_interval)
+        };
+        arrival.samples += 1;
+        arrival.last_arrival = now;
+        arrival.last_sequence = sequence.or(arrival.last_sequence);
+        gaps
+    }
+}
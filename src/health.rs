@@ -0,0 +1,28 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow};
+use serde_json::{Value, json};
+
+use crate::cli;
+
+/// Key of the queryable reporting the health of the recorder, under the instance prefix
+const HEALTH_TOPIC: &str = "health";
+/// Age of the last sample above which the recorder is considered stalled, the autopilot
+/// HEARTBEAT alone arrives every second
+const MAX_SAMPLE_AGE: Duration = Duration::from_secs(30);
+
+/// Key of the health queryable, e.g: `recorder/health`
+pub fn key() -> String {
+    format!("{}/{HEALTH_TOPIC}", cli::instance_prefix())
+}
+
+/// Health of the recorder, an error when no sample was received recently
+pub fn report(last_sample: Option<Instant>) -> Result<Value> {
+    let age = last_sample
+        .map(|last_sample| last_sample.elapsed())
+        .ok_or_else(|| anyhow!("No sample received"))?;
+    if age > MAX_SAMPLE_AGE {
+        return Err(anyhow!("No sample received for {} seconds", age.as_secs()));
+    }
+    Ok(json!({ "last_sample_age_s": age.as_secs_f64() }))
+}
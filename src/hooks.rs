@@ -0,0 +1,85 @@
+use std::{path::Path, process::Stdio};
+
+use serde::Deserialize;
+use tracing::*;
+
+use crate::config;
+
+/// Commands run on the recording lifecycle, e.g: `on_finish = { command = "/usr/bin/upload.sh" }`.
+/// They run in the background with `RECORDER_EVENT` and the variables of the event in their
+/// environment, their failures are only logged
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hooks {
+    /// Run when a recording file is created, with `RECORDER_FILE`, `RECORDER_SESSION_ID` and
+    /// `RECORDER_PART`
+    pub on_start: Option<Hook>,
+    /// Run when a recording file is finished and indexed, with `RECORDER_FILE`
+    pub on_finish: Option<Hook>,
+    /// Run on the errors stopping or degrading the recording, with `RECORDER_ERROR`
+    pub on_error: Option<Hook>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Hook {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+pub fn on_start(file: &Path, session_id: &str, part: u32) {
+    run(
+        config::get().hooks.on_start.as_ref(),
+        "start",
+        &[
+            ("RECORDER_FILE", &file.to_string_lossy()),
+            ("RECORDER_SESSION_ID", session_id),
+            ("RECORDER_PART", &part.to_string()),
+        ],
+    );
+}
+
+pub fn on_finish(file: &Path) {
+    run(
+        config::get().hooks.on_finish.as_ref(),
+        "finish",
+        &[("RECORDER_FILE", &file.to_string_lossy())],
+    );
+}
+
+pub fn on_error(message: &str) {
+    run(
+        config::get().hooks.on_error.as_ref(),
+        "error",
+        &[("RECORDER_ERROR", message)],
+    );
+}
+
+fn run(hook: Option<&Hook>, event: &str, variables: &[(&str, &str)]) {
+    let Some(hook) = hook else {
+        return;
+    };
+    let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+        warn!(command = %hook.command, event, "No runtime to run the hook");
+        return;
+    };
+
+    let mut command = tokio::process::Command::new(&hook.command);
+    command
+        .args(&hook.args)
+        .env("RECORDER_EVENT", event)
+        .envs(variables.iter().copied())
+        .stdin(Stdio::null());
+    let name = hook.command.clone();
+    let event = event.to_owned();
+    runtime.spawn(async move {
+        match command.status().await {
+            Ok(status) if status.success() => {
+                debug!(command = %name, event = %event, "Hook succeeded")
+            }
+            Ok(status) => warn!(%status, command = %name, event = %event, "Hook failed"),
+            Err(error) => warn!(%error, command = %name, event = %event, "Failed to run hook"),
+        }
+    });
+}
@@ -0,0 +1,41 @@
+use std::{fs::File, io::Write, path::Path, sync::Mutex};
+
+use once_cell::sync::OnceCell;
+use serde_json::{Value, json};
+use tracing::*;
+
+/// Append-only audit trail in the recorder directory, independent of the recordings
+const JOURNAL_FILE: &str = "journal.log";
+
+static JOURNAL: OnceCell<Mutex<File>> = OnceCell::new();
+
+/// Opens the journal of the recorder directory, events are not journaled when it fails
+#[instrument(level = "debug")]
+pub fn init(directory: &Path) {
+    let path = directory.join(JOURNAL_FILE);
+    match File::options().create(true).append(true).open(&path) {
+        Ok(file) => {
+            let _ = JOURNAL.set(Mutex::new(file));
+        }
+        Err(error) => warn!(%error, path = %path.display(), "Failed to open the journal"),
+    }
+}
+
+/// Appends a lifecycle event, e.g: start, stop, rotate, delete or error.
+/// Each line is synced before returning, a crash never loses an event already journaled
+pub fn record(event: &str, details: Value) {
+    let Some(journal) = JOURNAL.get() else {
+        return;
+    };
+    let line = json!({
+        "time": chrono::Utc::now().to_rfc3339(),
+        "event": event,
+        "details": details,
+    });
+    let mut file = journal
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Err(error) = writeln!(file, "{line}").and_then(|()| file.sync_data()) {
+        warn!(%error, event, "Failed to journal event");
+    }
+}
@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::trigger::Condition;
+
+/// Flush interval once a leak is detected, bounding what is lost if the electronics flood
+const LEAK_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Leak sensor condition, e.g: `/value == true` on `blueos/leak_sensor`
+pub struct LeakDetector {
+    condition: Option<Condition>,
+    active: bool,
+    /// Latched on the first detection, the cadence stays accelerated until the recorder stops
+    detected: bool,
+}
+
+impl LeakDetector {
+    pub fn new(condition: Option<Condition>) -> Self {
+        Self {
+            condition,
+            active: false,
+            detected: false,
+        }
+    }
+
+    /// Evaluates the condition on a sample, returns true when a leak starts being reported
+    pub fn update(&mut self, topic: &str, payload: &[u8]) -> bool {
+        let Some(condition) = &self.condition else {
+            return false;
+        };
+        if !condition.matches(topic) {
+            return false;
+        }
+        let Some(active) = std::str::from_utf8(payload)
+            .ok()
+            .and_then(|string| serde_json5::from_str::<Value>(string).ok())
+            .and_then(|value| condition.evaluate(&value))
+        else {
+            return false;
+        };
+
+        let started = active && !self.active;
+        self.active = active;
+        self.detected |= active;
+        started
+    }
+
+    /// Interval between two flushes, `default` until a leak is detected
+    pub fn flush_interval(&self, default: Duration) -> Duration {
+        if self.detected {
+            LEAK_FLUSH_INTERVAL
+        } else {
+            default
+        }
+    }
+}
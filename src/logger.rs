@@ -0,0 +1,59 @@
+use std::sync::Mutex;
+
+use anyhow::Result;
+use tracing_subscriber::{
+    EnvFilter, Layer, Registry, fmt::MakeWriter, layer::SubscriberExt, util::SubscriberInitExt,
+};
+
+use crate::{
+    cli::{self, LogFormat},
+    log_file::RotatingFile,
+};
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// Initializes the tracing subscriber, should be done inside main after cli::init()
+pub fn init() -> Result<()> {
+    let default_level = if cli::is_verbose() { "debug" } else { "info" };
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default_level));
+
+    // Stdout is left to the recording when it is piped
+    let console = if cli::is_stdout_output() {
+        layer(cli::log_format(), std::io::stderr, true)
+    } else {
+        layer(cli::log_format(), std::io::stdout, true)
+    };
+    let mut layers = vec![console];
+    if let Some(path) = cli::log_file() {
+        let file = RotatingFile::open(&path, cli::log_file_max_size(), cli::log_file_max_files())?;
+        layers.push(layer(cli::log_format(), Mutex::new(file), false));
+    }
+
+    tracing_subscriber::registry()
+        .with(layers)
+        .with(filter)
+        .init();
+    Ok(())
+}
+
+fn layer<W>(format: LogFormat, writer: W, ansi: bool) -> BoxedLayer
+where
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_file(true)
+        .with_line_number(true)
+        .with_ansi(ansi)
+        .with_writer(writer);
+
+    match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .boxed(),
+    }
+}
@@ -0,0 +1,93 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// Flush interval while recording normally
+const FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+/// Flush interval while in low power mode
+const LOW_POWER_FLUSH_INTERVAL: Duration = Duration::from_secs(300);
+/// Minimum period between two samples of the same topic while in low power mode
+const LOW_POWER_MIN_PERIOD: Duration = Duration::from_secs(1);
+/// Battery percentage above the threshold needed to leave low power mode, avoiding toggling
+const BATTERY_HYSTERESIS_PERCENT: u8 = 5;
+
+/// Low power mode, trading log density for endurance when the battery is low
+pub struct LowPower {
+    active: bool,
+    battery_threshold: Option<u8>,
+    last_recorded: HashMap<String, Instant>,
+}
+
+impl LowPower {
+    /// `battery_threshold` is the remaining battery percentage enabling the mode, `None` leaves
+    /// it to the control key only
+    pub fn new(battery_threshold: Option<u8>) -> Self {
+        Self {
+            active: false,
+            battery_threshold,
+            last_recorded: HashMap::new(),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Enables or disables the mode, returns true if it changed
+    pub fn set(&mut self, active: bool) -> bool {
+        if self.active == active {
+            return false;
+        }
+
+        self.active = active;
+        self.last_recorded.clear();
+        true
+    }
+
+    /// Updates the mode from the remaining battery percentage, returns the new state if it changed
+    pub fn update_battery(&mut self, remaining_percent: u8) -> Option<bool> {
+        let threshold = self.battery_threshold?;
+        let active = if self.active {
+            remaining_percent <= threshold.saturating_add(BATTERY_HYSTERESIS_PERCENT)
+        } else {
+            remaining_percent <= threshold
+        };
+        self.set(active).then_some(active)
+    }
+
+    /// Minimum period between two samples of a topic, `None` when not downsampling
+    pub fn min_period(&self) -> Option<Duration> {
+        self.active.then_some(LOW_POWER_MIN_PERIOD)
+    }
+
+    /// Returns false for samples dropped by the mode: camera topics and downsampled topics
+    pub fn should_record(&mut self, topic: &str) -> bool {
+        if !self.active {
+            return true;
+        }
+
+        if topic.starts_with("video/") {
+            return false;
+        }
+
+        let now = Instant::now();
+        if let Some(last_recorded) = self.last_recorded.get_mut(topic) {
+            if now.duration_since(*last_recorded) < LOW_POWER_MIN_PERIOD {
+                return false;
+            }
+            *last_recorded = now;
+        } else {
+            self.last_recorded.insert(topic.to_owned(), now);
+        }
+        true
+    }
+
+    pub fn flush_interval(&self) -> Duration {
+        if self.active {
+            LOW_POWER_FLUSH_INTERVAL
+        } else {
+            FLUSH_INTERVAL
+        }
+    }
+}
@@ -1,24 +1,66 @@
+mod backfill;
+mod catalog;
+mod cdr;
 mod channel_descriptor;
 mod cli;
+mod config;
+mod control;
+mod counters;
+mod deletes;
+mod dry_run;
+mod events;
+mod failsafe;
+mod files;
+mod foxglove_schemas;
+mod gaps;
+mod health;
+mod hooks;
+mod journal;
+mod leak;
+mod log_file;
+mod logger;
+mod low_power;
 mod mavlink;
 mod mcap;
+mod mirror;
+mod priority;
+mod queue_alarm;
+mod quota;
+mod readme;
+mod recording_session;
+mod ros_names;
+mod rotation;
+mod schema_cache;
+mod schema_fetch;
 mod service;
+mod sink;
+mod sources;
+mod stats;
+mod storage;
+mod stream;
+mod subscriber;
+mod systemd;
+mod time_sync;
+mod tools;
+mod topics;
+mod transform;
+mod trigger;
+mod trigger_history;
+mod warnings;
 use service::Service;
 
 use tokio_graceful_shutdown::{SubsystemBuilder, SubsystemHandle, Toplevel};
-use tracing_subscriber::EnvFilter;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     cli::init();
-    let default_level = if cli::is_verbose() { "debug" } else { "info" };
-    tracing_subscriber::fmt()
-        .with_file(true)
-        .with_line_number(true)
-        .with_env_filter(
-            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default_level)),
-        )
-        .init();
+    logger::init()?;
+
+    config::init(cli::config_path().as_deref())?;
+
+    if let Some(command) = cli::command() {
+        return tools::run(command).await;
+    }
 
     Toplevel::new(async |subsystem: &mut SubsystemHandle| {
         subsystem.start(SubsystemBuilder::new("Recorder", recorder));
@@ -29,20 +71,40 @@ async fn main() -> anyhow::Result<()> {
     .map_err(Into::into)
 }
 
-async fn recorder(subsystem: &mut SubsystemHandle) -> anyhow::Result<()> {
+/// Zenoh configuration of the recorder session, a client of the local router by default.
+/// `wait_for_router` keeps the service waiting for the endpoints, the one-shot tools fail instead
+fn zenoh_config(wait_for_router: bool) -> zenoh::Config {
     let mut config = zenoh::Config::default();
     config
         .insert_json5("mode", r#""client""#)
         .expect("Failed to insert client mode");
     config
-        .insert_json5("connect/endpoints", r#"["tcp/127.0.0.1:7447"]"#)
-        .expect("Failed to insert connection endpoint");
+        .insert_json5(
+            "connect/endpoints",
+            &serde_json::to_string(&cli::connect()).expect("Failed to serialize endpoints"),
+        )
+        .expect("Failed to insert connection endpoints");
+    // Waits for any of the endpoints instead of exiting, and keeps retrying them once connected
+    if wait_for_router {
+        config
+            .insert_json5("connect/timeout_ms", "-1")
+            .expect("Failed to insert connection timeout");
+        config
+            .insert_json5("connect/exit_on_failure", "false")
+            .expect("Failed to insert connection failure policy");
+    }
     config
         .insert_json5("adminspace", r#"{"enabled": true}"#)
         .expect("Failed to insert adminspace");
     config
-        .insert_json5("metadata", r#"{"name": "blueos-recorder"}"#)
+        .insert_json5(
+            "metadata",
+            &serde_json::json!({ "name": cli::instance_name() }).to_string(),
+        )
         .expect("Failed to insert metadata");
+    if cli::is_mirror() {
+        mirror::configure(&mut config);
+    }
 
     for (key, value) in cli::zkey_config() {
         config
@@ -55,7 +117,114 @@ async fn recorder(subsystem: &mut SubsystemHandle) -> anyhow::Result<()> {
             .unwrap_or_else(|error| panic!("Failed to insert {key}: {error}"));
     }
 
-    let mut service = Service::new(config, cli::recorder_path(), cli::schema_path()).await;
+    config
+}
+
+async fn recorder(subsystem: &mut SubsystemHandle) -> anyhow::Result<()> {
+    let config = zenoh_config(true);
+
+    let mut mcap_options = cli::mcap_options();
+    mcap_options
+        .sinks
+        .extend(config::get().sinks.iter().cloned());
+    for sink in &mcap_options.sinks {
+        sink.start()?;
+    }
+    if !cli::is_dry_run() {
+        journal::init(&cli::recorder_path());
+    }
+
+    let mut service = Service::new(
+        config,
+        cli::recorder_path(),
+        cli::schema_path(),
+        cli::cdr_to_json(),
+        mcap_options,
+    )
+    .await;
+
+    let rest_endpoints = config::get().rest_endpoints.clone();
+    if !rest_endpoints.is_empty() {
+        let sender = service.source_sender();
+        subsystem.start(SubsystemBuilder::new(
+            "RestPoller",
+            async move |subsystem: &mut SubsystemHandle| {
+                sources::rest::run(rest_endpoints, sender, subsystem).await
+            },
+        ));
+    }
+
+    if let Some(source) = config::get().mqtt.clone() {
+        let sender = service.source_sender();
+        subsystem.start(SubsystemBuilder::new(
+            "MqttSource",
+            async move |subsystem: &mut SubsystemHandle| {
+                sources::mqtt::run(source, sender, subsystem).await
+            },
+        ));
+    }
+
+    let commands = config::get().commands.clone();
+    if !commands.is_empty() {
+        let sender = service.source_sender();
+        subsystem.start(SubsystemBuilder::new(
+            "CommandSource",
+            async move |subsystem: &mut SubsystemHandle| {
+                sources::command::run(commands, sender, subsystem).await
+            },
+        ));
+    }
+
+    if let Some(address) = cli::mavlink_input() {
+        let sender = service.source_sender();
+        subsystem.start(SubsystemBuilder::new(
+            "MavlinkInput",
+            async move |subsystem: &mut SubsystemHandle| {
+                sources::mavlink::run(address, sender, subsystem).await
+            },
+        ));
+    }
+
+    if let Some(address) = cli::nmea_input() {
+        let sender = service.source_sender();
+        subsystem.start(SubsystemBuilder::new(
+            "NmeaInput",
+            async move |subsystem: &mut SubsystemHandle| {
+                sources::nmea::run(address, sender, subsystem).await
+            },
+        ));
+    }
+
+    if let Some(address) = cli::ping_target() {
+        let sender = service.source_sender();
+        subsystem.start(SubsystemBuilder::new(
+            "PingProbe",
+            async move |subsystem: &mut SubsystemHandle| {
+                sources::ping::run(address, cli::ping_interval(), sender, subsystem).await
+            },
+        ));
+    }
+
+    if let Some(key_expr) = cli::record_queries() {
+        let session = service.session();
+        let sender = service.source_sender();
+        subsystem.start(SubsystemBuilder::new(
+            "QueryObserver",
+            async move |subsystem: &mut SubsystemHandle| {
+                sources::queries::run(session, key_expr, sender, subsystem).await
+            },
+        ));
+    }
+
+    let session = service.session();
+    subsystem.start(SubsystemBuilder::new(
+        "FileServer",
+        async move |subsystem: &mut SubsystemHandle| {
+            files::run(session, cli::recorder_path(), subsystem).await
+        },
+    ));
+
+    systemd::notify_ready();
     service.run(subsystem).await?;
 
     Ok(())
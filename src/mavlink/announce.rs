@@ -0,0 +1,95 @@
+use std::time::Instant;
+
+use mavlink::{
+    MavHeader,
+    ardupilotmega::{MavComponent, MavMessage, MavSeverity, NAMED_VALUE_INT_DATA, STATUSTEXT_DATA},
+};
+use tracing::*;
+use zenoh::Session;
+
+use super::{RAW_MAVLINK_IN_TOPIC, encode};
+use crate::cli;
+
+/// System of the vehicle, the GCS lists the recorder among its components
+const SYSTEM_ID: u8 = 1;
+/// NAMED_VALUE_INT holding the recording state, 1 while recording
+const RECORDING_NAME: &str = "REC";
+
+/// Publishes the recorder state as MAVLink messages on the bridge input, so QGC or Cockpit show it
+/// through the existing telemetry: the recording state as the `REC` named value, and the starts,
+/// rotations and errors as STATUSTEXT
+pub struct Announcer {
+    session: Session,
+    sequence: u8,
+    start: Instant,
+}
+
+impl Announcer {
+    pub fn new(session: Session) -> Self {
+        Self {
+            session,
+            sequence: 0,
+            start: Instant::now(),
+        }
+    }
+
+    pub fn recording(&mut self, active: bool) {
+        let message = MavMessage::NAMED_VALUE_INT(NAMED_VALUE_INT_DATA {
+            time_boot_ms: self.start.elapsed().as_millis() as u32,
+            value: active.into(),
+            name: fixed::<10>(RECORDING_NAME).into(),
+        });
+        self.publish(&message);
+    }
+
+    pub fn info(&mut self, text: &str) {
+        self.text(MavSeverity::MAV_SEVERITY_INFO, text);
+    }
+
+    pub fn error(&mut self, text: &str) {
+        self.text(MavSeverity::MAV_SEVERITY_ERROR, text);
+    }
+
+    /// Texts longer than a STATUSTEXT are truncated
+    fn text(&mut self, severity: MavSeverity, text: &str) {
+        let message = MavMessage::STATUSTEXT(STATUSTEXT_DATA {
+            severity,
+            text: fixed::<50>(text).into(),
+            ..Default::default()
+        });
+        self.publish(&message);
+    }
+
+    fn publish(&mut self, message: &MavMessage) {
+        let header = MavHeader {
+            system_id: SYSTEM_ID,
+            component_id: MavComponent::MAV_COMP_ID_ONBOARD_COMPUTER as u8,
+            sequence: self.sequence,
+        };
+        self.sequence = self.sequence.wrapping_add(1);
+        let frame = encode(header, message);
+
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            warn!("No runtime to announce the recorder state");
+            return;
+        };
+        let session = self.session.clone();
+        runtime.spawn(async move {
+            if let Err(error) = session
+                .put(RAW_MAVLINK_IN_TOPIC, frame)
+                .allowed_destination(cli::own_keys_destination())
+                .await
+            {
+                warn!(%error, "Failed to announce the recorder state");
+            }
+        });
+    }
+}
+
+/// Truncates and NUL-pads a text to a MAVLink char array
+fn fixed<const N: usize>(text: &str) -> [u8; N] {
+    let mut array = [0; N];
+    let length = text.len().min(N);
+    array[..length].copy_from_slice(&text.as_bytes()[..length]);
+    array
+}
@@ -0,0 +1,129 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Name of the MCAP metadata record holding the battery statistics of the file
+pub const BATTERY_METADATA: &str = "battery";
+/// Longest time between two BATTERY_STATUS messages integrated in the energy, longer gaps are
+/// skipped rather than guessed
+const MAX_INTEGRATION_GAP: Duration = Duration::from_secs(10);
+
+/// Battery statistics of a recording file, from its BATTERY_STATUS messages
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatterySummary {
+    /// Energy drawn from the battery in watt-hours, integrated from the voltage and current
+    pub energy_wh: f64,
+    pub min_voltage: Option<f64>,
+    pub max_voltage: Option<f64>,
+    /// Current statistics in amperes
+    pub mean_current: Option<f64>,
+    pub max_current: Option<f64>,
+    #[serde(skip)]
+    current_samples: u64,
+    /// Reception time and power in watts of the last message, for the integration
+    #[serde(skip)]
+    last_power: Option<(Instant, f64)>,
+}
+
+/// Returns true for the per-field JSON topics reporting the battery state,
+/// e.g: `mavlink/1/1/BATTERY_STATUS` or `mavlink/1/1/SYS_STATUS`
+pub fn is_battery_topic(topic: &str) -> bool {
+    topic.starts_with("mavlink/")
+        && (topic.ends_with("/BATTERY_STATUS") || topic.ends_with("/SYS_STATUS"))
+}
+
+/// Remaining battery percentage of a BATTERY_STATUS or SYS_STATUS message, `None` when unknown
+pub fn remaining_percent(value: &Value) -> Option<u8> {
+    let message = value.get("message").unwrap_or(value);
+    // -1 is used when the autopilot does not estimate the remaining capacity
+    message
+        .get("battery_remaining")
+        .and_then(Value::as_i64)
+        .and_then(|remaining| u8::try_from(remaining).ok())
+}
+
+/// Returns true for the per-field JSON topics of BATTERY_STATUS, e.g: `mavlink/1/1/BATTERY_STATUS`
+pub fn is_battery_status_topic(topic: &str) -> bool {
+    topic.starts_with("mavlink/") && topic.ends_with("/BATTERY_STATUS")
+}
+
+/// Battery current in amperes of a BATTERY_STATUS or SYS_STATUS message, `None` when unknown
+pub fn current(value: &Value) -> Option<f64> {
+    let message = value.get("message").unwrap_or(value);
+    // In centiamperes, -1 when the autopilot does not measure it
+    message
+        .get("current_battery")
+        .and_then(Value::as_i64)
+        .filter(|centiamperes| *centiamperes >= 0)
+        .map(|centiamperes| centiamperes as f64 / 100.0)
+}
+
+/// Battery voltage in volts of a BATTERY_STATUS or SYS_STATUS message, `None` when unknown
+pub fn voltage(value: &Value) -> Option<f64> {
+    let message = value.get("message").unwrap_or(value);
+    // UINT16_MAX marks unknown voltages and unused cells
+    let valid = |millivolts: &Value| millivolts.as_u64().filter(|mv| *mv < u64::from(u16::MAX));
+
+    if let Some(millivolts) = message.get("voltage_battery") {
+        return valid(millivolts).map(|mv| mv as f64 / 1e3);
+    }
+
+    // Packs without cell monitoring are split across the cells, summing them gives the total
+    let millivolts: u64 = message
+        .get("voltages")?
+        .as_array()?
+        .iter()
+        .filter_map(valid)
+        .sum();
+    (millivolts > 0).then(|| millivolts as f64 / 1e3)
+}
+
+impl BatterySummary {
+    pub fn update(&mut self, voltage: Option<f64>, current: Option<f64>) {
+        if let Some(voltage) = voltage {
+            self.min_voltage = Some(self.min_voltage.map_or(voltage, |min| min.min(voltage)));
+            self.max_voltage = Some(self.max_voltage.map_or(voltage, |max| max.max(voltage)));
+        }
+        if let Some(current) = current {
+            let samples = self.current_samples as f64;
+            let mean = self.mean_current.unwrap_or_default();
+            self.mean_current = Some(mean + (current - mean) / (samples + 1.0));
+            self.max_current = Some(self.max_current.map_or(current, |max| max.max(current)));
+            self.current_samples += 1;
+        }
+
+        let Some((voltage, current)) = voltage.zip(current) else {
+            return;
+        };
+        let now = Instant::now();
+        let power = voltage * current;
+        if let Some((last, last_power)) = self.last_power {
+            let elapsed = now.duration_since(last);
+            if elapsed <= MAX_INTEGRATION_GAP {
+                self.energy_wh += (power + last_power) / 2.0 * elapsed.as_secs_f64() / 3600.0;
+            }
+        }
+        self.last_power = Some((now, power));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min_voltage.is_none() && self.current_samples == 0
+    }
+
+    pub fn metadata(&self) -> BTreeMap<String, String> {
+        [
+            ("energy_wh", Some(self.energy_wh)),
+            ("min_voltage", self.min_voltage),
+            ("max_voltage", self.max_voltage),
+            ("mean_current", self.mean_current),
+            ("max_current", self.max_current),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some((key.to_owned(), value?.to_string())))
+        .collect()
+    }
+}
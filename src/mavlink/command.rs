@@ -0,0 +1,68 @@
+use serde_json::{Value, json};
+
+/// Channel gathering every command and acknowledgement, never rate limited
+pub const COMMANDS_TOPIC: &str = "recorder/commands";
+
+/// Returns true for the per-field JSON topics of commands and their acknowledgements,
+/// e.g: `mavlink/255/190/COMMAND_LONG`
+pub fn is_command_topic(topic: &str) -> bool {
+    topic.starts_with("mavlink/")
+        && (topic.ends_with("/COMMAND_LONG")
+            || topic.ends_with("/COMMAND_INT")
+            || topic.ends_with("/COMMAND_ACK"))
+}
+
+/// Summarizes a command or acknowledgement as an event message and its details
+pub fn summary(topic: &str, value: &Value) -> Option<(String, Value)> {
+    let message = value.get("message").unwrap_or(value);
+    let kind = topic.rsplit('/').next()?;
+    let command = enum_name(message.get("command")?);
+    let source = match value.get("header") {
+        Some(header) => format!(
+            "{}/{}",
+            header.get("system_id").unwrap_or(&Value::Null),
+            header.get("component_id").unwrap_or(&Value::Null)
+        ),
+        None => topic.to_owned(),
+    };
+
+    if kind == "COMMAND_ACK" {
+        let result = message.get("result").map(enum_name).unwrap_or_default();
+        return Some((
+            format!("{command} acknowledged by {source}: {result}"),
+            json!({ "type": kind, "command": command, "result": result, "source": source }),
+        ));
+    }
+
+    let target = format!(
+        "{}/{}",
+        message.get("target_system").unwrap_or(&Value::Null),
+        message.get("target_component").unwrap_or(&Value::Null)
+    );
+    let params: Vec<&Value> = (1..=7)
+        .filter_map(|index| message.get(format!("param{index}")))
+        .collect();
+    Some((
+        format!("{command} sent by {source} to {target}"),
+        json!({
+            "type": kind,
+            "command": command,
+            "source": source,
+            "target": target,
+            "params": params,
+        }),
+    ))
+}
+
+/// Enums are serialized as `{"type": "NAME"}`, a name or their value
+fn enum_name(value: &Value) -> String {
+    match value {
+        Value::Object(object) => object
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_owned(),
+        Value::String(name) => name.clone(),
+        value => value.to_string(),
+    }
+}
@@ -0,0 +1,96 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::position::{self, Position};
+
+/// Name of the MCAP metadata record holding the dive profile of the file
+pub const DIVE_METADATA: &str = "dive";
+/// Depth range of the time-at-depth histogram buckets, in meters
+const DEPTH_BUCKET_METERS: f64 = 5.0;
+/// Longest time between two GLOBAL_POSITION_INT messages accounted in the histogram, longer
+/// gaps are skipped rather than guessed
+const MAX_SAMPLE_GAP: Duration = Duration::from_secs(10);
+/// Mean Earth radius, for the haversine distance
+const EARTH_RADIUS_METERS: f64 = 6_371_000.0;
+
+/// Dive profile of a recording file, from its GLOBAL_POSITION_INT messages
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DiveSummary {
+    /// Horizontal distance travelled in meters, while the autopilot had a position estimate
+    pub distance_m: f64,
+    pub max_depth_m: Option<f64>,
+    /// Seconds spent in each depth bucket, keyed by the bucket top in meters, e.g: `5` for 5-10 m
+    pub time_at_depth_s: BTreeMap<u32, f64>,
+    #[serde(skip)]
+    last_position: Option<Position>,
+    /// Reception time and depth of the last message, for the histogram
+    #[serde(skip)]
+    last_depth: Option<(Instant, f64)>,
+}
+
+/// Depth in meters of a GLOBAL_POSITION_INT message, from its altitude above home
+pub fn depth(value: &Value) -> Option<f64> {
+    let message = value.get("message").unwrap_or(value);
+    // In millimeters, negative underwater
+    let relative_alt = message.get("relative_alt")?.as_i64()?;
+    Some((-relative_alt as f64 / 1e3).max(0.0))
+}
+
+impl DiveSummary {
+    /// Updates the profile from a GLOBAL_POSITION_INT payload
+    pub fn update(&mut self, value: &Value) {
+        if let Some(position) = position::position(value) {
+            if let Some(last) = self.last_position {
+                self.distance_m += distance(&last, &position);
+            }
+            self.last_position = Some(position);
+        }
+
+        let Some(depth) = depth(value) else {
+            return;
+        };
+        self.max_depth_m = Some(self.max_depth_m.map_or(depth, |max| max.max(depth)));
+        let now = Instant::now();
+        if let Some((last, last_depth)) = self.last_depth {
+            let elapsed = now.duration_since(last);
+            if elapsed <= MAX_SAMPLE_GAP {
+                let bucket = (last_depth / DEPTH_BUCKET_METERS).floor() * DEPTH_BUCKET_METERS;
+                *self.time_at_depth_s.entry(bucket as u32).or_default() += elapsed.as_secs_f64();
+            }
+        }
+        self.last_depth = Some((now, depth));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.max_depth_m.is_none() && self.last_position.is_none()
+    }
+
+    pub fn metadata(&self) -> BTreeMap<String, String> {
+        let mut metadata = BTreeMap::from([("distance_m".to_owned(), self.distance_m.to_string())]);
+        if let Some(max_depth) = self.max_depth_m {
+            metadata.insert("max_depth_m".to_owned(), max_depth.to_string());
+        }
+        for (bucket, seconds) in &self.time_at_depth_s {
+            metadata.insert(
+                format!("time_at_depth_{bucket}m_s"),
+                format!("{seconds:.0}"),
+            );
+        }
+        metadata
+    }
+}
+
+/// Great-circle distance in meters
+fn distance(from: &Position, to: &Position) -> f64 {
+    let (latitude_from, latitude_to) = (from.latitude.to_radians(), to.latitude.to_radians());
+    let delta_latitude = latitude_to - latitude_from;
+    let delta_longitude = (to.longitude - from.longitude).to_radians();
+    let a = (delta_latitude / 2.0).sin().powi(2)
+        + latitude_from.cos() * latitude_to.cos() * (delta_longitude / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_METERS * a.sqrt().asin()
+}
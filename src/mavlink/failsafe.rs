@@ -0,0 +1,31 @@
+use serde_json::Value;
+
+use super::statustext;
+
+/// Returns true for the per-field JSON topics that may indicate a failsafe,
+/// e.g: `mavlink/1/1/HEARTBEAT` or `mavlink/1/1/STATUSTEXT`
+pub fn is_failsafe_topic(topic: &str) -> bool {
+    topic.starts_with("mavlink/")
+        && (topic.ends_with("/HEARTBEAT") || statustext::is_statustext_topic(topic))
+}
+
+/// Reason of the failsafe indicated by a HEARTBEAT in a critical or emergency state, or by a
+/// STATUSTEXT containing one of the `patterns` (case insensitive)
+pub fn indication(topic: &str, value: &Value, patterns: &[String]) -> Option<String> {
+    let message = value.get("message").unwrap_or(value);
+
+    if topic.ends_with("/HEARTBEAT") {
+        let status = message
+            .get("system_status")
+            .and_then(|status| status.get("type").unwrap_or(status).as_str())?;
+        return matches!(status, "MAV_STATE_CRITICAL" | "MAV_STATE_EMERGENCY")
+            .then(|| format!("System status {status}"));
+    }
+
+    let text = statustext::text(message.get("text")?)?;
+    let lowercase = text.to_lowercase();
+    patterns
+        .iter()
+        .any(|pattern| lowercase.contains(&pattern.to_lowercase()))
+        .then(|| format!("STATUSTEXT: {text}"))
+}
@@ -1,4 +1,14 @@
+pub mod announce;
+pub mod battery;
+pub mod command;
+pub mod dive;
+pub mod failsafe;
+pub mod named_value;
+pub mod position;
+pub mod sensors;
+pub mod statustext;
 pub mod vehicle;
+pub mod version;
 
 use ::mavlink::{
     MavHeader,
@@ -7,10 +17,10 @@ use ::mavlink::{
 };
 use tracing::*;
 
-use self::vehicle::VehicleArmGate;
+use self::vehicle::{ArmState, VehicleArmGate};
 
+pub const RAW_MAVLINK_TOPIC_PREFIX: &str = "mavlink_raw/";
 pub const RAW_MAVLINK_OUT_TOPIC: &str = "mavlink_raw/out";
-#[allow(unused)]
 pub const RAW_MAVLINK_IN_TOPIC: &str = "mavlink_raw/in";
 
 #[instrument(skip_all, level = "trace")]
@@ -22,7 +32,6 @@ where
     mavlink::read_any_msg(&mut reader)
 }
 
-#[allow(unused)]
 #[instrument(skip(message), level = "debug")]
 pub fn encode(header: MavHeader, message: &MavMessage) -> Vec<u8> {
     let mut bytes = Vec::new();
@@ -32,24 +41,58 @@ pub fn encode(header: MavHeader, message: &MavMessage) -> Vec<u8> {
     bytes
 }
 
+/// Handles raw MAVLink messages, returning the vehicle arm state when it changes
 #[instrument(skip_all, level = "trace")]
-pub async fn handle_mavlink_message(bytes: &[u8], vehicle_arm: &mut VehicleArmGate) {
+pub async fn handle_mavlink_message(
+    bytes: &[u8],
+    vehicle_arm: &mut VehicleArmGate,
+) -> Option<ArmState> {
     let (header, message) = match decode(bytes) {
         Ok(packet) => packet,
         Err(error) => {
             warn!("Failed decoding mavlink raw message: {error:?}");
-            return;
+            return None;
         }
     };
 
+    arm_state_change(&header, &message, vehicle_arm)
+}
+
+/// Returns the vehicle arm state when a decoded message changes it
+pub fn arm_state_change(
+    header: &MavHeader,
+    message: &MavMessage,
+    vehicle_arm: &mut VehicleArmGate,
+) -> Option<ArmState> {
     match message {
         MavMessage::HEARTBEAT(data)
             if header.component_id == MavComponent::MAV_COMP_ID_AUTOPILOT1 as u8 =>
         {
             trace!("Message decoded: {header:?}, {data:?}");
 
-            let _state = vehicle::on_heartbeat(vehicle_arm, &data);
+            vehicle::on_heartbeat(vehicle_arm, data)
+        }
+        _ => {
+            trace!("Message skipped");
+            None
         }
-        _ => trace!("Message skipped"),
     }
 }
+
+/// Converts a decoded message to its per-field JSON topic and payload, following the
+/// `mavlink/<system_id>/<component_id>/<MESSAGE_NAME>` layout of the zenoh MAVLink bridge
+pub fn to_json(header: &MavHeader, message: &MavMessage) -> (String, serde_json::Value) {
+    use ::mavlink::Message;
+
+    let topic = format!(
+        "mavlink/{}/{}/{}",
+        header.system_id,
+        header.component_id,
+        message.message_name()
+    );
+    let value = serde_json::json!({
+        "header": header,
+        "message": message,
+    });
+    (topic, value)
+}
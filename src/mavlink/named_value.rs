@@ -0,0 +1,88 @@
+use serde_json::{Value, json};
+
+use super::statustext;
+
+/// Prefix of the per-name channels, e.g: `recorder/named_value/1/1/PiTemp`
+pub const TOPIC_PREFIX: &str = "recorder/named_value";
+const NAMED_VALUE_SCHEMA: &str = "blueos_recorder.NamedValue";
+const DEBUG_VECTOR_SCHEMA: &str = "blueos_recorder.DebugVector";
+
+/// Debug value of the autopilot, recorded on a channel of its own
+pub struct NamedValue {
+    pub topic: String,
+    pub schema_name: &'static str,
+    pub schema: fn() -> Value,
+    pub value: Value,
+}
+
+/// Returns true for the per-field JSON topics of the named debug values,
+/// e.g: `mavlink/1/1/NAMED_VALUE_FLOAT` or `mavlink/1/1/DEBUG_VECT`
+pub fn is_named_value_topic(topic: &str) -> bool {
+    topic.starts_with("mavlink/")
+        && (topic.ends_with("/NAMED_VALUE_FLOAT")
+            || topic.ends_with("/NAMED_VALUE_INT")
+            || topic.ends_with("/DEBUG_VECT"))
+}
+
+/// Splits a NAMED_VALUE_FLOAT, NAMED_VALUE_INT or DEBUG_VECT message by its name, so every
+/// value plots as its own series instead of sharing the channel of the message type
+pub fn to_named_value(topic: &str, value: &Value) -> Option<NamedValue> {
+    // The message may be wrapped together with its header, as done by mavlink2rest
+    let message = value.get("message").unwrap_or(value);
+    let name: String = statustext::text(message.get("name")?)?
+        .chars()
+        .map(|char| {
+            if char.is_ascii_alphanumeric() || char == '_' || char == '-' {
+                char
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() {
+        return None;
+    }
+
+    let (source, message_name) = topic.strip_prefix("mavlink/")?.rsplit_once('/')?;
+    let topic = format!("{TOPIC_PREFIX}/{source}/{name}");
+    if message_name == "DEBUG_VECT" {
+        return Some(NamedValue {
+            topic,
+            schema_name: DEBUG_VECTOR_SCHEMA,
+            schema: debug_vector_schema,
+            value: json!({
+                "x": message.get("x")?.as_f64()?,
+                "y": message.get("y")?.as_f64()?,
+                "z": message.get("z")?.as_f64()?,
+            }),
+        });
+    }
+    Some(NamedValue {
+        topic,
+        schema_name: NAMED_VALUE_SCHEMA,
+        schema: named_value_schema,
+        value: json!({ "value": message.get("value")?.as_f64()? }),
+    })
+}
+
+fn named_value_schema() -> Value {
+    json!({
+        "title": NAMED_VALUE_SCHEMA,
+        "type": "object",
+        "properties": {
+            "value": { "type": "number" },
+        },
+    })
+}
+
+fn debug_vector_schema() -> Value {
+    json!({
+        "title": DEBUG_VECTOR_SCHEMA,
+        "type": "object",
+        "properties": {
+            "x": { "type": "number" },
+            "y": { "type": "number" },
+            "z": { "type": "number" },
+        },
+    })
+}
@@ -0,0 +1,73 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Name of the MCAP metadata record holding the launch position of the session
+pub const LAUNCH_POSITION_METADATA: &str = "launch_position";
+
+/// Messages revealing the vehicle location, or where it operates
+const LOCATION_MESSAGES: &[&str] = &[
+    "AHRS2",
+    "AHRS3",
+    "GLOBAL_POSITION_INT",
+    "GPS2_RAW",
+    "GPS_GLOBAL_ORIGIN",
+    "GPS_INPUT",
+    "GPS_RAW_INT",
+    "HOME_POSITION",
+    "MISSION_ITEM_INT",
+    "POSITION_TARGET_GLOBAL_INT",
+    "SET_GPS_GLOBAL_ORIGIN",
+    "SET_HOME_POSITION",
+];
+
+/// Global position in degrees
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Position {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl Position {
+    pub fn metadata(&self) -> BTreeMap<String, String> {
+        BTreeMap::from([
+            ("latitude".to_owned(), self.latitude.to_string()),
+            ("longitude".to_owned(), self.longitude.to_string()),
+        ])
+    }
+}
+
+/// Returns true for the per-field JSON topics reporting the vehicle position,
+/// e.g: `mavlink/1/1/GLOBAL_POSITION_INT`
+pub fn is_position_topic(topic: &str) -> bool {
+    topic.starts_with("mavlink/") && topic.ends_with("/GLOBAL_POSITION_INT")
+}
+
+/// Returns true for the per-field JSON topics of the messages holding a global position
+pub fn is_location_topic(topic: &str) -> bool {
+    topic.starts_with("mavlink/")
+        && topic
+            .rsplit('/')
+            .next()
+            .is_some_and(|name| LOCATION_MESSAGES.contains(&name))
+}
+
+/// Position of a GLOBAL_POSITION_INT message, `None` without a valid estimate
+pub fn position(value: &Value) -> Option<Position> {
+    let message = value.get("message").unwrap_or(value);
+    // Coordinates are in degE7, both are 0 until the autopilot has a position estimate
+    let latitude = message.get("lat")?.as_i64()?;
+    let longitude = message.get("lon")?.as_i64()?;
+    if (latitude, longitude) == (0, 0)
+        || latitude.abs() > 900_000_000
+        || longitude.abs() > 1_800_000_000
+    {
+        return None;
+    }
+
+    Some(Position {
+        latitude: latitude as f64 / 1e7,
+        longitude: longitude as f64 / 1e7,
+    })
+}
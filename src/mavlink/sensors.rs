@@ -0,0 +1,70 @@
+use std::collections::BTreeMap;
+
+use serde_json::{Value, json};
+
+/// Prefix of the engineering-unit channels, e.g: `recorder/sensors/1/1/SCALED_PRESSURE2`
+pub const TOPIC_PREFIX: &str = "recorder/sensors";
+pub const PRESSURE_SCHEMA: &str = "blueos_recorder.Pressure";
+
+/// Fields of the pressure channels and their units, stored in the channel metadata
+const PRESSURE_UNITS: &[(&str, &str)] = &[
+    ("pressure_hpa", "hPa"),
+    ("differential_pressure_hpa", "hPa"),
+    ("temperature_degc", "degC"),
+    ("differential_temperature_degc", "degC"),
+];
+
+/// Returns true for the per-field JSON topics of the pressure sensors,
+/// e.g: `mavlink/1/1/SCALED_PRESSURE` or `mavlink/1/1/SCALED_PRESSURE2` for the depth sensor
+pub fn is_pressure_topic(topic: &str) -> bool {
+    topic.starts_with("mavlink/")
+        && topic.rsplit('/').next().is_some_and(|name| {
+            matches!(
+                name,
+                "SCALED_PRESSURE" | "SCALED_PRESSURE2" | "SCALED_PRESSURE3"
+            )
+        })
+}
+
+/// Converts a SCALED_PRESSURE message to engineering units, returns its channel and value
+pub fn to_pressure(topic: &str, value: &Value) -> Option<(String, Value)> {
+    // The message may be wrapped together with its header, as done by mavlink2rest
+    let message = value.get("message").unwrap_or(value);
+    // Temperatures are in centidegrees, the differential one is 0 when not available
+    let centidegrees = |field: &str| message.get(field).and_then(Value::as_i64);
+    let differential_temperature = centidegrees("temperature_press_diff")
+        .filter(|centidegrees| *centidegrees != 0)
+        .map(|centidegrees| centidegrees as f64 / 100.0);
+
+    Some((
+        format!("{TOPIC_PREFIX}/{}", topic.strip_prefix("mavlink/")?),
+        json!({
+            "pressure_hpa": message.get("press_abs")?.as_f64()?,
+            "differential_pressure_hpa": message.get("press_diff")?.as_f64()?,
+            "temperature_degc": centidegrees("temperature")? as f64 / 100.0,
+            "differential_temperature_degc": differential_temperature,
+        }),
+    ))
+}
+
+pub fn pressure_schema() -> Value {
+    json!({
+        "title": PRESSURE_SCHEMA,
+        "type": "object",
+        "properties": {
+            "pressure_hpa": { "type": "number" },
+            "differential_pressure_hpa": { "type": "number" },
+            "temperature_degc": { "type": "number" },
+            "differential_temperature_degc": { "type": ["number", "null"] },
+        },
+    })
+}
+
+/// Channel metadata of the pressure channels, `unit/<field>` for every field
+pub fn pressure_metadata(source: &str) -> BTreeMap<String, String> {
+    PRESSURE_UNITS
+        .iter()
+        .map(|(field, unit)| (format!("unit/{field}"), (*unit).to_owned()))
+        .chain([("source".to_owned(), source.to_owned())])
+        .collect()
+}
@@ -0,0 +1,79 @@
+use serde_json::Value;
+
+use crate::foxglove_schemas::{self, LogLevel};
+
+/// Channel where STATUSTEXT messages are recorded as foxglove.Log
+pub const LOG_TOPIC: &str = "recorder/statustext";
+
+/// Returns true for the per-field JSON topics of STATUSTEXT messages, e.g: `mavlink/1/1/STATUSTEXT`
+pub fn is_statustext_topic(topic: &str) -> bool {
+    topic.starts_with("mavlink/") && topic.ends_with("/STATUSTEXT")
+}
+
+/// Converts a STATUSTEXT JSON payload into a foxglove.Log message
+pub fn to_log(topic: &str, value: &Value, timestamp: u64) -> Option<Value> {
+    // The message may be wrapped together with its header, as done by mavlink2rest
+    let message = value.get("message").unwrap_or(value);
+    let text = text(message.get("text")?)?;
+    let level = severity_level(message.get("severity"));
+
+    let name = match value.get("header") {
+        Some(header) => format!(
+            "{}/{}",
+            header.get("system_id").unwrap_or(&Value::Null),
+            header.get("component_id").unwrap_or(&Value::Null)
+        ),
+        None => topic.to_owned(),
+    };
+
+    Some(foxglove_schemas::log(timestamp, level, &name, &text))
+}
+
+/// Text is a null padded char array, serialized either as a string or an array of chars/bytes
+pub fn text(value: &Value) -> Option<String> {
+    let text = match value {
+        Value::String(text) => text.clone(),
+        Value::Array(chars) => chars
+            .iter()
+            .filter_map(|char| match char {
+                Value::String(char) => char.chars().next(),
+                Value::Number(byte) => byte.as_u64().and_then(|byte| char::from_u32(byte as u32)),
+                _ => None,
+            })
+            .collect(),
+        _ => return None,
+    };
+
+    Some(text.trim_end_matches('\0').to_owned())
+}
+
+/// Maps MAV_SEVERITY, serialized as `{"type": "MAV_SEVERITY_*"}`, a name or its value
+fn severity_level(value: Option<&Value>) -> LogLevel {
+    let severity = match value {
+        Some(Value::Object(object)) => object.get("type").and_then(Value::as_str),
+        Some(Value::String(name)) => Some(name.as_str()),
+        Some(Value::Number(number)) => match number.as_u64() {
+            Some(0) => Some("MAV_SEVERITY_EMERGENCY"),
+            Some(1) => Some("MAV_SEVERITY_ALERT"),
+            Some(2) => Some("MAV_SEVERITY_CRITICAL"),
+            Some(3) => Some("MAV_SEVERITY_ERROR"),
+            Some(4) => Some("MAV_SEVERITY_WARNING"),
+            Some(5) => Some("MAV_SEVERITY_NOTICE"),
+            Some(6) => Some("MAV_SEVERITY_INFO"),
+            Some(7) => Some("MAV_SEVERITY_DEBUG"),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    match severity {
+        Some("MAV_SEVERITY_EMERGENCY" | "MAV_SEVERITY_ALERT" | "MAV_SEVERITY_CRITICAL") => {
+            LogLevel::Fatal
+        }
+        Some("MAV_SEVERITY_ERROR") => LogLevel::Error,
+        Some("MAV_SEVERITY_WARNING") => LogLevel::Warning,
+        Some("MAV_SEVERITY_NOTICE" | "MAV_SEVERITY_INFO") => LogLevel::Info,
+        Some("MAV_SEVERITY_DEBUG") => LogLevel::Debug,
+        _ => LogLevel::Unknown,
+    }
+}
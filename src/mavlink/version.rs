@@ -0,0 +1,22 @@
+use serde_json::Value;
+
+/// Returns true for the per-field JSON topics of AUTOPILOT_VERSION,
+/// e.g: `mavlink/1/1/AUTOPILOT_VERSION`
+pub fn is_version_topic(topic: &str) -> bool {
+    topic.starts_with("mavlink/") && topic.ends_with("/AUTOPILOT_VERSION")
+}
+
+/// Flight software version of an AUTOPILOT_VERSION message, e.g: `4.5.1`
+pub fn firmware_version(value: &Value) -> Option<String> {
+    let message = value.get("message").unwrap_or(value);
+    // Packed as major, minor, patch and release type bytes, 0 when unknown
+    let version = message.get("flight_sw_version")?.as_u64()?;
+    (version != 0).then(|| {
+        format!(
+            "{}.{}.{}",
+            (version >> 24) & 0xFF,
+            (version >> 16) & 0xFF,
+            (version >> 8) & 0xFF
+        )
+    })
+}
@@ -1,56 +1,428 @@
 use std::{
     collections::{BTreeMap, HashMap},
     fs::File,
-    io::BufWriter,
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
 };
 
 use anyhow::{Context, Result, anyhow};
 use mcap::Writer;
+use serde_json::{Value, json};
 use tracing::*;
 
-use crate::channel_descriptor::ChannelDescriptor;
+use crate::{
+    cdr::CdrDecoder,
+    channel_descriptor::{ChannelDescriptor, MessageEncoding, SchemaEncoding},
+    ros_names,
+    sink::{self, FanOut, Sink, SinkConfig},
+    stream::Tee,
+};
+
+/// Channel holding the last message of every JSON and CDR channel, written when the file is
+/// finished
+pub const LATEST_TOPIC: &str = "recorder/latest";
+const LATEST_SCHEMA_NAME: &str = "recorder.LatestValue";
+/// Largest payload kept as a latest value, bigger messages are images, point clouds and alike
+const MAX_LATEST_VALUE_SIZE: usize = 16 * 1024;
 
 pub struct Mcap {
-    writer: Option<Writer<BufWriter<File>>>,
+    writer: Option<Writer<BufWriter<FanOut>>>,
+    /// Handles on the recording file and its copies, used to make the written data durable
+    files: Vec<File>,
+    path: Option<PathBuf>,
     channel: HashMap<String, Channel>,
+    /// Registered channels, carried to the next file of the recording
+    descriptors: Vec<ChannelDescriptor>,
+    profile: &'static str,
+    batch: Batch,
+    /// Highest log time written, later messages are clamped to it
+    last_log_time: u64,
+    /// Messages whose log time was clamped since the last report
+    clamped_log_times: u64,
+    /// The recording file is one of equal destinations, its sync failures are tolerated
+    redundant: bool,
+    /// Channels are written under their ROS 2 names instead of their zenoh keys
+    ros_topic_names: bool,
+    /// Uncompressed size of the messages written in the file
+    message_bytes: u64,
+    /// Path of the ros2msg schemas, decoding the CDR channels for the latest values
+    schema_path: Option<PathBuf>,
+}
+
+/// Messages waiting to be handed to the writer together
+struct Batch {
+    messages: Vec<(mcap::records::MessageHeader, Vec<u8>)>,
+    since: Option<Instant>,
+    max_messages: usize,
+    max_age: Option<Duration>,
 }
 
 pub struct Channel {
     channel_id: u16,
     sequence: u32,
+    message_encoding: MessageEncoding,
+    schema_name: String,
+    /// Whether the last message is kept, only for the JSON and small CDR channels
+    keeps_last_message: bool,
+    /// Log time and payload of the last message
+    last_message: Option<(u64, Vec<u8>)>,
+}
+
+/// MCAP header profile, for more information: https://mcap.dev/spec/registry#well-known-profiles
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Profile {
+    /// `ros2` when all the known channels are CDR, empty otherwise
+    #[default]
+    Auto,
+    Ros2,
+    None,
+}
+
+#[derive(Debug, Clone)]
+pub struct McapOptions {
+    pub profile: Profile,
+    /// Target uncompressed chunk size in bytes, `None` keeps the mcap crate default
+    pub chunk_size: Option<u64>,
+    /// zstd level of the chunks, `None` keeps the mcap crate default
+    pub compression_level: Option<u32>,
+    /// Groups messages in compressed and indexed chunks
+    pub use_chunks: bool,
+    /// Writes message index records after each chunk, required for random-access playback
+    pub emit_message_indexes: bool,
+    /// Buffers chunks in memory instead of seeking back to patch their headers
+    pub disable_seeking: bool,
+    /// Computes the CRC of each chunk, helps detecting storage corruption
+    pub chunk_crcs: bool,
+    /// Computes the CRC of the data section, written in the footer
+    pub data_section_crc: bool,
+    /// Capacity of the file buffer in bytes, `None` keeps the std default
+    pub buffer_size: Option<usize>,
+    /// Number of messages coalesced before hitting the writer, 1 bounds the batches by their age
+    /// only, or disables batching without `batch_interval`
+    pub batch_messages: usize,
+    /// Maximum age of a batch before it is written, checked when new messages arrive
+    pub batch_interval: Option<Duration>,
+    /// Additional destinations of the files, e.g: a USB drive copy or a stream
+    pub sinks: Vec<SinkConfig>,
+    /// Tolerates the failure of the recording file as long as one of the sinks is still written
+    pub redundant: bool,
+    /// Writes the channels under valid ROS 2 topic names, the zenoh key is kept in the `key_expr`
+    /// channel metadata
+    pub ros_topic_names: bool,
+    /// Path of the ros2msg schemas, decoding the CDR channels for the latest values
+    pub schema_path: Option<PathBuf>,
+}
+
+impl Profile {
+    /// Resolves the header profile, `encodings` are the channels expected in the file
+    pub fn resolve(self, encodings: &[MessageEncoding]) -> &'static str {
+        match self {
+            Self::Auto
+                if !encodings.is_empty()
+                    && encodings
+                        .iter()
+                        .all(|encoding| *encoding == MessageEncoding::Cdr) =>
+            {
+                "ros2"
+            }
+            Self::Auto | Self::None => "",
+            Self::Ros2 => "ros2",
+        }
+    }
 }
 
 impl Mcap {
+    /// Creates a new MCAP file, `encodings` are the channels expected in the file and are
+    /// used to resolve the header profile, since it is written before any channel is known
     #[instrument(skip_all, fields(path = %path.display()))]
-    pub fn try_new(path: &std::path::Path) -> Result<Self> {
-        info!("Creating mcap file");
-        let file = std::fs::File::create(path).context("Failed to create MCAP file")?;
-        let writer = Writer::new(BufWriter::new(file)).context("Failed to create MCAP writer")?;
+    pub fn try_new(
+        path: &Path,
+        options: &McapOptions,
+        encodings: &[MessageEncoding],
+    ) -> Result<Self> {
+        let file = File::create(path).context("Failed to create MCAP file")?;
+        let mut files = vec![
+            file.try_clone()
+                .context("Failed to clone MCAP file handle")?,
+        ];
+        let mut sinks: Vec<Box<dyn Sink>> = vec![Box::new(file)];
+
+        for sink in &options.sinks {
+            let SinkConfig::Directory { path: directory } = sink else {
+                continue;
+            };
+            let copy_path = directory.join(path.file_name().unwrap_or_default());
+            match File::create(&copy_path).and_then(|file| Ok((file.try_clone()?, file))) {
+                Ok((sync_file, file)) => {
+                    files.push(sync_file);
+                    sinks.push(Box::new(file));
+                }
+                Err(error) => {
+                    warn!(%error, path = %copy_path.display(), "Failed to create recording copy")
+                }
+            }
+        }
+        if options.sinks.iter().any(SinkConfig::is_stream) {
+            sinks.push(Box::new(Tee::new(path)));
+        }
+
+        let mut mcap =
+            Self::with_output(FanOut::new(sinks, options.redundant), options, encodings)?;
+        mcap.files = files;
+        mcap.path = Some(path.to_owned());
+        Ok(mcap)
+    }
+
+    /// Creates a writer piping the file to stdout, seeking must be disabled
+    #[instrument(skip_all)]
+    pub fn stdout(options: &McapOptions, encodings: &[MessageEncoding]) -> Result<Self> {
+        Self::with_output(
+            FanOut::new(vec![Box::new(sink::Stdout(io::stdout()))], false),
+            options,
+            encodings,
+        )
+    }
+
+    /// Creates a writer going through the whole pipeline without writing any file
+    #[instrument(skip_all)]
+    pub fn discard(options: &McapOptions, encodings: &[MessageEncoding]) -> Result<Self> {
+        Self::with_output(FanOut::new(Vec::new(), false), options, encodings)
+    }
+
+    fn with_output(
+        output: FanOut,
+        options: &McapOptions,
+        encodings: &[MessageEncoding],
+    ) -> Result<Self> {
+        let profile = options.profile.resolve(encodings);
+        info!(profile, "Creating mcap file");
+        let mut write_options = mcap::WriteOptions::new()
+            .profile(profile)
+            .library(format!("blueos-recorder {}", env!("CARGO_PKG_VERSION")))
+            .use_chunks(options.use_chunks)
+            .emit_message_indexes(options.use_chunks && options.emit_message_indexes)
+            .disable_seeking(
+                options.disable_seeking || options.sinks.iter().any(SinkConfig::is_stream),
+            )
+            .calculate_chunk_crcs(options.chunk_crcs)
+            .calculate_data_section_crc(options.data_section_crc);
+        if let Some(chunk_size) = options.chunk_size {
+            write_options = write_options.chunk_size(Some(chunk_size));
+        }
+        if let Some(compression_level) = options.compression_level {
+            write_options = write_options.compression_level(compression_level);
+        }
+        let output = match options.buffer_size {
+            Some(capacity) => BufWriter::with_capacity(capacity, output),
+            None => BufWriter::new(output),
+        };
+        let writer = write_options
+            .create(output)
+            .context("Failed to create MCAP writer")?;
         Ok(Self {
             writer: Some(writer),
+            files: Vec::new(),
+            path: None,
             channel: HashMap::new(),
+            descriptors: Vec::new(),
+            profile,
+            batch: Batch {
+                messages: Vec::new(),
+                since: None,
+                max_messages: options.batch_messages.max(1),
+                max_age: options.batch_interval,
+            },
+            last_log_time: 0,
+            clamped_log_times: 0,
+            redundant: options.redundant,
+            ros_topic_names: options.ros_topic_names,
+            message_bytes: 0,
+            schema_path: options.schema_path.clone(),
         })
     }
 
+    /// Path of the recording file, `None` when discarding
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Uncompressed size of the messages written in the file
+    pub fn message_bytes(&self) -> u64 {
+        self.message_bytes
+    }
+
+    /// Size of the recording file on the storage, compressed chunks included
+    pub fn file_bytes(&self) -> Option<u64> {
+        let path = self.path.as_deref()?;
+        std::fs::metadata(path).map(|metadata| metadata.len()).ok()
+    }
+
+    /// Follows the recording file after it was renamed
+    pub fn set_path(&mut self, path: PathBuf) {
+        self.path = Some(path);
+    }
+
+    /// Message encodings of the registered channels
+    pub fn message_encodings(&self) -> Vec<MessageEncoding> {
+        self.channel
+            .values()
+            .map(|channel| channel.message_encoding)
+            .collect()
+    }
+
+    /// Channels registered so far
+    pub fn channel_descriptors(&self) -> &[ChannelDescriptor] {
+        &self.descriptors
+    }
+
+    /// Registers channels known from a previous file, before any of their messages
+    pub fn register_channels(&mut self, descriptors: &[ChannelDescriptor]) {
+        for desc in descriptors {
+            if let Err(error) = self.register_channel(desc.clone()) {
+                warn!(%error, topic = %desc.topic, "Failed to register known channel");
+            }
+        }
+    }
+
+    /// Writes the summary section and makes the file durable, the pending batch is written first
+    /// so a rushed finish (e.g: low battery) only has the summary left to write
     #[instrument(skip_all)]
     pub fn finish(&mut self) -> Result<()> {
+        if self.writer.is_some()
+            && let Err(error) = self.write_latest_values()
+        {
+            warn!(%error, "Failed to write the latest values");
+        }
+        self.write_batch()?;
         let Some(mut writer) = self.writer.take() else {
             return Ok(());
         };
         writer.finish().context("Failed to finish MCAP writer")?;
+        drop(writer);
+        let mut files = std::mem::take(&mut self.files).into_iter();
+        if !self.redundant
+            && let Some(file) = files.next()
+        {
+            file.sync_all().context("Failed to sync MCAP file")?;
+        }
+        // Failures of the copies are reported when writing them
+        for file in files {
+            let _ = file.sync_all();
+        }
         Ok(())
     }
 
+    /// Writes the last message of every JSON and CDR channel on the latest channel, decoded to
+    /// JSON, quick-look tools read the end state of the recording, e.g: battery or position, from
+    /// the file tail only
+    fn write_latest_values(&mut self) -> Result<()> {
+        let mut cdr_decoder = CdrDecoder::new(self.schema_path.clone());
+        let mut latest: Vec<Value> = self
+            .channel
+            .iter()
+            .filter_map(|(topic, channel)| {
+                let (log_time, payload) = channel.last_message.as_ref()?;
+                let value = match channel.message_encoding {
+                    MessageEncoding::Json => serde_json::from_slice(payload).ok()?,
+                    MessageEncoding::Cdr => cdr_decoder
+                        .decode(&channel.schema_name, payload)
+                        .inspect_err(|error| {
+                            debug!(%error, topic, "Failed to decode the latest CDR value");
+                        })
+                        .ok()?,
+                    MessageEncoding::Mavlink => return None,
+                };
+                Some(json!({ "topic": topic, "log_time": log_time, "value": value }))
+            })
+            .collect();
+        latest.sort_by(|a, b| a["topic"].as_str().cmp(&b["topic"].as_str()));
+
+        let log_time = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64;
+        for value in &latest {
+            self.write_json_with_schema(
+                LATEST_TOPIC,
+                LATEST_SCHEMA_NAME,
+                latest_schema,
+                log_time,
+                log_time,
+                value,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Flushes the written chunks to the storage, so they survive a power loss
     #[instrument(skip_all, level = "info")]
     pub fn flush(&mut self) -> Result<()> {
+        self.write_batch()?;
         let Some(writer) = self.writer.as_mut() else {
             warn!("Writer not available");
             return Ok(()); // Nothing to flush since the writer is not available
         };
         writer.flush().context("Failed to flush MCAP writer")?;
+        let mut files = self.files.iter();
+        if !self.redundant
+            && let Some(file) = files.next()
+        {
+            file.sync_data().context("Failed to sync MCAP file")?;
+        }
+        // Failures of the copies are reported when writing them
+        for file in files {
+            let _ = file.sync_data();
+        }
         Ok(())
     }
 
+    /// Writes a metadata record, e.g: offsets or summaries computed during the recording
+    #[instrument(skip(self, metadata))]
+    pub fn write_metadata(&mut self, name: &str, metadata: BTreeMap<String, String>) -> Result<()> {
+        let writer = self
+            .writer
+            .as_mut()
+            .ok_or_else(|| anyhow!("Writer not available"))?;
+
+        writer
+            .write_metadata(&mcap::records::Metadata {
+                name: name.to_owned(),
+                metadata,
+            })
+            .context("Failed to write MCAP metadata")
+    }
+
+    /// Writes an attachment, e.g: a camera snapshot
+    #[instrument(skip(self, data))]
+    pub fn write_attachment(
+        &mut self,
+        name: &str,
+        media_type: &str,
+        log_time: u64,
+        data: &[u8],
+    ) -> Result<()> {
+        let writer = self
+            .writer
+            .as_mut()
+            .ok_or_else(|| anyhow!("Writer not available"))?;
+
+        writer
+            .attach(&mcap::Attachment {
+                log_time,
+                create_time: log_time,
+                name: name.to_owned(),
+                media_type: media_type.to_owned(),
+                data: std::borrow::Cow::Borrowed(data),
+            })
+            .context("Failed to write MCAP attachment")
+    }
+
+    /// Number of messages whose log time was clamped since the last call
+    pub fn take_clamped_log_times(&mut self) -> u64 {
+        std::mem::take(&mut self.clamped_log_times)
+    }
+
     #[inline]
     pub fn has_channel(&self, topic: &str) -> bool {
         self.channel.contains_key(topic)
@@ -62,37 +434,58 @@ impl Mcap {
             return Err(anyhow!("Channel already registered"));
         }
 
+        if self.profile == "ros2" && desc.message_encoding != MessageEncoding::Cdr {
+            warn!(
+                topic = %desc.topic,
+                encoding = %desc.message_encoding,
+                "Registering a non-CDR channel in a ros2 profile file"
+            );
+        }
+
         let Some(writer) = self.writer.as_mut() else {
             return Err(anyhow!("Writer not available"));
         };
 
-        let schema_id = writer
-            .add_schema(
-                &desc.schema_name,
-                desc.schema_encoding.as_str(),
-                desc.schema_content.as_bytes(),
-            )
-            .context("Failed to add MCAP schema")?;
+        // Schema ID 0 marks a schemaless channel
+        let schema_id = match desc.schema_encoding {
+            SchemaEncoding::None => 0,
+            _ => writer
+                .add_schema(
+                    &desc.schema_name,
+                    desc.schema_encoding.as_str(),
+                    desc.schema_content.as_bytes(),
+                )
+                .context("Failed to add MCAP schema")?,
+        };
 
+        let (topic, metadata) = if self.ros_topic_names {
+            let mut metadata = desc.metadata.clone();
+            metadata
+                .entry("key_expr".to_owned())
+                .or_insert_with(|| desc.topic.clone());
+            (ros_names::to_ros_name(&desc.topic), metadata)
+        } else {
+            (desc.topic.clone(), desc.metadata.clone())
+        };
         let channel_id = writer
-            .add_channel(
-                schema_id,
-                &desc.topic,
-                desc.message_encoding.as_str(),
-                &BTreeMap::new(),
-            )
+            .add_channel(schema_id, &topic, desc.message_encoding.as_str(), &metadata)
             .context("Failed to add MCAP channel")?;
 
-        self.channel.insert(desc.topic, Channel::new(channel_id));
+        self.channel
+            .insert(desc.topic.clone(), Channel::new(channel_id, &desc));
+        self.descriptors.push(desc);
         Ok(())
     }
 
+    /// Writes a message, `sequence` is the publisher sequence number when known, otherwise the
+    /// channel counter is used
     #[instrument(skip_all)]
     pub fn write_message(
         &mut self,
         topic: &str,
         log_time: u64,
         publish_time: u64,
+        sequence: Option<u32>,
         payload: &[u8],
         new_channel: Option<ChannelDescriptor>,
     ) -> Result<()> {
@@ -113,19 +506,138 @@ impl Mcap {
             .get_mut(topic)
             .ok_or_else(|| anyhow!("Channel not registered"))?;
 
+        // Readers expect non-decreasing log times, the system clock can step back, e.g: on an
+        // NTP sync, and the sources are stamped at different places
+        let log_time = if log_time < self.last_log_time {
+            self.clamped_log_times += 1;
+            self.last_log_time
+        } else {
+            self.last_log_time = log_time;
+            log_time
+        };
+
+        if channel.keeps_last_message {
+            if payload.len() > MAX_LATEST_VALUE_SIZE {
+                channel.last_message = None;
+            } else {
+                let (last_log_time, last_payload) = channel.last_message.get_or_insert_default();
+                *last_log_time = log_time;
+                last_payload.clear();
+                last_payload.extend_from_slice(payload);
+            }
+        }
+
+        let sequence = sequence.unwrap_or(channel.sequence);
         let header = mcap::records::MessageHeader {
             channel_id: channel.channel_id,
-            sequence: channel.sequence,
+            sequence,
             log_time,
             publish_time,
         };
 
-        writer
-            .write_to_known_channel(&header, payload)
-            .context("Failed to write message to MCAP channel")?;
-        channel.sequence += 1;
+        if self.batch.max_messages == 1 && self.batch.max_age.is_none() {
+            writer
+                .write_to_known_channel(&header, payload)
+                .context("Failed to write message to MCAP channel")?;
+            channel.sequence = sequence.wrapping_add(1);
+            self.message_bytes += payload.len() as u64;
+            return Ok(());
+        }
+
+        channel.sequence = sequence.wrapping_add(1);
+        self.message_bytes += payload.len() as u64;
+        self.batch.messages.push((header, payload.to_vec()));
+        self.batch.since.get_or_insert_with(Instant::now);
+        if self.batch.is_due() {
+            self.write_batch()?;
+        }
         Ok(())
     }
+
+    /// Hands the pending batch of messages to the writer
+    #[instrument(skip_all, level = "trace")]
+    fn write_batch(&mut self) -> Result<()> {
+        if self.batch.messages.is_empty() {
+            self.batch.since = None;
+            return Ok(());
+        }
+
+        let writer = self
+            .writer
+            .as_mut()
+            .ok_or_else(|| anyhow!("Writer not available"))?;
+
+        // The messages not handed to the writer stay in the batch, retried on the next write
+        let mut written = 0;
+        let result = self
+            .batch
+            .messages
+            .iter()
+            .try_for_each(|(header, payload)| {
+                writer.write_to_known_channel(header, payload)?;
+                written += 1;
+                Ok::<_, mcap::McapError>(())
+            });
+        self.batch.messages.drain(..written);
+        if self.batch.messages.is_empty() {
+            self.batch.since = None;
+        }
+        result.context("Failed to write message to MCAP channel")
+    }
+
+    /// Writes a JSON value, registering its channel with an inferred schema when needed
+    /// Returns the size of the serialized payload
+    #[instrument(skip_all)]
+    pub fn write_json(
+        &mut self,
+        topic: &str,
+        schema_name: Option<&str>,
+        log_time: u64,
+        publish_time: u64,
+        value: &serde_json::Value,
+    ) -> Result<usize> {
+        let new_channel = if self.has_channel(topic) {
+            None
+        } else {
+            let desc = ChannelDescriptor::from_json(topic, schema_name, value)
+                .ok_or_else(|| anyhow!("JSON value is not an object"))?;
+            Some(desc)
+        };
+
+        let payload = serde_json::to_vec(value).context("Failed to serialize JSON value")?;
+        self.write_message(topic, log_time, publish_time, None, &payload, new_channel)?;
+        Ok(payload.len())
+    }
+
+    /// Writes a JSON value on a channel with a known schema, returns the size of the payload
+    #[instrument(skip_all)]
+    pub fn write_json_with_schema(
+        &mut self,
+        topic: &str,
+        schema_name: &str,
+        schema: impl FnOnce() -> serde_json::Value,
+        log_time: u64,
+        publish_time: u64,
+        value: &serde_json::Value,
+    ) -> Result<usize> {
+        let new_channel = (!self.has_channel(topic))
+            .then(|| ChannelDescriptor::with_json_schema(topic, schema_name, &schema()));
+
+        let payload = serde_json::to_vec(value).context("Failed to serialize JSON value")?;
+        self.write_message(topic, log_time, publish_time, None, &payload, new_channel)?;
+        Ok(payload.len())
+    }
+}
+
+fn latest_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "topic": { "type": "string" },
+            "log_time": { "type": "integer" },
+            "value": {},
+        },
+    })
 }
 
 impl Drop for Mcap {
@@ -137,11 +649,106 @@ impl Drop for Mcap {
     }
 }
 
+impl Batch {
+    fn is_due(&self) -> bool {
+        // With an interval, a batch of a single message only bounds the age
+        let full = self.messages.len() >= self.max_messages
+            && (self.max_messages > 1 || self.max_age.is_none());
+        full || self
+            .max_age
+            .zip(self.since)
+            .is_some_and(|(max_age, since)| since.elapsed() >= max_age)
+    }
+}
+
 impl Channel {
-    fn new(channel_id: u16) -> Self {
+    fn new(channel_id: u16, desc: &ChannelDescriptor) -> Self {
         Self {
             channel_id,
             sequence: 0,
+            message_encoding: desc.message_encoding,
+            schema_name: desc.schema_name.clone(),
+            keeps_last_message: keeps_last_message(desc),
+            last_message: None,
+        }
+    }
+}
+
+/// Images and video frames are too big to be quick-looked from the file tail
+fn keeps_last_message(desc: &ChannelDescriptor) -> bool {
+    match desc.message_encoding {
+        MessageEncoding::Json => desc.topic != LATEST_TOPIC,
+        MessageEncoding::Cdr => {
+            !desc.topic.trim_start_matches('/').starts_with("video/")
+                && !desc.schema_name.ends_with("Image")
+                && !desc.schema_name.ends_with("PointCloud2")
+        }
+        MessageEncoding::Mavlink => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::sink::Memory;
+
+    #[test]
+    fn test_fan_out() {
+        let options = McapOptions {
+            profile: Profile::None,
+            chunk_size: None,
+            compression_level: None,
+            use_chunks: true,
+            emit_message_indexes: true,
+            disable_seeking: false,
+            chunk_crcs: true,
+            data_section_crc: true,
+            buffer_size: None,
+            batch_messages: 1,
+            batch_interval: None,
+            sinks: Vec::new(),
+            redundant: false,
+            ros_topic_names: false,
+            schema_path: None,
+        };
+        let (primary, copy) = (Memory::default(), Memory::default());
+        let output = FanOut::new(
+            vec![Box::new(primary.clone()), Box::new(copy.clone())],
+            false,
+        );
+        let mut mcap = Mcap::with_output(output, &options, &[]).unwrap();
+        mcap.write_json("test", None, 1, 1, &serde_json::json!({ "value": 1 }))
+            .unwrap();
+        mcap.finish().unwrap();
+
+        let primary = primary.0.lock().unwrap().get_ref().clone();
+        let copy = copy.0.lock().unwrap().get_ref().clone();
+        assert!(primary.starts_with(mcap::MAGIC) && primary.ends_with(mcap::MAGIC));
+        assert_eq!(primary, copy);
+    }
+
+    struct Failing;
+
+    impl Sink for Failing {
+        fn write_all(&mut self, _buf: &[u8]) -> io::Result<()> {
+            Err(io::Error::from(io::ErrorKind::ReadOnlyFilesystem))
         }
+
+        fn seek(&mut self, _position: u64) -> io::Result<()> {
+            Err(io::Error::from(io::ErrorKind::ReadOnlyFilesystem))
+        }
+    }
+
+    #[test]
+    fn test_redundant_fan_out() {
+        let copy = Memory::default();
+        let mut output = FanOut::new(vec![Box::new(Failing), Box::new(copy.clone())], true);
+        output.write_all(b"data").unwrap();
+        assert_eq!(copy.0.lock().unwrap().get_ref().as_slice(), b"data");
+
+        let mut output = FanOut::new(vec![Box::new(Failing)], true);
+        assert!(output.write_all(b"data").is_err());
     }
 }
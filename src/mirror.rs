@@ -0,0 +1,47 @@
+use std::time::{Duration, Instant};
+
+/// Subscriber queue in mirror mode, absorbing the bursts of a tether recovering from a dropout
+pub const QUEUE_SIZE: usize = 65536;
+/// Period of the link checks in mirror mode
+pub const LINK_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// Lease of the zenoh link in mirror mode, the session rides through shorter tether dropouts
+/// instead of being closed and reopened
+const LINK_LEASE_MS: u64 = 30_000;
+
+/// Tunes the zenoh session for a lossy tether, the `--zkey` values are applied afterwards and
+/// take precedence
+pub fn configure(config: &mut zenoh::Config) {
+    config
+        .insert_json5("transport/link/tx/lease", &LINK_LEASE_MS.to_string())
+        .expect("Failed to insert link lease");
+}
+
+pub enum LinkChange {
+    Lost,
+    Restored { outage: Duration },
+}
+
+/// Follows the connection of the topside recorder to the vehicle router
+#[derive(Default)]
+pub struct LinkMonitor {
+    lost_since: Option<Instant>,
+}
+
+impl LinkMonitor {
+    /// Updates the link state from the routers the session is connected to, returns the change
+    pub fn update(&mut self, connected: bool) -> Option<LinkChange> {
+        match (connected, self.lost_since) {
+            (false, None) => {
+                self.lost_since = Some(Instant::now());
+                Some(LinkChange::Lost)
+            }
+            (true, Some(lost_since)) => {
+                self.lost_since = None;
+                Some(LinkChange::Restored {
+                    outage: lost_since.elapsed(),
+                })
+            }
+            _ => None,
+        }
+    }
+}
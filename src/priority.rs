@@ -0,0 +1,90 @@
+use serde::Deserialize;
+use zenoh::key_expr::OwnedKeyExpr;
+
+/// Priority class of a topic, deciding which samples are dropped first when the writer falls behind
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    /// Dropped as soon as the subscriber queue is half full, e.g: HEARTBEAT
+    Low,
+    /// Dropped when the subscriber queue is almost full
+    #[default]
+    Normal,
+    /// Never dropped, e.g: ATTITUDE or pressure
+    High,
+}
+
+/// Assigns a priority class to every topic matching `topic`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PriorityRule {
+    /// Key expression of the topics this rule applies to
+    pub topic: OwnedKeyExpr,
+    pub priority: Priority,
+}
+
+impl Priority {
+    /// Returns the priority of the first rule matching the topic, normal otherwise
+    pub fn of(rules: &[PriorityRule], topic: &str) -> Self {
+        let Ok(topic) = zenoh::key_expr::keyexpr::new(topic) else {
+            return Self::default();
+        };
+
+        rules
+            .iter()
+            .find(|rule| rule.topic.includes(topic))
+            .map(|rule| rule.priority)
+            .unwrap_or_default()
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Normal => "normal",
+            Self::High => "high",
+        }
+    }
+
+    /// Returns true if a sample of this class should be dropped with the subscriber queue
+    /// holding `depth` out of `capacity` samples
+    pub fn should_drop(self, depth: usize, capacity: Option<usize>) -> bool {
+        let Some(capacity) = capacity.filter(|capacity| *capacity > 0) else {
+            return false;
+        };
+
+        let fill = depth as f64 / capacity as f64;
+        match self {
+            Self::Low => fill >= 0.5,
+            Self::Normal => fill >= 0.9,
+            Self::High => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_priority_classes() {
+        let rules: Vec<PriorityRule> = serde_json::from_value(serde_json::json!([
+            { "topic": "mavlink/**/HEARTBEAT", "priority": "low" },
+            { "topic": "mavlink/**/ATTITUDE", "priority": "high" },
+        ]))
+        .unwrap();
+
+        let heartbeat = Priority::of(&rules, "mavlink/1/1/HEARTBEAT");
+        let attitude = Priority::of(&rules, "mavlink/1/1/ATTITUDE");
+        let other = Priority::of(&rules, "mavlink/1/1/GPS_RAW_INT");
+        assert_eq!(heartbeat, Priority::Low);
+        assert_eq!(attitude, Priority::High);
+        assert_eq!(other, Priority::Normal);
+
+        assert!(!heartbeat.should_drop(10, Some(100)));
+        assert!(heartbeat.should_drop(50, Some(100)));
+        assert!(!other.should_drop(50, Some(100)));
+        assert!(other.should_drop(95, Some(100)));
+        assert!(!attitude.should_drop(100, Some(100)));
+        assert!(!heartbeat.should_drop(100, None));
+    }
+}
@@ -0,0 +1,110 @@
+use serde_json::{Value, json};
+
+/// Change of the queue alarm, reported on the events channel
+#[derive(Debug, PartialEq)]
+pub enum QueueAlarmChange {
+    /// The subscriber queue filled above a higher watermark, in percent
+    Watermark(u8),
+    /// Samples started being dropped to shed load
+    Dropping,
+    /// The subscriber queue drained below half the lowest watermark
+    Cleared,
+}
+
+/// Follows the fill of the subscriber queue, raising an alarm on every watermark crossed upwards
+/// and on the first dropped sample, until the queue drains below half the lowest watermark
+pub struct QueueAlarm {
+    /// Fill percentages of the queue, sorted
+    watermarks: Vec<u8>,
+    /// Highest watermark crossed since the alarm was raised
+    level: Option<u8>,
+    dropping: bool,
+}
+
+impl QueueAlarm {
+    pub fn new(mut watermarks: Vec<u8>) -> Self {
+        watermarks.sort_unstable();
+        watermarks.dedup();
+        Self {
+            watermarks,
+            level: None,
+            dropping: false,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.level.is_some() || self.dropping
+    }
+
+    /// Updates the alarm from the queue depth, `capacity` is `None` when the queue is unbounded
+    pub fn update(&mut self, depth: usize, capacity: Option<usize>) -> Option<QueueAlarmChange> {
+        let capacity = capacity.filter(|capacity| *capacity > 0)?;
+        let fill = depth as f64 * 100.0 / capacity as f64;
+        let level = self
+            .watermarks
+            .iter()
+            .rev()
+            .find(|watermark| fill >= f64::from(**watermark))
+            .copied();
+
+        if let Some(level) = level
+            && self.level.is_none_or(|current| level > current)
+        {
+            self.level = Some(level);
+            return Some(QueueAlarmChange::Watermark(level));
+        }
+
+        // Clearing at half the lowest watermark keeps a queue hovering around it, or around the
+        // fill where the low priority topics are dropped, from flapping the alarm
+        let clear = f64::from(self.watermarks.first().copied().unwrap_or(100)) / 2.0;
+        if fill < clear && self.is_active() {
+            self.level = None;
+            self.dropping = false;
+            return Some(QueueAlarmChange::Cleared);
+        }
+        None
+    }
+
+    /// Counts a dropped sample, returns the change on the first one
+    pub fn record_drop(&mut self) -> Option<QueueAlarmChange> {
+        (!std::mem::replace(&mut self.dropping, true)).then_some(QueueAlarmChange::Dropping)
+    }
+
+    /// State of the alarm for the status report
+    pub fn status(&self) -> Value {
+        json!({
+            "active": self.is_active(),
+            "watermark_percent": self.level,
+            "dropping": self.dropping,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue_alarm() {
+        let mut alarm = QueueAlarm::new(vec![90, 50]);
+        assert_eq!(alarm.update(10, None), None);
+        assert_eq!(alarm.update(40, Some(100)), None);
+        assert_eq!(
+            alarm.update(60, Some(100)),
+            Some(QueueAlarmChange::Watermark(50))
+        );
+        assert_eq!(alarm.update(70, Some(100)), None);
+        assert_eq!(
+            alarm.update(95, Some(100)),
+            Some(QueueAlarmChange::Watermark(90))
+        );
+        assert_eq!(alarm.record_drop(), Some(QueueAlarmChange::Dropping));
+        assert_eq!(alarm.record_drop(), None);
+        // Draining between the watermarks keeps the alarm raised
+        assert_eq!(alarm.update(60, Some(100)), None);
+        assert_eq!(alarm.update(40, Some(100)), None);
+        assert_eq!(alarm.update(20, Some(100)), Some(QueueAlarmChange::Cleared));
+        assert!(!alarm.is_active());
+        assert_eq!(alarm.update(20, Some(100)), None);
+    }
+}
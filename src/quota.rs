@@ -0,0 +1,58 @@
+use std::time::{Duration, Instant};
+
+use serde_json::{Value, json};
+
+/// Hard limits of a recording session, e.g: a vehicle left armed on the bench overnight
+pub struct SessionQuota {
+    max_bytes: Option<u64>,
+    max_duration: Option<Duration>,
+    /// Written bytes when the session started
+    start_bytes: u64,
+    start: Instant,
+    exceeded: bool,
+}
+
+impl SessionQuota {
+    pub fn new(max_bytes: Option<u64>, max_duration: Option<Duration>) -> Self {
+        Self {
+            max_bytes,
+            max_duration,
+            start_bytes: 0,
+            start: Instant::now(),
+            exceeded: false,
+        }
+    }
+
+    /// Starts accounting a new session, `total_bytes` being the bytes written so far
+    pub fn reset(&mut self, total_bytes: u64) {
+        self.start_bytes = total_bytes;
+        self.start = Instant::now();
+        self.exceeded = false;
+    }
+
+    /// Returns the details of the exceeded limits, only once per session
+    pub fn check(&mut self, total_bytes: u64) -> Option<Value> {
+        if self.exceeded {
+            return None;
+        }
+
+        let bytes = total_bytes - self.start_bytes;
+        let duration = self.start.elapsed();
+        self.exceeded = self.max_bytes.is_some_and(|max_bytes| bytes >= max_bytes)
+            || self
+                .max_duration
+                .is_some_and(|max_duration| duration >= max_duration);
+        self.exceeded.then(|| {
+            json!({
+                "bytes": bytes,
+                "duration_s": duration.as_secs(),
+                "max_bytes": self.max_bytes,
+                "max_duration_s": self.max_duration.map(|max_duration| max_duration.as_secs()),
+            })
+        })
+    }
+
+    pub fn is_exceeded(&self) -> bool {
+        self.exceeded
+    }
+}
@@ -0,0 +1,94 @@
+use std::{collections::BTreeMap, fmt::Write, path::Path};
+
+use crate::{channel_descriptor::ChannelDescriptor, cli};
+
+/// Name of the attachment describing the recording, readable without any MCAP tool, e.g: when
+/// the file is forwarded by email
+pub const README_NAME: &str = "README.txt";
+
+/// Renders the README of a recording file: vehicle, firmware versions, recorder configuration
+/// and topic inventory. `firmware` maps the AUTOPILOT_VERSION topics to their versions
+pub fn render(
+    path: Option<&Path>,
+    descriptors: &[ChannelDescriptor],
+    firmware: &BTreeMap<String, String>,
+) -> String {
+    let mut readme = String::new();
+    let file = path
+        .and_then(Path::file_name)
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let _ = writeln!(readme, "BlueOS recording {file}\n");
+    let _ = writeln!(
+        readme,
+        "Vehicle: {}",
+        cli::vehicle().as_deref().unwrap_or("unknown")
+    );
+    let _ = writeln!(
+        readme,
+        "Recorder: blueos-recorder {} ({})",
+        env!("CARGO_PKG_VERSION"),
+        cli::instance_prefix()
+    );
+    let _ = writeln!(readme, "Command line: {}", command_line());
+
+    readme.push_str("\nFirmware:\n");
+    if firmware.is_empty() {
+        readme.push_str("  unknown, no AUTOPILOT_VERSION received\n");
+    }
+    for (topic, version) in firmware {
+        let _ = writeln!(readme, "  {topic}: {version}");
+    }
+
+    let _ = writeln!(readme, "\nTopics ({}):", descriptors.len());
+    for descriptor in descriptors {
+        let _ = writeln!(
+            readme,
+            "  {} [{}, {}]",
+            descriptor.topic,
+            descriptor.message_encoding.as_str(),
+            if descriptor.schema_name.is_empty() {
+                "no schema"
+            } else {
+                &descriptor.schema_name
+            }
+        );
+    }
+
+    readme.push_str(
+        "\nThe file is an MCAP recording, open it with Foxglove or the mcap CLI, \
+         see https://mcap.dev\n",
+    );
+    readme
+}
+
+/// Arguments of the recorder, hiding the `--zkey` values since they may hold credentials, e.g:
+/// the transport authentication
+fn command_line() -> String {
+    let mut in_zkey = false;
+    let arguments: Vec<_> = std::env::args()
+        .skip(1)
+        .map(|argument| {
+            if let Some(pair) = argument.strip_prefix("--zkey=") {
+                in_zkey = false;
+                return format!("--zkey={}", redact_zkey(pair));
+            }
+            if argument.starts_with('-') {
+                in_zkey = argument == "--zkey";
+                return argument;
+            }
+            // --zkey takes several values
+            if in_zkey {
+                redact_zkey(&argument)
+            } else {
+                argument
+            }
+        })
+        .collect();
+    arguments.join(" ")
+}
+
+fn redact_zkey(pair: &str) -> String {
+    let key = pair.split_once('=').map_or(pair, |(key, _)| key);
+    format!("{key}=<redacted>")
+}
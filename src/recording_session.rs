@@ -0,0 +1,143 @@
+use std::{collections::BTreeMap, time::Instant};
+
+use chrono::{DateTime, FixedOffset, Utc};
+use uuid::Uuid;
+
+use crate::mavlink::position::Position;
+
+/// Name of the MCAP metadata record linking the segments of a session
+pub const SESSION_METADATA: &str = "session";
+/// Earliest plausible time (2024-01-01), boards without RTC start at the UNIX epoch until synced
+const MIN_SANE_TIMESTAMP: i64 = 1_704_067_200;
+
+/// Time zone of the timestamp in the file names, e.g: the local time the operators sort the files
+/// by on deck, the metadata and manifests always hold UTC times
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilenameTimezone {
+    Utc,
+    Local,
+    Fixed(FixedOffset),
+}
+
+impl std::str::FromStr for FilenameTimezone {
+    type Err = String;
+
+    fn from_str(timezone: &str) -> Result<Self, Self::Err> {
+        match timezone {
+            "utc" => Ok(Self::Utc),
+            "local" => Ok(Self::Local),
+            offset => offset.parse().map(Self::Fixed).map_err(|_| {
+                format!("Invalid time zone {offset:?}, expected utc, local or +HH:MM")
+            }),
+        }
+    }
+}
+
+impl FilenameTimezone {
+    fn format(self, time: DateTime<Utc>, format: &str) -> String {
+        match self {
+            Self::Utc => time.format(format).to_string(),
+            Self::Local => time
+                .with_timezone(&chrono::Local)
+                .format(format)
+                .to_string(),
+            Self::Fixed(offset) => time.with_timezone(&offset).format(format).to_string(),
+        }
+    }
+}
+
+/// Returns false for the times given by a clock that was never synchronized
+pub fn is_clock_sane(time: DateTime<Utc>) -> bool {
+    time.timestamp() >= MIN_SANE_TIMESTAMP
+}
+
+/// Current time, shifted by `correction_ns` while the system clock is not synchronized, e.g:
+/// once the session start was corrected from the GPS time
+pub fn corrected_now(correction_ns: i64) -> DateTime<Utc> {
+    let now = Utc::now();
+    if is_clock_sane(now) {
+        now
+    } else {
+        now + chrono::TimeDelta::nanoseconds(correction_ns)
+    }
+}
+
+/// A recording session, e.g: from arm to disarm, possibly split in multiple files (parts)
+pub struct RecordingSession {
+    pub id: Uuid,
+    pub start: DateTime<Utc>,
+    pub part: u32,
+    /// Persisted session counter, ordering the recordings even if the clock resets between boots,
+    /// `None` for the idle periods between the recordings
+    pub number: Option<u64>,
+    /// Persisted count of the vehicle armings when the session started
+    pub arm_count: u64,
+    /// First valid vehicle position received during the session
+    pub launch_position: Option<Position>,
+    /// Sum of the start corrections, see [`corrected_now`]
+    pub clock_correction_ns: i64,
+    started: Instant,
+}
+
+impl RecordingSession {
+    pub fn new(number: Option<u64>, arm_count: u64) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            start: Utc::now(),
+            part: 1,
+            number,
+            arm_count,
+            launch_position: None,
+            clock_correction_ns: 0,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn next_part(&mut self) {
+        self.part += 1;
+    }
+
+    /// Parts of the same session share the session start timestamp, e.g:
+    /// `recorder_0042_20250101_120000_part02.mcap` or `recorder_idle_20250101_120000_part01.mcap`
+    /// for an idle period, `prefix` being the recorder instance and `timezone` the one of the
+    /// timestamp
+    pub fn filename(&self, prefix: &str, timezone: FilenameTimezone) -> String {
+        self.part_filename(prefix, timezone, self.part)
+    }
+
+    /// The timestamp is replaced by `unsynced` until the clock is sane
+    pub fn part_filename(&self, prefix: &str, timezone: FilenameTimezone, part: u32) -> String {
+        let start = if is_clock_sane(self.start) {
+            timezone.format(self.start, "%Y%m%d_%H%M%S")
+        } else {
+            "unsynced".to_owned()
+        };
+        match self.number {
+            Some(number) => format!("{prefix}_{number:04}_{start}_part{part:02}.mcap"),
+            None => format!("{prefix}_idle_{start}_part{part:02}.mcap"),
+        }
+    }
+
+    /// Moves the start to the time elapsed before `now`, once the clock is known to be right,
+    /// returns the correction in nanoseconds
+    pub fn correct_start(&mut self, now: DateTime<Utc>) -> i64 {
+        let start = now - self.started.elapsed();
+        let correction = (start - self.start).num_nanoseconds().unwrap_or_default();
+        self.start = start;
+        self.clock_correction_ns += correction;
+        correction
+    }
+
+    pub fn metadata(&self) -> BTreeMap<String, String> {
+        let mut metadata = BTreeMap::from([
+            ("session_id".to_owned(), self.id.to_string()),
+            ("session_start".to_owned(), self.start.to_rfc3339()),
+            ("part".to_owned(), self.part.to_string()),
+            ("arm_count".to_owned(), self.arm_count.to_string()),
+        ]);
+        if let Some(number) = self.number {
+            metadata.insert("session_number".to_owned(), number.to_string());
+        }
+        metadata
+    }
+}
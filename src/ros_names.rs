@@ -0,0 +1,58 @@
+/// Converts a zenoh key expression into a valid ROS 2 topic name, e.g: `mavlink/1/1/HEARTBEAT`
+/// becomes `/mavlink/_1/_1/HEARTBEAT`, so strict rosbag validators accept the channel.
+/// Tokens only keep alphanumerics and underscores, wildcards and other characters become `_`,
+/// runs of `_` are collapsed, tokens starting with a digit are prefixed with `_` and empty tokens
+/// are dropped
+pub fn to_ros_name(key_expr: &str) -> String {
+    let tokens: Vec<String> = key_expr
+        .split('/')
+        .filter(|token| !token.is_empty())
+        .map(|token| {
+            let mut name = String::with_capacity(token.len());
+            for char in token.chars() {
+                let char = if char.is_ascii_alphanumeric() {
+                    char
+                } else {
+                    '_'
+                };
+                // ROS 2 names forbid repeated underscores
+                if char != '_' || !name.ends_with('_') {
+                    name.push(char);
+                }
+            }
+            if name.starts_with(|char: char| char.is_ascii_digit()) {
+                format!("_{name}")
+            } else {
+                name
+            }
+        })
+        .collect();
+    format!("/{}", tokens.join("/"))
+}
+
+/// Zenoh key of a channel read from a recording, the `key_expr` metadata of the channels written
+/// under their ROS 2 name
+pub fn key_expr<'a>(channel: &'a mcap::Channel<'_>) -> &'a str {
+    channel.metadata.get("key_expr").unwrap_or(&channel.topic)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_ros_name() {
+        assert_eq!(
+            to_ros_name("mavlink/1/1/HEARTBEAT"),
+            "/mavlink/_1/_1/HEARTBEAT"
+        );
+        assert_eq!(to_ros_name("/camera/image_raw"), "/camera/image_raw");
+        assert_eq!(to_ros_name("video/**"), "/video/_");
+        assert_eq!(to_ros_name("sensors/a--b/_1"), "/sensors/a_b/_1");
+        assert_eq!(
+            to_ros_name("sensors//depth-sensor/"),
+            "/sensors/depth_sensor"
+        );
+        assert_eq!(to_ros_name("recorder/events"), "/recorder/events");
+    }
+}
@@ -0,0 +1,28 @@
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+
+/// Clock boundaries at which recordings are split into a new file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SplitAt {
+    Hourly,
+    Daily,
+}
+
+impl SplitAt {
+    fn period(self) -> TimeDelta {
+        match self {
+            Self::Hourly => TimeDelta::hours(1),
+            Self::Daily => TimeDelta::days(1),
+        }
+    }
+
+    /// Returns the first boundary strictly after `now`
+    pub fn next_boundary(self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let period = self.period();
+        now.duration_trunc(period).unwrap_or(now) + period
+    }
+}
+
+/// Time left until `boundary`, zero if it already passed
+pub fn time_until(boundary: DateTime<Utc>) -> std::time::Duration {
+    (boundary - Utc::now()).to_std().unwrap_or_default()
+}
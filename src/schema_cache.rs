@@ -0,0 +1,158 @@
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde_json::Value;
+use tracing::*;
+use zenoh::bytes::Encoding;
+
+use crate::channel_descriptor::{ChannelDescriptor, MessageEncoding, cdr_schema_name};
+
+/// File persisting the channel schemas in the recorder directory
+const SCHEMA_CACHE_FILE: &str = "schema_cache.json";
+
+/// Schemas of the channels recorded by previous runs, a restarted recorder keeps the field types
+/// it inferred instead of inferring them again from the first sample
+pub struct SchemaCache {
+    path: PathBuf,
+    descriptors: BTreeMap<String, ChannelDescriptor>,
+}
+
+impl SchemaCache {
+    /// Loads the cache of the recorder directory, starting empty when missing or invalid
+    #[instrument(level = "debug")]
+    pub fn load(directory: &Path) -> Self {
+        let path = directory.join(SCHEMA_CACHE_FILE);
+        let descriptors = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self { path, descriptors }
+    }
+
+    /// Returns the cached channel of `topic` when it still describes its samples, without
+    /// inferring the schema of `payload`. A stale entry is dropped, the channel is then created
+    /// from the sample and cached again
+    pub fn get(
+        &mut self,
+        topic: &str,
+        encoding: &Encoding,
+        payload: &[u8],
+    ) -> Option<ChannelDescriptor> {
+        let cached = self.descriptors.get(topic)?;
+        if is_current(cached, encoding, payload) {
+            debug!(topic, "Using cached schema");
+            return Some(cached.clone());
+        }
+
+        info!(topic, "Schema changed, dropping the cached one");
+        // Persisted along with the channel replacing it
+        self.descriptors.remove(topic);
+        None
+    }
+
+    /// Caches a channel created from a sample
+    pub fn insert(&mut self, desc: &ChannelDescriptor) {
+        self.descriptors.insert(desc.topic.clone(), desc.clone());
+        self.save();
+    }
+
+    /// Encodings of the cached channels, the channels expected before any sample is received
+    pub fn message_encodings(&self) -> Vec<MessageEncoding> {
+        self.descriptors
+            .values()
+            .map(|desc| desc.message_encoding)
+            .collect()
+    }
+
+    fn save(&self) {
+        let temporary = self.path.with_extension("json.tmp");
+        let result = serde_json::to_vec(&self.descriptors)
+            .map_err(std::io::Error::from)
+            .and_then(|content| std::fs::write(&temporary, content))
+            .and_then(|()| std::fs::rename(&temporary, &self.path));
+        if let Err(error) = result {
+            warn!(%error, path = %self.path.display(), "Failed to persist schema cache");
+        }
+    }
+}
+
+/// Checks the sample has the encoding and schema name of the cached channel, and for JSON, no
+/// field missing from the cached schema. The cached field types are kept even when the sample
+/// shows another, e.g: an integer in a float field, this is what the cache is for
+fn is_current(cached: &ChannelDescriptor, encoding: &Encoding, payload: &[u8]) -> bool {
+    if let Some(schema_name) = cdr_schema_name(encoding) {
+        return cached.message_encoding == MessageEncoding::Cdr
+            && cached.schema_name == schema_name;
+    }
+
+    let encoding = Cow::from(encoding);
+    let (mime, schema_name) = match encoding.split_once(';') {
+        Some((mime, schema_name)) => (mime, Some(schema_name)),
+        None => (encoding.as_ref(), None),
+    };
+    let expected_name = schema_name.map_or_else(|| cached.topic.replace('/', "."), str::to_owned);
+    if mime != "application/json"
+        || cached.message_encoding != MessageEncoding::Json
+        || cached.schema_name != expected_name
+    {
+        return false;
+    }
+
+    let Some(properties) = serde_json::from_str::<Value>(&cached.schema_content)
+        .ok()
+        .and_then(|schema| schema.get("properties").cloned())
+    else {
+        return false;
+    };
+    std::str::from_utf8(payload)
+        .ok()
+        .and_then(|string| {
+            serde_json::from_str::<Value>(string)
+                .or_else(|_| serde_json5::from_str::<Value>(string))
+                .ok()
+        })
+        .and_then(|value| {
+            value
+                .as_object()
+                .map(|fields| fields.keys().all(|key| properties.get(key).is_some()))
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cached_schema_invalidation() {
+        let directory = std::env::temp_dir().join("blueos-recorder-schema-cache-test");
+        std::fs::create_dir_all(&directory).unwrap();
+        let _ = std::fs::remove_file(directory.join(SCHEMA_CACHE_FILE));
+        let mut cache = SchemaCache::load(&directory);
+        let encoding = Encoding::APPLICATION_JSON;
+
+        let first = br#"{"depth": 1.5}"#;
+        let desc = ChannelDescriptor::new("sensors/depth", &encoding, first, None).unwrap();
+        cache.insert(&desc);
+
+        // A restarted recorder keeps the number type, even if the first sample is an integer
+        let mut cache = SchemaCache::load(&directory);
+        let cached = cache.get("sensors/depth", &encoding, br#"{"depth": 2}"#);
+        assert_eq!(cached.unwrap().schema_content, desc.schema_content);
+
+        // A new field is a schema change
+        assert!(
+            cache
+                .get(
+                    "sensors/depth",
+                    &encoding,
+                    br#"{"depth": 2, "temperature": 20}"#
+                )
+                .is_none()
+        );
+        assert!(cache.get("sensors/depth", &encoding, first).is_none());
+    }
+}
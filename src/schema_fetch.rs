@@ -0,0 +1,93 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+use once_cell::sync::OnceCell;
+use tracing::*;
+use zenoh::Session;
+
+/// Key prefix where the publishers serve the definitions of their CDR types, e.g: a queryable
+/// on `schemas/sensor_msgs/Imu` replying the content of `Imu.msg`
+const SCHEMAS_PREFIX: &str = "schemas";
+/// Time before querying again a schema no publisher served
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Fetcher {
+    session: Session,
+    schemas: HashMap<String, String>,
+    queried: HashMap<String, Instant>,
+}
+
+static FETCHER: OnceCell<Mutex<Fetcher>> = OnceCell::new();
+
+/// Enables fetching the schemas missing from the schema directories over zenoh
+pub fn init(session: Session) {
+    let _ = FETCHER.set(Mutex::new(Fetcher {
+        session,
+        schemas: HashMap::new(),
+        queried: HashMap::new(),
+    }));
+}
+
+/// Returns the definition of a `package.Type` served by its publishers. An unknown schema is
+/// queried in the background, it is returned by the calls following the reply
+pub fn get(schema_name: &str) -> Option<String> {
+    let mut fetcher = FETCHER
+        .get()?
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(schema) = fetcher.schemas.get(schema_name) {
+        return Some(schema.clone());
+    }
+    if fetcher
+        .queried
+        .get(schema_name)
+        .is_some_and(|queried| queried.elapsed() < RETRY_INTERVAL)
+    {
+        return None;
+    }
+
+    let runtime = tokio::runtime::Handle::try_current().ok()?;
+    fetcher
+        .queried
+        .insert(schema_name.to_owned(), Instant::now());
+    let session = fetcher.session.clone();
+    let schema_name = schema_name.to_owned();
+    runtime.spawn(async move {
+        match query(&session, &schema_name).await {
+            Ok(schema) => {
+                info!(%schema_name, "Fetched schema from its publisher");
+                if let Some(fetcher) = FETCHER.get() {
+                    fetcher
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .schemas
+                        .insert(schema_name, schema);
+                }
+            }
+            Err(error) => debug!(%error, %schema_name, "Failed to fetch schema"),
+        }
+    });
+    None
+}
+
+async fn query(session: &Session, schema_name: &str) -> anyhow::Result<String> {
+    let key = format!("{SCHEMAS_PREFIX}/{}", schema_name.replace('.', "/"));
+    let replies = session
+        .get(key.as_str())
+        .timeout(QUERY_TIMEOUT)
+        .await
+        .map_err(|error| anyhow!("Failed to query {key}: {error}"))?;
+    while let Ok(reply) = replies.recv_async().await {
+        if let Ok(sample) = reply.into_result()
+            && let Ok(schema) = sample.payload().try_to_string()
+        {
+            return Ok(schema.into_owned());
+        }
+    }
+    Err(anyhow!("No publisher served {key}"))
+}
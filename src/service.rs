@@ -1,35 +1,241 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
 
+use serde_json::json;
 use tokio_graceful_shutdown::SubsystemHandle;
 use tracing::*;
-use zenoh::{Config, Session, handlers::FifoChannelHandler, pubsub::Subscriber, sample::Sample};
+use zenoh::{
+    Config, Session,
+    bytes::Encoding,
+    handlers::{FifoChannel, FifoChannelHandler},
+    key_expr::OwnedKeyExpr,
+    pubsub::Subscriber,
+    query::{Query, Queryable},
+    sample::{Sample, SampleKind},
+};
 
 use crate::{
-    channel_descriptor::ChannelDescriptor,
-    mavlink::{RAW_MAVLINK_OUT_TOPIC, vehicle::VehicleArmGate},
-    mcap::Mcap,
+    backfill,
+    catalog::{self, Manifest},
+    cdr::{CdrDecoder, CdrToJson, InvalidCdr},
+    channel_descriptor::{ChannelDescriptor, MessageEncoding, SchemaEncoding, cdr_schema_name},
+    cli, config,
+    control::{self, ControlCommand},
+    counters::Counters,
+    deletes::{self, DELETES_TOPIC, DeleteSamples},
+    dry_run::{self, DRY_RUN_INTERVAL, DryRun},
+    events,
+    failsafe::{self, BatteryFailsafe, FailsafeTrigger},
+    foxglove_schemas,
+    gaps::{GAPS_TOPIC, GapDetector},
+    health, hooks, journal,
+    leak::LeakDetector,
+    low_power::LowPower,
+    mavlink::{
+        RAW_MAVLINK_OUT_TOPIC, RAW_MAVLINK_TOPIC_PREFIX,
+        announce::Announcer,
+        battery::{self, BATTERY_METADATA, BatterySummary},
+        command,
+        dive::{DIVE_METADATA, DiveSummary},
+        failsafe as mavlink_failsafe, named_value,
+        position::{self, LAUNCH_POSITION_METADATA},
+        sensors, statustext,
+        vehicle::{ArmState, VehicleArmGate},
+        version,
+    },
+    mcap::{Mcap, McapOptions},
+    mirror::{self, LinkChange, LinkMonitor},
+    queue_alarm::{QueueAlarm, QueueAlarmChange},
+    quota::SessionQuota,
+    readme::{self, README_NAME},
+    recording_session::{RecordingSession, SESSION_METADATA, is_clock_sane},
+    rotation::{self, SplitAt},
+    schema_cache::SchemaCache,
+    schema_fetch,
+    sources::{self, SourceMessage, SourceReceiver, SourceSender},
+    stats::{Stats, WriteHealth},
+    storage::{self, ReadOnlyPolicy},
+    systemd,
+    time_sync::{ClockSource, DEFAULT_CLOCK, TIME_SYNC_METADATA, TIME_SYNC_TOPIC, TimeSync},
+    topics::{self, RateLimiter, TopicSettings},
+    transform::{self, NonObjectJson},
+    trigger::{self, Trigger},
+    trigger_history::{self, TriggerHistory},
+    warnings::RepeatedWarnings,
 };
 
+/// Name of the attachment holding the camera frame grabbed when the vehicle arms
+const ARM_SNAPSHOT_NAME: &str = "arm_snapshot.jpg";
+
+/// Key where the recorder publishes its status, under the instance prefix
+const STATUS_TOPIC: &str = "status";
+const PROGRESS_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Internal channel holding samples that could not be recorded as they are, under the instance
+/// prefix
+const DIAGNOSTICS_TOPIC: &str = "diagnostics";
+
 pub struct Service {
-    #[allow(dead_code)]
     session: Session,
     subscriber: Subscriber<FifoChannelHandler<Sample>>,
     mcap: Mcap,
     vehicle_arm: VehicleArmGate,
     schema_path: Option<std::path::PathBuf>,
+    cdr_to_json: CdrToJson,
+    invalid_cdr: InvalidCdr,
+    delete_samples: DeleteSamples,
+    cdr_decoder: CdrDecoder,
+    record_raw_mavlink: bool,
+    non_object_json: NonObjectJson,
+    /// Large JSON integers are written as strings
+    large_integers_as_strings: bool,
+    /// Schemas of the JSON channels holding integers written as strings, by channel
+    stringified_schemas: BTreeMap<String, serde_json::Value>,
+    stats: Stats,
+    write_health: WriteHealth,
+    time_sync: TimeSync,
+    recorder_path: std::path::PathBuf,
+    mcap_options: McapOptions,
+    split_at: Option<SplitAt>,
+    recording_session: RecordingSession,
+    control: Queryable<FifoChannelHandler<Query>>,
+    paused_since: Option<SystemTime>,
+    dry_run: Option<DryRun>,
+    low_power: LowPower,
+    battery_failsafe: BatteryFailsafe,
+    arm_snapshot_topic: Option<OwnedKeyExpr>,
+    /// Key expression announcing the vehicle shutdown
+    shutdown_key: Option<OwnedKeyExpr>,
+    /// Set once the recording was finalized for a vehicle shutdown, stopping the recorder
+    shutting_down: bool,
+    arm_snapshot_pending: bool,
+    source_sender: SourceSender,
+    source_receiver: SourceReceiver,
+    trigger: Option<Trigger>,
+    trigger_history: TriggerHistory,
+    history_queryable: Queryable<FifoChannelHandler<Query>>,
+    health_queryable: Queryable<FifoChannelHandler<Query>>,
+    /// Reception time of the last sample, reported by the health queryable
+    last_sample: Option<Instant>,
+    catalog_queryable: Queryable<FifoChannelHandler<Query>>,
+    gaps: GapDetector,
+    quota: SessionQuota,
+    failsafe_trigger: FailsafeTrigger,
+    /// Recording state last reported to the history, combining the trigger and the failsafes
+    recording_active: bool,
+    leak: LeakDetector,
+    counters: Counters,
+    schema_cache: SchemaCache,
+    /// Set once the recorder path became read-only, published in the status
+    storage_alert: Option<String>,
+    rate_limiter: RateLimiter,
+    queue_alarm: QueueAlarm,
+    /// Announces the recorder state to the GCS, when enabled
+    announcer: Option<Announcer>,
+    /// Link to the vehicle router, followed in mirror mode
+    link: Option<LinkMonitor>,
+    /// Deadline and cause of a recording stop held by the session gap timeout, the session
+    /// continues if the recording restarts before it
+    pending_stop: Option<(tokio::time::Instant, String)>,
+    /// Battery statistics of the file being written
+    battery: BatterySummary,
+    /// Dive profile of the file being written
+    dive: DiveSummary,
+    warnings: RepeatedWarnings,
+    /// Flight software versions by AUTOPILOT_VERSION topic, described in the README of the files
+    firmware: BTreeMap<String, String>,
 }
 
-fn generate_filename() -> String {
-    let now = SystemTime::now();
-    let datetime = now
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .expect("Time went backwards");
-    let datetime = chrono::DateTime::<chrono::Utc>::from_timestamp(
-        datetime.as_secs() as i64,
-        datetime.subsec_nanos(),
-    )
-    .expect("Invalid timestamp");
-    format!("recorder_{}.mcap", datetime.format("%Y%m%d_%H%M%S"))
+/// Creates the file of the current session part, tagged with the session metadata
+fn open_mcap(
+    recorder_path: &std::path::Path,
+    options: &McapOptions,
+    recording_session: &RecordingSession,
+    encodings: &[MessageEncoding],
+) -> anyhow::Result<Mcap> {
+    let path = match cli::output() {
+        Some(output) => output.to_path_buf(),
+        None => recorder_path
+            .join(recording_session.filename(&cli::instance_prefix(), cli::filename_timezone())),
+    };
+    info!(path = %path.display(), part = recording_session.part, "Opening recording file");
+
+    let mut mcap = if cli::is_dry_run() {
+        Mcap::discard(options, encodings)?
+    } else if cli::is_stdout_output() {
+        Mcap::stdout(options, encodings)?
+    } else {
+        let mcap = Mcap::try_new(&path, options, encodings)?;
+        if let Err(error) = Manifest::new(&path, recording_session, cli::vehicle()).save(&path) {
+            warn!(%error, "Failed to write the recording manifest");
+        }
+        hooks::on_start(
+            &path,
+            &recording_session.id.to_string(),
+            recording_session.part,
+        );
+        mcap
+    };
+    mcap.write_metadata(SESSION_METADATA, recording_session.metadata())?;
+    if let Some(position) = recording_session.launch_position {
+        mcap.write_metadata(LAUNCH_POSITION_METADATA, position.metadata())?;
+    }
+    Ok(mcap)
+}
+
+/// Writes the metadata computed during the recording and finishes the file
+fn finalize(
+    mcap: &mut Mcap,
+    time_sync: &TimeSync,
+    battery: BatterySummary,
+    dive: DiveSummary,
+    firmware: &BTreeMap<String, String>,
+) {
+    let time_sync = time_sync.metadata();
+    if !time_sync.is_empty()
+        && let Err(error) = mcap.write_metadata(TIME_SYNC_METADATA, time_sync)
+    {
+        error!(%error, "Failed to write time sync metadata");
+    }
+    let battery = (!battery.is_empty()).then_some(battery);
+    if let Some(battery) = &battery
+        && let Err(error) = mcap.write_metadata(BATTERY_METADATA, battery.metadata())
+    {
+        error!(%error, "Failed to write battery metadata");
+    }
+    let dive = (!dive.is_empty()).then_some(dive);
+    if let Some(dive) = &dive
+        && let Err(error) = mcap.write_metadata(DIVE_METADATA, dive.metadata())
+    {
+        error!(%error, "Failed to write dive metadata");
+    }
+    let readme = readme::render(mcap.path(), mcap.channel_descriptors(), firmware);
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64;
+    if let Err(error) = mcap.write_attachment(README_NAME, "text/plain", now, readme.as_bytes()) {
+        error!(%error, "Failed to write README attachment");
+    }
+
+    if let Err(error) = mcap.finish() {
+        error!(%error, "Failed to finish MCAP writer");
+        journal::record(
+            "error",
+            json!({
+                "message": "Failed to finish MCAP writer",
+                "error": error.to_string(),
+            }),
+        );
+        hooks::on_error(&format!("Failed to finish MCAP writer: {error}"));
+    }
+    if let Some(path) = mcap.path() {
+        catalog::finish(path, battery, dive);
+        hooks::on_finish(path);
+    }
 }
 
 impl Service {
@@ -38,33 +244,174 @@ impl Service {
         config: Config,
         recorder_path: std::path::PathBuf,
         schema_path: Option<std::path::PathBuf>,
+        cdr_to_json: CdrToJson,
+        mcap_options: McapOptions,
     ) -> Self {
         let session = zenoh::open(config)
             .await
             .expect("Failed to open zenoh session");
+        schema_fetch::init(session.clone());
+        let announcer = cli::announce_mavlink().then(|| Announcer::new(session.clone()));
         let subscriber = session
             .declare_subscriber("**")
+            .allowed_origin(config::get().subscriber.allowed_origin.into())
+            .with(
+                cli::subscriber_queue_size()
+                    .map(FifoChannel::new)
+                    .unwrap_or_default(),
+            )
             .await
             .expect("Failed to declare global zenoh subscriber");
 
-        let path = recorder_path.join(generate_filename());
-        info!("Opening recording session");
+        let control = session
+            .declare_queryable(control::key())
+            .await
+            .expect("Failed to declare control queryable");
+
+        let mut counters = Counters::load(&recorder_path);
+        let schema_cache = SchemaCache::load(&recorder_path);
+        // Only the recordings are counted, the recorder starts idle until its condition is met
+        let recording_session = RecordingSession::new(
+            cli::output().is_some().then(|| counters.next_session()),
+            counters.arms,
+        );
+        info!(session_id = %recording_session.id, "Opening recording session");
+
+        let health_queryable = session
+            .declare_queryable(health::key())
+            .await
+            .expect("Failed to declare health queryable");
+
+        let history_queryable = session
+            .declare_queryable(trigger_history::key())
+            .await
+            .expect("Failed to declare trigger history queryable");
+
+        let catalog_queryable = session
+            .declare_queryable(catalog::key())
+            .await
+            .expect("Failed to declare catalog queryable");
+
+        let (source_sender, source_receiver) = sources::channel();
 
-        let mcap = Mcap::try_new(&path).unwrap();
+        // The header profile is written before any channel is known, the first file expects the
+        // channels of the previous runs
+        let mcap = open_mcap(
+            &recorder_path,
+            &mcap_options,
+            &recording_session,
+            &schema_cache.message_encodings(),
+        )
+        .unwrap();
+        let trigger_history = TriggerHistory::load(&recorder_path);
         Self {
             session,
             subscriber,
             mcap,
             vehicle_arm: VehicleArmGate::new(),
+            cdr_decoder: CdrDecoder::new(schema_path.clone()),
             schema_path,
+            cdr_to_json,
+            invalid_cdr: cli::invalid_cdr(),
+            delete_samples: cli::delete_samples(),
+            record_raw_mavlink: cli::record_raw_mavlink(),
+            non_object_json: cli::non_object_json(),
+            large_integers_as_strings: cli::json_large_integers_as_strings(),
+            stringified_schemas: BTreeMap::new(),
+            stats: Stats::new(),
+            write_health: WriteHealth::default(),
+            time_sync: TimeSync::new(),
+            recorder_path,
+            mcap_options,
+            split_at: cli::split_at(),
+            recording_session,
+            control,
+            paused_since: None,
+            dry_run: cli::is_dry_run().then(DryRun::new),
+            low_power: LowPower::new(cli::low_power_battery_percent()),
+            battery_failsafe: BatteryFailsafe::new(
+                cli::failsafe_battery_percent(),
+                cli::failsafe_battery_voltage(),
+            ),
+            arm_snapshot_topic: cli::arm_snapshot_topic(),
+            shutdown_key: cli::shutdown_key(),
+            shutting_down: false,
+            arm_snapshot_pending: false,
+            source_sender,
+            source_receiver,
+            trigger: Trigger::from_config(config::get()).expect("Invalid recording trigger"),
+            trigger_history,
+            history_queryable,
+            health_queryable,
+            last_sample: None,
+            catalog_queryable,
+            gaps: GapDetector::new(),
+            quota: SessionQuota::new(cli::max_session_bytes(), cli::max_session_duration()),
+            failsafe_trigger: FailsafeTrigger::new(cli::failsafe_hold()),
+            leak: LeakDetector::new(config::get().leak.clone()),
+            counters,
+            schema_cache,
+            // An explicit output records from the start, the recording never changes sessions
+            recording_active: cli::output().is_some(),
+            storage_alert: None,
+            rate_limiter: RateLimiter::default(),
+            queue_alarm: QueueAlarm::new(cli::queue_watermarks()),
+            announcer,
+            link: cli::is_mirror().then(LinkMonitor::default),
+            pending_stop: None,
+            battery: BatterySummary::default(),
+            dive: DiveSummary::default(),
+            warnings: RepeatedWarnings::default(),
+            firmware: BTreeMap::new(),
         }
     }
 
+    /// Sender used by the secondary sources to have their messages recorded
+    pub fn source_sender(&self) -> SourceSender {
+        self.source_sender.clone()
+    }
+
+    /// Zenoh session shared with the sources observing the bus
+    pub fn session(&self) -> Session {
+        self.session.clone()
+    }
+
     #[instrument(skip_all)]
     pub async fn run(&mut self, subsystem: &mut SubsystemHandle) -> anyhow::Result<()> {
         let mut last_flush = SystemTime::now();
-        info!("Waiting for vehicle to be armed");
+        let mut progress = tokio::time::interval_at(
+            tokio::time::Instant::now() + PROGRESS_INTERVAL,
+            PROGRESS_INTERVAL,
+        );
+        let mut next_split = self
+            .split_at
+            .map(|split_at| split_at.next_boundary(chrono::Utc::now()));
+        let checkpoint_interval = cli::checkpoint_interval();
+        let mut checkpoint = tokio::time::interval_at(
+            tokio::time::Instant::now() + checkpoint_interval.unwrap_or(PROGRESS_INTERVAL),
+            checkpoint_interval.unwrap_or(PROGRESS_INTERVAL),
+        );
+        let mut dry_run_report = tokio::time::interval(DRY_RUN_INTERVAL);
+        let watchdog_interval = systemd::watchdog_interval();
+        let mut watchdog = tokio::time::interval(watchdog_interval.unwrap_or(PROGRESS_INTERVAL));
+        let mut link_check = tokio::time::interval(mirror::LINK_CHECK_INTERVAL);
+        journal::record(
+            "service_start",
+            json!({ "version": env!("CARGO_PKG_VERSION") }),
+        );
+        if let Some(output) = cli::output() {
+            info!(output = %output.display(), "Recording until stopped");
+        } else if self.trigger.is_some() {
+            info!("Waiting for recording trigger");
+        } else {
+            info!("Waiting for vehicle to be armed");
+        }
         loop {
+            let split_delay = next_split.map(rotation::time_until).unwrap_or_default();
+            let stop_deadline = self
+                .pending_stop
+                .as_ref()
+                .map_or_else(tokio::time::Instant::now, |(deadline, _)| *deadline);
             let sample = tokio::select! {
                 sample = self.subscriber.recv_async() => {
                     let Ok(sample) = sample else {
@@ -73,78 +420,1662 @@ impl Service {
 
                     sample
                 },
+                _ = watchdog.tick(), if watchdog_interval.is_some() => {
+                    systemd::notify_watchdog();
+                    continue;
+                },
+                () = tokio::time::sleep_until(stop_deadline), if self.pending_stop.is_some() => {
+                    if let Some((_, cause)) = self.pending_stop.take() {
+                        self.on_recording_changed(false, &cause);
+                    }
+                    continue;
+                },
+                _ = link_check.tick(), if self.link.is_some() => {
+                    self.check_link().await;
+                    continue;
+                },
+                () = tokio::time::sleep(split_delay), if next_split.is_some() => {
+                    if let Err(error) = self.rotate() {
+                        error!(%error, "Failed to split recording");
+                    }
+                    next_split = self
+                        .split_at
+                        .map(|split_at| split_at.next_boundary(chrono::Utc::now()));
+                    continue;
+                },
+                // Finished parts are fully indexed, an abrupt stop only affects the last one
+                _ = checkpoint.tick(), if checkpoint_interval.is_some() => {
+                    if self.is_recording_active()
+                        && let Err(error) = self.rotate()
+                    {
+                        error!(%error, "Failed to checkpoint recording");
+                    }
+                    continue;
+                },
+                query = self.control.recv_async() => {
+                    if let Ok(query) = query {
+                        self.handle_control(query).await;
+                    }
+                    if self.shutting_down {
+                        subsystem.request_shutdown();
+                        break;
+                    }
+                    continue;
+                },
+                query = self.health_queryable.recv_async() => {
+                    if let Ok(query) = query {
+                        control::reply(&query, health::report(self.last_sample)).await;
+                    }
+                    continue;
+                },
+                query = self.history_queryable.recv_async() => {
+                    if let Ok(query) = query {
+                        let limit = query
+                            .parameters()
+                            .get("limit")
+                            .and_then(|limit| limit.parse().ok());
+                        control::reply(&query, Ok(self.trigger_history.to_json(limit))).await;
+                    }
+                    continue;
+                },
+                query = self.catalog_queryable.recv_async() => {
+                    if let Ok(query) = query {
+                        let key = query.key_expr().as_str();
+                        let result = if catalog::is_modifying(key) {
+                            control::authorize(&query)
+                        } else {
+                            Ok(())
+                        }
+                        .and_then(|()| {
+                            catalog::handle(
+                                &self.recorder_path,
+                                self.mcap.path(),
+                                key,
+                                query.parameters(),
+                            )
+                        });
+                        control::reply(&query, result).await;
+                    }
+                    continue;
+                },
+                _ = dry_run_report.tick(), if self.dry_run.is_some() => {
+                    if let Some(dry_run) = &mut self.dry_run {
+                        println!("{}", dry_run.table());
+                    }
+                    continue;
+                },
+                Some(message) = self.source_receiver.recv() => {
+                    self.write_source_message(message);
+                    continue;
+                },
+                _ = progress.tick() => {
+                    self.correct_clock();
+                    self.report_progress().await;
+                    continue;
+                },
                 () = subsystem.on_shutdown_requested() => {
                     break;
                 },
             };
 
+            self.last_sample = Some(Instant::now());
+            self.check_quota().await;
+
+            let queue_depth = self.subscriber.len() + 1;
+            self.stats.observe_queue(queue_depth);
+            if let Some(change) = self
+                .queue_alarm
+                .update(queue_depth, self.subscriber.capacity())
+            {
+                self.on_queue_alarm(change).await;
+            }
+
             let topic = sample.key_expr().as_str();
             let encoding = sample.encoding();
-            let payload = sample.payload();
+            let payload = sample.payload().to_bytes();
             let span = info_span!("sample", topic = %topic, encoding = %encoding);
             let _sample_span = span.enter();
 
-            if topic.starts_with(RAW_MAVLINK_OUT_TOPIC) {
-                crate::mavlink::handle_mavlink_message(&payload.to_bytes(), &mut self.vehicle_arm)
-                    .await;
+            if self
+                .shutdown_key
+                .as_ref()
+                .is_some_and(|shutdown_key| shutdown_key.includes(sample.key_expr()))
+            {
+                if let Err(error) = self.prepare_shutdown(topic) {
+                    error!(%error, "Failed to finalize the recording before shutdown");
+                }
+                subsystem.request_shutdown();
+                break;
+            }
+
+            if topic.starts_with(RAW_MAVLINK_OUT_TOPIC)
+                && let Some(state) =
+                    crate::mavlink::handle_mavlink_message(&payload, &mut self.vehicle_arm).await
+            {
+                self.on_arm_state_changed(state);
             }
 
-            if !self.should_record_sample(topic) {
+            if let Some(trigger) = &mut self.trigger
+                && let Some(active) = trigger.update(topic, &payload)
+            {
+                self.on_trigger_changed(topic, active);
+            }
+
+            if self.arm_snapshot_pending && self.is_arm_snapshot(topic, encoding, &payload) {
+                self.arm_snapshot_pending = false;
+                let log_time = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_nanos() as u64;
+                match self.mcap.write_attachment(
+                    ARM_SNAPSHOT_NAME,
+                    "image/jpeg",
+                    log_time,
+                    &payload,
+                ) {
+                    Ok(()) => info!("Stored arm snapshot"),
+                    Err(error) => error!(%error, "Failed to store arm snapshot"),
+                }
+            }
+
+            // Publisher sequence numbers make the samples lost on the network visible
+            let sequence = sample
+                .source_info()
+                .map(|source_info| source_info.source_sn());
+            for gap in self.gaps.update(topic, sequence) {
+                self.write_gap(gap);
+            }
+
+            if battery::is_battery_topic(topic) {
+                self.handle_battery(topic, &payload);
+            }
+
+            if position::is_position_topic(topic) {
+                self.handle_position(&payload);
+            }
+
+            if mavlink_failsafe::is_failsafe_topic(topic) {
+                self.handle_failsafe(topic, &payload);
+            }
+            if self.failsafe_trigger.update() {
+                info!("Failsafe hold expired");
+                self.refresh_recording(failsafe::FAILSAFE);
+            }
+
+            if self.leak.update(topic, &payload) {
+                self.handle_leak(topic);
+            }
+
+            // Commands are recorded even while disarmed, and are never dropped nor downsampled
+            let is_command = command::is_command_topic(topic);
+            if is_command
+                && let Some(value) = std::str::from_utf8(&payload)
+                    .ok()
+                    .and_then(|string| serde_json5::from_str::<serde_json::Value>(string).ok())
+            {
+                self.write_command(topic, &value);
+            }
+
+            let settings = topics::resolve(config::get(), topic);
+            // Shed the low-priority topics first when the writer falls behind
+            if !is_command
+                && settings
+                    .priority
+                    .should_drop(queue_depth, self.subscriber.capacity())
+            {
+                trace!("Dropping sample to shed load");
+                self.stats.record_drop();
+                if let Some(change) = self.queue_alarm.record_drop() {
+                    self.on_queue_alarm(change).await;
+                }
+                continue;
+            }
+
+            let recorded = self.should_record_sample(topic)
+                && (is_command
+                    || (settings.enabled
+                        && self.low_power.should_record(topic)
+                        && self.rate_limiter.should_record(topic, settings.rate_limit)));
+            // Deletes carry no payload, they are kept apart to not register empty channels
+            if sample.kind() == SampleKind::Delete {
+                if recorded && self.delete_samples == DeleteSamples::Tombstone {
+                    self.write_tombstone(topic, sample.timestamp());
+                }
+                continue;
+            }
+            if let Some(dry_run) = &mut self.dry_run {
+                let status = if recorded {
+                    dry_run::Status::Recorded
+                } else {
+                    dry_run::Status::Gated
+                };
+                let transform = settings.transform.map(|(key, _)| key);
+                dry_run.record(topic, status, payload.len(), transform);
+            }
+            if !recorded {
                 continue;
             }
 
-            let new_channel = if self.mcap.has_channel(topic) {
+            let now = SystemTime::now();
+            let log_time = now.duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64;
+            // The clocks read the autopilot time fields, which the transforms may remove
+            let publish_time = self.publish_time(&sample, &payload, settings.clock, log_time);
+
+            let Some(payload) = self.write_payload(
+                topic,
+                encoding,
+                payload,
+                &settings,
+                log_time,
+                publish_time,
+                sequence,
+            ) else {
+                continue;
+            };
+
+            if statustext::is_statustext_topic(topic) {
+                self.write_statustext_log(topic, &payload, publish_time);
+            }
+
+            if named_value::is_named_value_topic(topic) {
+                self.write_named_value(topic, &payload, publish_time);
+            }
+
+            if version::is_version_topic(topic) {
+                self.handle_version(topic, &payload);
+            }
+
+            if sensors::is_pressure_topic(topic) {
+                self.write_pressure(topic, &payload, publish_time);
+            }
+
+            if TimeSync::is_source_topic(topic) {
+                self.write_time_sync(&payload, log_time);
+                self.correct_clock();
+            }
+
+            let flush_interval = self.leak.flush_interval(self.low_power.flush_interval());
+            if now.duration_since(last_flush).unwrap() > flush_interval {
+                let flush_start = Instant::now();
+                let result = self.mcap.flush();
+                self.write_health.observe_flush(flush_start.elapsed());
+                if let Err(error) = result {
+                    error!(%error, "Failed to flush MCAP writer");
+                    self.handle_write_error(&error);
+                }
+                last_flush = now;
+            }
+        }
+
+        systemd::notify_stopping();
+        self.finalize_file();
+        journal::record("service_stop", json!({}));
+
+        Ok(())
+    }
+
+    /// Finishes the current file and continues the recording on a new part of the session
+    #[instrument(skip_all)]
+    fn rotate(&mut self) -> anyhow::Result<()> {
+        // A stopped session has no file to continue, and an explicit output is a single file
+        if self.quota.is_exceeded() || cli::output().is_some() {
+            return Ok(());
+        }
+        self.recording_session.next_part();
+        self.open_next_file()?;
+        journal::record(
+            "rotate",
+            json!({
+                "session_id": self.recording_session.id.to_string(),
+                "part": self.recording_session.part,
+                "file": self.mcap.path(),
+            }),
+        );
+        if let Some(announcer) = &mut self.announcer {
+            announcer.info(&format!(
+                "Recording rotated, part {}",
+                self.recording_session.part
+            ));
+        }
+        Ok(())
+    }
+
+    /// Turns the int64 and uint64 fields of a new JSON channel into strings, remembering the
+    /// schema to stringify the samples of the channel
+    fn stringify_schema(&mut self, channel_descriptor: &mut ChannelDescriptor) {
+        if channel_descriptor.schema_encoding != SchemaEncoding::JsonSchema {
+            return;
+        }
+        let Ok(mut schema) = serde_json::from_str(&channel_descriptor.schema_content) else {
+            return;
+        };
+        if transform::stringify_schema(&mut schema) {
+            channel_descriptor.schema_content = schema.to_string();
+            self.stringified_schemas
+                .insert(channel_descriptor.topic.clone(), schema);
+        }
+    }
+
+    /// Writes the metadata computed during the recording and finishes the file being written
+    fn finalize_file(&mut self) {
+        finalize(
+            &mut self.mcap,
+            &self.time_sync,
+            std::mem::take(&mut self.battery),
+            std::mem::take(&mut self.dive),
+            &self.firmware,
+        );
+    }
+
+    /// Announces an error stopping or degrading the recording to the GCS
+    fn announce_error(&mut self, text: &str) {
+        if let Some(announcer) = &mut self.announcer {
+            announcer.error(text);
+        }
+    }
+
+    /// Finishes the current file and starts a new recording session, only the active ones are
+    /// numbered so the session numbers count the recordings, e.g: the dives
+    #[instrument(skip_all)]
+    fn start_session(&mut self, active: bool) -> anyhow::Result<()> {
+        self.recording_session = RecordingSession::new(
+            active.then(|| self.counters.next_session()),
+            self.counters.arms,
+        );
+        info!(session_id = %self.recording_session.id, "Starting recording session");
+        self.quota.reset(self.stats.total_bytes());
+        self.open_next_file()
+    }
+
+    /// Hard-stops the session when it exceeds its quota: the file is finalized and nothing is
+    /// recorded until the next session starts
+    async fn check_quota(&mut self) {
+        let Some(details) = self.quota.check(self.stats.total_bytes()) else {
+            return;
+        };
+        warn!(%details, session_id = %self.recording_session.id, "Recording session quota exceeded");
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64;
+        let event = events::event(
+            "quota_exceeded",
+            "Recording session quota exceeded, recording stopped until the next session",
+            details,
+        );
+        self.write_event(timestamp, event.clone());
+        journal::record("error", event.clone());
+        hooks::on_error(event["message"].as_str().unwrap_or_default());
+        self.announce_error("Recording quota exceeded");
+
+        let mcap = match Mcap::discard(&self.mcap_options, &self.mcap.message_encodings()) {
+            Ok(mcap) => mcap,
+            Err(error) => {
+                error!(%error, "Failed to stop recording session");
+                return;
+            }
+        };
+        self.finalize_file();
+        self.mcap = mcap;
+
+        if let Err(error) = self
+            .session
+            .put(events::key(), event.to_string())
+            .encoding(Encoding::APPLICATION_JSON)
+            .allowed_destination(cli::own_keys_destination())
+            .await
+        {
+            warn!(%error, "Failed to publish quota exceeded event");
+        }
+    }
+
+    /// Reports the subscriber queue alarms on the events channel and to the remote subscribers,
+    /// operators can reduce the camera rates before the recording degrades further
+    async fn on_queue_alarm(&mut self, change: QueueAlarmChange) {
+        let details = json!({
+            "queue_depth": self.subscriber.len(),
+            "queue_capacity": self.subscriber.capacity(),
+        });
+        let event = match change {
+            QueueAlarmChange::Watermark(percent) => {
+                warn!(percent, "Subscriber queue above its watermark");
+                events::event(
+                    "queue_watermark",
+                    &format!("Subscriber queue above {percent}%, the recording may degrade"),
+                    details,
+                )
+            }
+            QueueAlarmChange::Dropping => {
+                warn!("Dropping samples to shed load");
+                events::event(
+                    "queue_dropping",
+                    "Dropping low priority samples to shed load, the recording is degraded",
+                    details,
+                )
+            }
+            QueueAlarmChange::Cleared => {
+                info!("Subscriber queue drained");
+                events::event("queue_cleared", "Subscriber queue drained", details)
+            }
+        };
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64;
+        self.write_event(timestamp, event.clone());
+
+        if let Err(error) = self
+            .session
+            .put(events::key(), event.to_string())
+            .encoding(Encoding::APPLICATION_JSON)
+            .allowed_destination(cli::own_keys_destination())
+            .await
+        {
+            warn!(%error, "Failed to publish queue alarm event");
+        }
+    }
+
+    /// Stops failing every write once the recorder path becomes read-only: the recording
+    /// continues in the emergency path or stops, as set by `--on-read-only`
+    fn handle_write_error(&mut self, error: &anyhow::Error) {
+        self.write_health.record_error(error);
+        if self.storage_alert.is_some() || !storage::is_read_only(error) {
+            return;
+        }
+        let read_only_path = self.recorder_path.clone();
+        let emergency_path = cli::emergency_path();
+        // The alert reports what actually happened, a failed switch stops the recording
+        let alert = match cli::on_read_only() {
+            ReadOnlyPolicy::Buffer => match self.switch_recorder_path(&emergency_path) {
+                Ok(()) => format!(
+                    "Recorder path is read-only, recording to {}",
+                    emergency_path.display()
+                ),
+                Err(error) => {
+                    error!(%error, "Failed to move the recording out of the read-only path");
+                    self.stop_file();
+                    format!(
+                        "Recorder path is read-only, recording stopped, failed to switch to {}: \
+                         {error}",
+                        emergency_path.display()
+                    )
+                }
+            },
+            ReadOnlyPolicy::Stop => {
+                self.stop_file();
+                "Recorder path is read-only, recording stopped".to_owned()
+            }
+        };
+        error!(path = %read_only_path.display(), "{alert}");
+        journal::record("error", json!({ "message": alert }));
+        hooks::on_error(&alert);
+        self.announce_error("Recorder path read-only");
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64;
+        self.write_event(timestamp, events::event("read_only", &alert, json!({})));
+        self.storage_alert = Some(alert);
+    }
+
+    /// Continues the recording in `path`, e.g: the emergency path, the current path is kept when
+    /// no file could be opened there
+    fn switch_recorder_path(&mut self, path: &std::path::Path) -> anyhow::Result<()> {
+        if cli::output().is_some() {
+            return Err(anyhow::anyhow!("--output records into a single file"));
+        }
+        std::fs::create_dir_all(path)?;
+        let previous = std::mem::replace(&mut self.recorder_path, path.to_owned());
+        // A stopped session has no file to continue, the next one starts in the new path
+        if self.quota.is_exceeded() {
+            return Ok(());
+        }
+        if let Err(error) = self.rotate() {
+            self.recorder_path = previous;
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    /// Finalizes the file being written and goes on without writing any
+    fn stop_file(&mut self) {
+        match Mcap::discard(&self.mcap_options, &self.mcap.message_encodings()) {
+            Ok(mcap) => {
+                self.finalize_file();
+                self.mcap = mcap;
+            }
+            Err(error) => error!(%error, "Failed to stop the recording file"),
+        }
+    }
+
+    /// Renames the files of a session started before the clock was synchronized, once the time
+    /// is known from the system clock or the GPS
+    fn correct_clock(&mut self) {
+        if is_clock_sane(self.recording_session.start) {
+            return;
+        }
+        let mut now = chrono::Utc::now();
+        if !is_clock_sane(now)
+            && let Some(offset) = self.time_sync.unix_offset_ns()
+        {
+            now -= chrono::TimeDelta::nanoseconds(offset as i64);
+        }
+        if !is_clock_sane(now) {
+            return;
+        }
+
+        let (prefix, timezone) = (cli::instance_prefix(), cli::filename_timezone());
+        let placeholders: Vec<_> = (1..=self.recording_session.part)
+            .map(|part| {
+                self.recording_session
+                    .part_filename(&prefix, timezone, part)
+            })
+            .collect();
+        let correction_ns = self.recording_session.correct_start(now);
+        info!(correction_ns, start = %self.recording_session.start, "Clock corrected");
+
+        for (part, placeholder) in (1..).zip(placeholders) {
+            let from = self.recorder_path.join(placeholder);
+            // Nothing to rename when dry running or piping the recording
+            if !from.exists() {
+                continue;
+            }
+            let to = self.recorder_path.join(
+                self.recording_session
+                    .part_filename(&prefix, timezone, part),
+            );
+            if let Err(error) = catalog::rename(&from, &to, correction_ns) {
+                warn!(%error, "Failed to rename the recording file");
+            } else if self.mcap.path() == Some(from.as_path()) {
+                self.mcap.set_path(to);
+            }
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64;
+        self.write_event(
+            timestamp,
+            events::event(
+                "clock_corrected",
+                "Clock synchronized, recording files renamed",
+                json!({
+                    "session_start": self.recording_session.start.to_rfc3339(),
+                    "correction_ns": correction_ns,
+                }),
+            ),
+        );
+    }
+
+    fn open_next_file(&mut self) -> anyhow::Result<()> {
+        let mut mcap = open_mcap(
+            &self.recorder_path,
+            &self.mcap_options,
+            &self.recording_session,
+            &self.mcap.message_encodings(),
+        )?;
+        // The channels of the previous file are registered upfront, readers of the new file see
+        // every known topic from its start
+        mcap.register_channels(self.mcap.channel_descriptors());
+        self.finalize_file();
+        self.mcap = mcap;
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(key = %query.key_expr()))]
+    async fn handle_control(&mut self, query: Query) {
+        let result = control::authorize(&query).and_then(|()| {
+            match ControlCommand::from_key(query.key_expr().as_str()) {
+                Some(command) => self.apply_control(command),
+                None => Err(anyhow::anyhow!("Unknown control command")),
+            }
+        });
+        control::reply(&query, result).await;
+    }
+
+    fn apply_control(&mut self, command: ControlCommand) -> anyhow::Result<serde_json::Value> {
+        let now = SystemTime::now();
+        let timestamp = now.duration_since(UNIX_EPOCH)?.as_nanos() as u64;
+
+        match (command, self.paused_since) {
+            (ControlCommand::Pause, None) => {
+                info!("Pausing recording");
+                self.paused_since = Some(now);
+                self.write_event(
+                    timestamp,
+                    events::event("pause", "Recording paused", json!({})),
+                );
+            }
+            (ControlCommand::Resume, Some(paused_since)) => {
+                info!("Resuming recording");
+                self.paused_since = None;
+                let paused_at = paused_since.duration_since(UNIX_EPOCH)?.as_nanos() as u64;
+                self.write_event(
+                    timestamp,
+                    events::event(
+                        "resume",
+                        "Recording resumed",
+                        json!({
+                            "paused_at": paused_at,
+                            "paused_duration_ns": timestamp.saturating_sub(paused_at),
+                        }),
+                    ),
+                );
+            }
+            (ControlCommand::LowPower(active), _) => {
+                if self.low_power.set(active) {
+                    self.on_low_power_changed(timestamp, json!({ "reason": "control" }));
+                }
+            }
+            (ControlCommand::Shutdown, _) => return self.prepare_shutdown("control"),
+            (ControlCommand::Manual(manual), _) => {
+                if let Some(trigger) = &mut self.trigger
+                    && let Some(active) = trigger.set_manual(manual)
+                {
+                    self.on_trigger_changed(trigger::MANUAL, active);
+                }
+            }
+            _ => debug!(?command, "Control command had no effect"),
+        }
+
+        Ok(json!({
+            "paused": self.paused_since.is_some(),
+            "recording": self.is_recording_active(),
+            "low_power": self.low_power.is_active(),
+        }))
+    }
+
+    /// Finalizes and syncs the recording before the vehicle powers down, nothing is recorded
+    /// afterwards and the recorder stops
+    fn prepare_shutdown(&mut self, cause: &str) -> anyhow::Result<serde_json::Value> {
+        info!(cause, "Vehicle shutting down, finalizing the recording");
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos() as u64;
+        self.write_event(
+            timestamp,
+            events::event(
+                "shutdown",
+                "Vehicle shutting down, recording finalized",
+                json!({ "cause": cause }),
+            ),
+        );
+        journal::record(
+            "shutdown",
+            json!({ "cause": cause, "file": self.mcap.path() }),
+        );
+
+        let mcap = Mcap::discard(&self.mcap_options, &self.mcap.message_encodings())?;
+        self.finalize_file();
+        let previous = std::mem::replace(&mut self.mcap, mcap);
+        self.shutting_down = true;
+        Ok(json!({ "file": previous.path() }))
+    }
+
+    /// Forces the recording when a HEARTBEAT or STATUSTEXT payload indicates a failsafe
+    fn handle_failsafe(&mut self, topic: &str, payload: &[u8]) {
+        let Some(reason) = std::str::from_utf8(payload)
+            .ok()
+            .and_then(|string| serde_json5::from_str::<serde_json::Value>(string).ok())
+            .and_then(|value| {
+                mavlink_failsafe::indication(topic, &value, &config::get().failsafe_statustext)
+            })
+        else {
+            return;
+        };
+        if !self.failsafe_trigger.indicate() {
+            return;
+        }
+
+        warn!(reason, "Vehicle failsafe, forcing the recording");
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64;
+        self.write_event(
+            timestamp,
+            events::event(
+                "failsafe",
+                "Vehicle failsafe, recording forced",
+                json!({ "reason": reason, "topic": topic }),
+            ),
+        );
+        self.refresh_recording(failsafe::FAILSAFE);
+    }
+
+    /// Flushes and syncs the recording as soon as a leak is reported, the data up to the incident
+    /// then survives a flooded electronics tray
+    fn handle_leak(&mut self, topic: &str) {
+        error!("Leak detected, flushing the recording");
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64;
+        self.write_event(
+            timestamp,
+            events::event(
+                "leak",
+                "Leak detected, recording flushed",
+                json!({ "topic": topic }),
+            ),
+        );
+        if let Err(error) = self.mcap.flush() {
+            error!(%error, "Failed to flush MCAP writer");
+        }
+    }
+
+    /// Feeds the dive profile of the file from GLOBAL_POSITION_INT, and stores the first valid
+    /// position of the session as its launch position
+    fn handle_position(&mut self, payload: &[u8]) {
+        let Some(value) = std::str::from_utf8(payload)
+            .ok()
+            .and_then(|string| serde_json5::from_str::<serde_json::Value>(string).ok())
+        else {
+            return;
+        };
+        self.dive.update(&value);
+        if self.recording_session.launch_position.is_some() {
+            return;
+        }
+        let Some(position) = position::position(&value) else {
+            return;
+        };
+
+        info!(?position, "Launch position");
+        self.recording_session.launch_position = Some(position);
+        if let Err(error) = self
+            .mcap
+            .write_metadata(LAUNCH_POSITION_METADATA, position.metadata())
+        {
+            error!(%error, "Failed to write launch position metadata");
+        }
+        if let Some(path) = self.mcap.path()
+            && let Err(error) = Manifest::load(path).and_then(|mut manifest| {
+                manifest.launch_position = Some(position);
+                manifest.save(path)
+            })
+        {
+            warn!(%error, "Failed to add the launch position to the manifest");
+        }
+    }
+
+    /// Keeps the flight software version of an AUTOPILOT_VERSION payload for the README
+    fn handle_version(&mut self, topic: &str, payload: &[u8]) {
+        let Some(version) = std::str::from_utf8(payload)
+            .ok()
+            .and_then(|string| serde_json5::from_str::<serde_json::Value>(string).ok())
+            .and_then(|value| version::firmware_version(&value))
+        else {
+            return;
+        };
+        if self.firmware.get(topic) != Some(&version) {
+            info!(topic, version, "Autopilot firmware version");
+            self.firmware.insert(topic.to_owned(), version);
+        }
+    }
+
+    /// Toggles the low power mode and the failsafe from a BATTERY_STATUS or SYS_STATUS payload,
+    /// BATTERY_STATUS also feeds the battery statistics of the file
+    fn handle_battery(&mut self, topic: &str, payload: &[u8]) {
+        let Some(value) = std::str::from_utf8(payload)
+            .ok()
+            .and_then(|string| serde_json5::from_str::<serde_json::Value>(string).ok())
+        else {
+            return;
+        };
+        let remaining_percent = battery::remaining_percent(&value);
+        let voltage = battery::voltage(&value);
+        if battery::is_battery_status_topic(topic) {
+            self.battery.update(voltage, battery::current(&value));
+        }
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64;
+
+        if let Some(remaining_percent) = remaining_percent
+            && self.low_power.update_battery(remaining_percent).is_some()
+        {
+            self.on_low_power_changed(
+                timestamp,
+                json!({ "reason": "battery", "battery_remaining": remaining_percent }),
+            );
+        }
+
+        if self.battery_failsafe.update(remaining_percent, voltage) {
+            warn!(
+                ?remaining_percent,
+                ?voltage,
+                "Battery is low, finalizing the recording file"
+            );
+            self.write_event(
+                timestamp,
+                events::event(
+                    "battery_failsafe",
+                    "Battery is low, recording file finalized",
+                    json!({ "battery_remaining": remaining_percent, "voltage": voltage }),
+                ),
+            );
+            if let Err(error) = self.rotate() {
+                error!(%error, "Failed to finalize the recording file");
+            }
+        }
+    }
+
+    fn on_low_power_changed(&mut self, timestamp: u64, details: serde_json::Value) {
+        let active = self.low_power.is_active();
+        info!(active, %details, "Low power mode changed");
+        let (name, message) = if active {
+            ("low_power_on", "Low power mode enabled")
+        } else {
+            ("low_power_off", "Low power mode disabled")
+        };
+        self.write_event(timestamp, events::event(name, message, details));
+    }
+
+    /// Records the dropouts of the link to the vehicle router in mirror mode, and backfills them
+    /// from the vehicle storages of the --backfill key expressions once it is back
+    async fn check_link(&mut self) {
+        let connected = self.session.info().routers_zid().await.next().is_some();
+        let Some(change) = self.link.as_mut().and_then(|link| link.update(connected)) else {
+            return;
+        };
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64;
+        match change {
+            LinkChange::Lost => {
+                warn!("Lost the link to the vehicle router");
+                journal::record("link_lost", json!({}));
+                self.write_event(
+                    timestamp,
+                    events::event("link_lost", "Lost the link to the vehicle", json!({})),
+                );
+            }
+            LinkChange::Restored { outage } => {
+                info!(outage = ?outage, "Link to the vehicle router restored");
+                let details = json!({ "outage_seconds": outage.as_secs_f64() });
+                journal::record("link_restored", details.clone());
+                self.write_event(
+                    timestamp,
+                    events::event("link_restored", "Link to the vehicle restored", details),
+                );
+                // Only the configured storages are queried, a `**` query would reach every
+                // queryable of the vehicle over the tether
+                let key_exprs = cli::backfill();
+                if self.is_recording_active() && !key_exprs.is_empty() {
+                    // Rounded up as the storages are queried with a whole number of seconds
+                    backfill::spawn(
+                        self.session.clone(),
+                        key_exprs,
+                        outage + std::time::Duration::from_secs(1),
+                        self.source_sender.clone(),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Records a message from a secondary source, going through the same gates as zenoh samples
+    #[instrument(skip_all)]
+    fn write_source_message(&mut self, message: SourceMessage) {
+        let (topic, schema, value) = match message {
+            SourceMessage::Encoded {
+                topic,
+                encoding,
+                publish_time,
+                payload,
+            } => {
+                self.write_encoded(&topic, &encoding, publish_time, &payload);
+                return;
+            }
+            SourceMessage::Json { topic, value } => (topic, None, value),
+            SourceMessage::JsonWithSchema {
+                topic,
+                schema_name,
+                schema,
+                value,
+            } => (topic, Some((schema_name, schema)), value),
+            SourceMessage::Mavlink { header, message } => {
+                if let Some(state) =
+                    crate::mavlink::arm_state_change(&header, &message, &mut self.vehicle_arm)
+                {
+                    self.on_arm_state_changed(state);
+                }
+                let (topic, value) = crate::mavlink::to_json(&header, &message);
+                (topic, None, value)
+            }
+        };
+
+        let is_command = command::is_command_topic(&topic);
+        if is_command {
+            self.write_command(&topic, &value);
+        }
+
+        let settings = topics::resolve(config::get(), &topic);
+        if !self.should_record_sample(&topic)
+            || !settings.enabled
+            || !(is_command || self.low_power.should_record(&topic))
+            || !self.rate_limiter.should_record(&topic, settings.rate_limit)
+        {
+            return;
+        }
+
+        // A transformed value no longer follows the known schema, its schema is inferred instead
+        let (schema, value) = match settings.transform {
+            Some((_, transform)) => (None, transform.apply(value)),
+            None => (schema, value),
+        };
+        let channel = settings.rename.unwrap_or(&topic);
+        let log_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64;
+        let result = match schema {
+            Some((schema_name, schema)) => self.mcap.write_json_with_schema(
+                channel,
+                schema_name,
+                schema,
+                log_time,
+                log_time,
+                &value,
+            ),
+            None => self
+                .mcap
+                .write_json(channel, None, log_time, log_time, &value),
+        };
+        match result {
+            Ok(size) => self.stats.record(channel, size),
+            Err(error) => error!(%error, %topic, "Failed to write source message"),
+        }
+    }
+
+    /// Time of a sample from the first available clock source of its topic
+    fn publish_time(
+        &self,
+        sample: &Sample,
+        payload: &[u8],
+        clock: &[ClockSource],
+        log_time: u64,
+    ) -> u64 {
+        clock
+            .iter()
+            .find_map(|source| match source {
+                ClockSource::Hlc => sample.timestamp().map(|ts| ts.get_time().as_nanos()),
+                ClockSource::Mavlink => std::str::from_utf8(payload)
+                    .ok()
+                    .and_then(|string| serde_json5::from_str::<serde_json::Value>(string).ok())
+                    .and_then(|value| self.time_sync.autopilot_time_ns(&value)),
+                ClockSource::Local => Some(log_time),
+            })
+            .unwrap_or(log_time)
+    }
+
+    /// Describes the policies altering the samples of a topic, written in its channel metadata so
+    /// readers know why the recorded rate or content differs from what was published
+    fn channel_policies(
+        &self,
+        topic: &str,
+        encoding: &Encoding,
+        settings: &TopicSettings,
+    ) -> BTreeMap<String, String> {
+        let mut policies = BTreeMap::new();
+        if settings.rename.is_some() {
+            policies.insert("key_expr".to_owned(), topic.to_owned());
+        }
+        if encoding.to_string().starts_with("application/json")
+            && let Some((_, transform)) = settings.transform
+            && let Ok(transform) = serde_json::to_string(transform)
+        {
+            policies.insert("transform".to_owned(), transform);
+        }
+        if settings.clock != DEFAULT_CLOCK {
+            let clock: Vec<_> = settings
+                .clock
+                .iter()
+                .map(|source| source.as_str())
+                .collect();
+            policies.insert("clock".to_owned(), clock.join(","));
+        }
+
+        // Commands are never dropped nor downsampled
+        if command::is_command_topic(topic) {
+            return policies;
+        }
+        policies.insert("priority".to_owned(), settings.priority.as_str().to_owned());
+        if let Some(rate_limit) = settings.rate_limit {
+            policies.insert("rate_limit".to_owned(), rate_limit.to_string());
+        }
+        if let Some(min_period) = self.low_power.min_period() {
+            policies.insert(
+                "low_power_min_period_ms".to_owned(),
+                min_period.as_millis().to_string(),
+            );
+        }
+        policies
+    }
+
+    /// Records an encoded message from a secondary source, e.g: a backfilled sample, with the same
+    /// gates, transforms and channels as the zenoh samples
+    fn write_encoded(
+        &mut self,
+        topic: &str,
+        encoding: &Encoding,
+        publish_time: u64,
+        payload: &[u8],
+    ) {
+        let settings = topics::resolve(config::get(), topic);
+        if !self.should_record_sample(topic)
+            || !settings.enabled
+            || !self.low_power.should_record(topic)
+            || !self.rate_limiter.should_record(topic, settings.rate_limit)
+        {
+            return;
+        }
+
+        let log_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64;
+        self.write_payload(
+            topic,
+            encoding,
+            Cow::Borrowed(payload),
+            &settings,
+            log_time,
+            publish_time,
+            None,
+        );
+    }
+
+    /// Transforms `payload` and records it on the channel of `topic`, registering the channel and
+    /// its schema on first use. The zenoh samples and the encoded source messages both go through
+    /// it, so a channel holds the same content whichever way its messages arrived. Returns the
+    /// recorded payload, `None` when it was dropped
+    #[allow(clippy::too_many_arguments)]
+    fn write_payload<'a>(
+        &mut self,
+        topic: &str,
+        encoding: &Encoding,
+        payload: Cow<'a, [u8]>,
+        settings: &TopicSettings,
+        log_time: u64,
+        publish_time: u64,
+        sequence: Option<u32>,
+    ) -> Option<Cow<'a, [u8]>> {
+        let payload = if encoding.to_string().starts_with("application/json") {
+            // Wrapped first, so the transforms address the wrapped value as `/value`
+            let mut payload = payload;
+            if self.non_object_json == NonObjectJson::Wrap {
+                payload = transform::wrap_non_object(payload);
+            }
+            transform::apply(settings.transform.map(|(_, transform)| transform), payload)
+        } else {
+            payload
+        };
+
+        let decoded = match self.decode_cdr(encoding, &payload) {
+            Some((schema_name, Ok(value))) => {
+                (self.cdr_to_json != CdrToJson::Off).then_some((schema_name, value))
+            }
+            Some((schema_name, Err(error))) => {
+                if let Some(occurrences) = self.warnings.record(topic, "cdr") {
+                    warn!(
+                        %error,
+                        %schema_name,
+                        payload_size = payload.len(),
+                        occurrences,
+                        "CDR payload is inconsistent with its schema"
+                    );
+                }
+                if self.invalid_cdr == InvalidCdr::Divert {
+                    self.write_diagnostic(topic, &schema_name, &payload, &error, log_time);
+                    return None;
+                }
+                None
+            }
+            None => None,
+        };
+        // When replacing, CDR samples that failed to decode are dropped instead of
+        // registering a CDR channel on a topic meant to hold JSON
+        let replaced =
+            self.cdr_to_json == CdrToJson::Replace && cdr_schema_name(encoding).is_some();
+
+        // Renamed topics are recorded on their configured channel
+        let channel = settings.rename.unwrap_or(topic);
+        if !replaced {
+            let new_channel = if self.mcap.has_channel(channel) {
                 None
             } else {
-                let Some(channel_descriptor) =
-                    ChannelDescriptor::new(topic, encoding, payload, self.schema_path.as_ref())
-                else {
-                    warn!("Failed creating a channel descriptor");
-                    continue;
+                let channel_descriptor = if self.record_raw_mavlink
+                    && topic.starts_with(RAW_MAVLINK_TOPIC_PREFIX)
+                {
+                    Ok(ChannelDescriptor::raw_mavlink(channel))
+                } else if let Some(cached) = self.schema_cache.get(channel, encoding, &payload) {
+                    Ok(cached)
+                } else {
+                    ChannelDescriptor::new(channel, encoding, &payload, self.schema_path.as_ref())
+                        .inspect(|channel_descriptor| self.schema_cache.insert(channel_descriptor))
+                };
+                if let Some(dry_run) = &mut self.dry_run {
+                    dry_run.resolve(topic, channel_descriptor.as_ref().ok());
+                }
+                let mut channel_descriptor = match channel_descriptor {
+                    Ok(channel_descriptor) => channel_descriptor,
+                    Err(error) => {
+                        if let Some(occurrences) = self.warnings.record(topic, "channel") {
+                            warn!(%error, occurrences, "Failed creating a channel descriptor");
+                        }
+                        return None;
+                    }
                 };
+                channel_descriptor.metadata = self.channel_policies(topic, encoding, settings);
+                if self.large_integers_as_strings {
+                    self.stringify_schema(&mut channel_descriptor);
+                }
 
                 info!(schema_name = %channel_descriptor.schema_name, "Adding schema");
                 Some(channel_descriptor)
             };
 
-            let now = SystemTime::now();
-            let log_time = now.duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64;
-            let publish_time = sample
-                .timestamp()
-                .map(|ts| ts.get_time().as_nanos())
-                .unwrap_or(log_time);
+            // The integers are stringified after the schema inference, following its formats
+            let payload = match self.stringified_schemas.get(channel) {
+                Some(schema) => {
+                    transform::stringify_large_integers(Cow::Borrowed(&payload), schema)
+                }
+                None => Cow::Borrowed(&*payload),
+            };
             if let Err(error) = self.mcap.write_message(
-                topic,
+                channel,
                 log_time,
                 publish_time,
-                &payload.to_bytes(),
+                sequence,
+                &payload,
                 new_channel,
             ) {
                 error!(%error, "Failed to write MCAP message");
-                continue;
+                self.handle_write_error(&error);
+                return None;
+            }
+            self.stats.record(channel, payload.len());
+        }
+
+        if let Some((schema_name, value)) = decoded {
+            let json_topic = match self.cdr_to_json {
+                CdrToJson::Replace => channel.to_owned(),
+                _ => format!("{channel}/json"),
+            };
+            match self.mcap.write_json(
+                &json_topic,
+                Some(&schema_name),
+                log_time,
+                publish_time,
+                &value,
+            ) {
+                Ok(size) => self.stats.record(&json_topic, size),
+                Err(error) => error!(%error, "Failed to write decoded CDR message"),
             }
+        }
 
-            if now.duration_since(last_flush).unwrap() > std::time::Duration::from_secs(30) {
-                if let Err(error) = self.mcap.flush() {
-                    error!(%error, "Failed to flush MCAP writer");
+        Some(payload)
+    }
+
+    /// Records a command or acknowledgement on the commands channel, summarized as an event
+    #[instrument(skip_all)]
+    fn write_command(&mut self, topic: &str, value: &serde_json::Value) {
+        if self.paused_since.is_some() {
+            return;
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64;
+        let record = json!({ "topic": topic, "payload": value });
+        match self
+            .mcap
+            .write_json(command::COMMANDS_TOPIC, None, timestamp, timestamp, &record)
+        {
+            Ok(size) => self.stats.record(command::COMMANDS_TOPIC, size),
+            Err(error) => error!(%error, "Failed to write command"),
+        }
+
+        if let Some((message, details)) = command::summary(topic, value) {
+            info!(%message, "Command");
+            self.write_event(timestamp, events::event("command", &message, details));
+        }
+    }
+
+    #[instrument(skip_all)]
+    fn write_gap(&mut self, gap: serde_json::Value) {
+        debug!(%gap, "Gap detected");
+        self.stats.record_gap();
+        if self.paused_since.is_some() {
+            return;
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64;
+        match self
+            .mcap
+            .write_json(GAPS_TOPIC, None, timestamp, timestamp, &gap)
+        {
+            Ok(size) => self.stats.record(GAPS_TOPIC, size),
+            Err(error) => error!(%error, "Failed to write gap"),
+        }
+    }
+
+    fn write_tombstone(&mut self, topic: &str, timestamp: Option<&zenoh::time::Timestamp>) {
+        let log_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64;
+        let publish_time = timestamp
+            .map(|ts| ts.get_time().as_nanos())
+            .unwrap_or(log_time);
+        match self.mcap.write_json(
+            DELETES_TOPIC,
+            None,
+            log_time,
+            publish_time,
+            &deletes::tombstone(topic, publish_time),
+        ) {
+            Ok(size) => self.stats.record(DELETES_TOPIC, size),
+            Err(error) => error!(%error, "Failed to write tombstone"),
+        }
+    }
+
+    /// Writes a recorder lifecycle event on the events channel
+    #[instrument(skip_all)]
+    fn write_event(&mut self, timestamp: u64, event: serde_json::Value) {
+        let topic = events::key();
+        match self.mcap.write_json_with_schema(
+            &topic,
+            events::EVENT_SCHEMA,
+            events::schema,
+            timestamp,
+            timestamp,
+            &event,
+        ) {
+            Ok(size) => self.stats.record(&topic, size),
+            Err(error) => error!(%error, "Failed to write event"),
+        }
+    }
+
+    #[instrument(skip_all)]
+    async fn report_progress(&mut self) {
+        let queue_capacity = self.subscriber.capacity();
+        let mut report = self.stats.report(self.subscriber.len(), queue_capacity);
+        report["paused"] = json!(self.paused_since.is_some());
+        report["low_power"] = json!(self.low_power.is_active());
+        report["log_times_clamped"] = json!(self.mcap.take_clamped_log_times());
+        report["alert"] = json!(self.storage_alert);
+        report["warnings"] = self.warnings.summarize();
+        report["queue_alarm"] = self.queue_alarm.status();
+        // Repeated so a GCS connecting mid-recording shows the state too
+        if let Some(announcer) = &mut self.announcer {
+            announcer.recording(self.recording_active);
+        }
+        report["write"] = self
+            .write_health
+            .report(self.mcap.message_bytes(), self.mcap.file_bytes());
+        info!(
+            messages_per_second = %report["messages_per_second"],
+            megabytes_written = %report["megabytes_written"],
+            queue_depth = %report["queue_depth"],
+            queue_high_watermark = %report["queue_high_watermark"],
+            messages_dropped = %report["messages_dropped"],
+            gaps = %report["gaps"],
+            log_times_clamped = %report["log_times_clamped"],
+            top_topics = %report["top_topics"],
+            "Recording progress"
+        );
+        if let Some(capacity) = queue_capacity
+            && report["queue_high_watermark"].as_u64() >= Some(capacity as u64)
+        {
+            warn!(
+                capacity,
+                "Subscriber queue was full, zenoh was blocked and samples may have been lost"
+            );
+        }
+
+        if let Err(error) = self
+            .session
+            .put(
+                format!("{}/{STATUS_TOPIC}", cli::instance_prefix()),
+                report.to_string(),
+            )
+            .encoding(Encoding::APPLICATION_JSON)
+            .allowed_destination(cli::own_keys_destination())
+            .await
+        {
+            warn!(%error, "Failed to publish recorder status");
+        }
+    }
+
+    /// Mirrors STATUSTEXT messages into a foxglove.Log channel
+    #[instrument(skip_all)]
+    fn write_statustext_log(&mut self, topic: &str, payload: &[u8], publish_time: u64) {
+        let Some(log) = std::str::from_utf8(payload)
+            .ok()
+            .and_then(|string| serde_json5::from_str::<serde_json::Value>(string).ok())
+            .and_then(|value| statustext::to_log(topic, &value, publish_time))
+        else {
+            warn!("Failed to convert STATUSTEXT message to a log message");
+            return;
+        };
+
+        match self.mcap.write_json_with_schema(
+            statustext::LOG_TOPIC,
+            foxglove_schemas::LOG,
+            foxglove_schemas::log_schema,
+            publish_time,
+            publish_time,
+            &log,
+        ) {
+            Ok(size) => self.stats.record(statustext::LOG_TOPIC, size),
+            Err(error) => error!(%error, "Failed to write STATUSTEXT log message"),
+        }
+    }
+
+    /// Mirrors the named debug values of the autopilot into per-name channels
+    #[instrument(skip_all)]
+    fn write_named_value(&mut self, topic: &str, payload: &[u8], publish_time: u64) {
+        let Some(named_value) = std::str::from_utf8(payload)
+            .ok()
+            .and_then(|string| serde_json5::from_str::<serde_json::Value>(string).ok())
+            .and_then(|value| named_value::to_named_value(topic, &value))
+        else {
+            debug!(topic, "Failed to convert named value message");
+            return;
+        };
+
+        match self.mcap.write_json_with_schema(
+            &named_value.topic,
+            named_value.schema_name,
+            named_value.schema,
+            publish_time,
+            publish_time,
+            &named_value.value,
+        ) {
+            Ok(size) => self.stats.record(&named_value.topic, size),
+            Err(error) => error!(%error, "Failed to write named value message"),
+        }
+    }
+
+    /// Mirrors the pressure sensors into channels in engineering units, with the units in their
+    /// metadata
+    #[instrument(skip_all)]
+    fn write_pressure(&mut self, topic: &str, payload: &[u8], publish_time: u64) {
+        let Some((channel, value)) = std::str::from_utf8(payload)
+            .ok()
+            .and_then(|string| serde_json5::from_str::<serde_json::Value>(string).ok())
+            .and_then(|value| sensors::to_pressure(topic, &value))
+        else {
+            debug!(topic, "Failed to convert pressure message");
+            return;
+        };
+
+        let new_channel = (!self.mcap.has_channel(&channel)).then(|| {
+            let mut descriptor = ChannelDescriptor::with_json_schema(
+                &channel,
+                sensors::PRESSURE_SCHEMA,
+                &sensors::pressure_schema(),
+            );
+            descriptor.metadata = sensors::pressure_metadata(topic);
+            descriptor
+        });
+        let payload = value.to_string().into_bytes();
+        match self.mcap.write_message(
+            &channel,
+            publish_time,
+            publish_time,
+            None,
+            &payload,
+            new_channel,
+        ) {
+            Ok(()) => self.stats.record(&channel, payload.len()),
+            Err(error) => error!(%error, "Failed to write pressure message"),
+        }
+    }
+
+    #[instrument(skip_all)]
+    fn write_time_sync(&mut self, payload: &[u8], log_time: u64) {
+        let Some(mapping) = std::str::from_utf8(payload)
+            .ok()
+            .and_then(|string| serde_json5::from_str::<serde_json::Value>(string).ok())
+            .and_then(|value| self.time_sync.update(&value, log_time))
+        else {
+            return;
+        };
+
+        match self
+            .mcap
+            .write_json(TIME_SYNC_TOPIC, None, log_time, log_time, &mapping)
+        {
+            Ok(size) => self.stats.record(TIME_SYNC_TOPIC, size),
+            Err(error) => error!(%error, "Failed to write time sync message"),
+        }
+    }
+
+    /// Decodes CDR payloads when either JSON decoding or validation is enabled,
+    /// returning the schema name and the decoding result. Samples whose schema is unavailable,
+    /// e.g: still being fetched from its publisher, are not decoded and recorded unchanged
+    fn decode_cdr(
+        &mut self,
+        encoding: &zenoh::bytes::Encoding,
+        payload: &[u8],
+    ) -> Option<(String, anyhow::Result<serde_json::Value>)> {
+        if self.cdr_to_json == CdrToJson::Off && self.invalid_cdr == InvalidCdr::Ignore {
+            return None;
+        }
+
+        let schema_name = cdr_schema_name(encoding)?;
+        if let Err(error) = self.cdr_decoder.resolve(&schema_name) {
+            debug!(%error, %schema_name, "CDR schema unavailable, not decoding");
+            return None;
+        }
+        let decoded = self.cdr_decoder.decode(&schema_name, payload);
+        Some((schema_name, decoded))
+    }
+
+    #[instrument(skip_all)]
+    fn write_diagnostic(
+        &mut self,
+        topic: &str,
+        schema_name: &str,
+        payload: &[u8],
+        error: &anyhow::Error,
+        log_time: u64,
+    ) {
+        let diagnostic = serde_json::json!({
+            "topic": topic,
+            "schema_name": schema_name,
+            "payload_size": payload.len(),
+            "error": error.to_string(),
+            "payload": payload.iter().map(|byte| format!("{byte:02x}")).collect::<String>(),
+        });
+
+        let diagnostics_topic = format!("{}/{DIAGNOSTICS_TOPIC}", cli::instance_prefix());
+        match self
+            .mcap
+            .write_json(&diagnostics_topic, None, log_time, log_time, &diagnostic)
+        {
+            Ok(size) => self.stats.record(&diagnostics_topic, size),
+            Err(error) => error!(%error, "Failed to write diagnostic message"),
+        }
+    }
+
+    fn on_arm_state_changed(&mut self, state: ArmState) {
+        info!(?state, "Vehicle arm state changed");
+        let armed = state == ArmState::Armed;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64;
+        // The disarm event ends the file of the dive, the arm event starts the next one
+        if !armed {
+            self.write_event(
+                timestamp,
+                events::event("disarm", "Vehicle disarmed", json!({})),
+            );
+        }
+        // The armed state is only a condition of the trigger when configured
+        match &mut self.trigger {
+            Some(trigger) => {
+                if let Some(active) = trigger.set_armed(armed) {
+                    self.on_trigger_changed(trigger::ARMED, active);
                 }
-                last_flush = now;
             }
+            None => self.refresh_recording(trigger::ARMED),
         }
+        if armed {
+            let arm_count = self.counters.record_arm();
+            info!(arm_count, "Vehicle armed");
+            self.write_event(
+                timestamp,
+                events::event("arm", "Vehicle armed", json!({ "arm_count": arm_count })),
+            );
+        }
+    }
+
+    /// `cause` is the topic or builtin condition that changed the trigger state
+    fn on_trigger_changed(&mut self, cause: &str, active: bool) {
+        info!(cause, active, "Recording trigger changed");
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64;
+        let (name, message) = if active {
+            ("trigger_on", "Recording trigger activated")
+        } else {
+            ("trigger_off", "Recording trigger deactivated")
+        };
+        self.write_event(
+            timestamp,
+            events::event(name, message, json!({ "cause": cause })),
+        );
+        self.refresh_recording(cause);
+    }
 
-        if let Err(error) = self.mcap.finish() {
-            error!(%error, "Failed to finish MCAP writer");
+    /// Starts or stops the recording when the trigger and the failsafes change its state
+    fn refresh_recording(&mut self, cause: &str) {
+        let active = self.is_recording_active();
+        if active != self.recording_active {
+            self.recording_active = active;
+            if !active && let Some(timeout) = cli::session_gap_timeout() {
+                info!(?timeout, cause, "Recording stopped, holding the session");
+                self.pending_stop = Some((tokio::time::Instant::now() + timeout, cause.to_owned()));
+                return;
+            }
+            self.on_recording_changed(active, cause);
         }
+    }
 
-        Ok(())
+    /// Each active period of the recording condition is recorded as its own session, a restart
+    /// within the session gap timeout continues the previous one
+    fn on_recording_changed(&mut self, active: bool, cause: &str) {
+        let resumed = active && self.pending_stop.take().is_some();
+        if resumed {
+            info!(
+                session_id = %self.recording_session.id,
+                "Recording restarted within the session gap timeout, continuing the session"
+            );
+        } else if let Err(error) = self.start_session(active) {
+            error!(%error, "Failed to start a new recording session");
+            journal::record(
+                "error",
+                json!({
+                    "message": "Failed to start a new recording session",
+                    "error": error.to_string(),
+                }),
+            );
+            hooks::on_error(&format!("Failed to start a new recording session: {error}"));
+            self.announce_error("Failed to start recording");
+        }
+        journal::record(
+            match (active, resumed) {
+                (true, true) => "resume",
+                (true, false) => "start",
+                (false, _) => "stop",
+            },
+            json!({
+                "cause": cause,
+                "session_id": self.recording_session.id.to_string(),
+                "file": self.mcap.path(),
+            }),
+        );
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64;
+        self.trigger_history.push(
+            timestamp,
+            active,
+            cause,
+            &self.recording_session.id.to_string(),
+        );
+        self.arm_snapshot_pending = active && self.arm_snapshot_topic.is_some();
+        if let Some(announcer) = &mut self.announcer {
+            announcer.recording(active);
+            if !active {
+                announcer.info("Recording stopped");
+            } else if !resumed {
+                announcer.info(&format!(
+                    "Recording started, session {}",
+                    self.recording_session.id
+                ));
+            }
+        }
+
+        let backfill = cli::backfill();
+        if active && !resumed && !backfill.is_empty() {
+            backfill::spawn(
+                self.session.clone(),
+                backfill,
+                cli::backfill_history(),
+                self.source_sender.clone(),
+            );
+        }
+    }
+
+    /// Returns true while the recording condition holds: the trigger if configured, or the
+    /// vehicle armed state, or a recent vehicle failsafe. Always true with an explicit output
+    fn is_recording_active(&self) -> bool {
+        if cli::output().is_some() {
+            return true;
+        }
+        let active = match &self.trigger {
+            Some(trigger) => trigger.is_active(),
+            None => self.vehicle_arm.is_armed(),
+        };
+        active || self.failsafe_trigger.is_active()
+    }
+
+    /// Returns true for JPEG frames published on the arm snapshot topic
+    fn is_arm_snapshot(&self, topic: &str, encoding: &Encoding, payload: &[u8]) -> bool {
+        let Some(snapshot_topic) = &self.arm_snapshot_topic else {
+            return false;
+        };
+
+        zenoh::key_expr::keyexpr::new(topic).is_ok_and(|topic| snapshot_topic.includes(topic))
+            && (*encoding == Encoding::IMAGE_JPEG || payload.starts_with(&[0xFF, 0xD8, 0xFF]))
     }
 
     fn should_record_sample(&self, topic: &str) -> bool {
+        if self.paused_since.is_some() || self.quota.is_exceeded() {
+            return false;
+        }
+
         if topic.starts_with("mavlink/")
-            || topic.starts_with("mavlink_raw/")
+            || topic.starts_with(RAW_MAVLINK_TOPIC_PREFIX)
             || topic.starts_with("video/")
         {
-            self.vehicle_arm.is_armed()
+            self.is_recording_active()
         } else {
             true
         }
@@ -0,0 +1,190 @@
+use std::{
+    fs::File,
+    io::{self, Seek, SeekFrom, Write},
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::*;
+
+use crate::{hooks, journal, stream};
+
+/// Destination of the bytes of a recording file
+pub trait Sink: Send {
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()>;
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Moves the write position, only the sinks written in place, e.g: files, can move backwards
+    fn seek(&mut self, position: u64) -> io::Result<()>;
+}
+
+/// Additional destination of the recordings, configured in the `sinks` table of the configuration
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SinkConfig {
+    /// Copy of the recordings in another directory, e.g: a USB drive
+    Directory { path: PathBuf },
+    /// Mirror of the file being written to the TCP clients, e.g: `tcp://0.0.0.0:9000`
+    Stream { address: String },
+}
+
+impl SinkConfig {
+    /// Prepares the destination, done once at startup
+    pub fn start(&self) -> Result<()> {
+        match self {
+            Self::Directory { path } => std::fs::create_dir_all(path)
+                .with_context(|| format!("Failed to create sink directory {path:?}")),
+            Self::Stream { address } => stream::listen(address),
+        }
+    }
+
+    /// Streaming destinations cannot seek back to patch what they already sent
+    pub fn is_stream(&self) -> bool {
+        matches!(self, Self::Stream { .. })
+    }
+}
+
+/// Writes every byte to all its sinks. The first one is the primary output, whose errors fail the
+/// recording, the others are dropped on error so a removed USB drive does not stop it. When
+/// redundant, the primary output is dropped as well and only the failure of the last sink fails
+/// the recording. Without any sink the bytes are discarded, only the position is tracked for the
+/// writer seeks
+pub struct FanOut {
+    sinks: Vec<Box<dyn Sink>>,
+    redundant: bool,
+    position: u64,
+    len: u64,
+}
+
+impl FanOut {
+    pub fn new(sinks: Vec<Box<dyn Sink>>, redundant: bool) -> Self {
+        Self {
+            sinks,
+            redundant,
+            position: 0,
+            len: 0,
+        }
+    }
+
+    fn each(
+        &mut self,
+        mut operation: impl FnMut(&mut dyn Sink) -> io::Result<()>,
+    ) -> io::Result<()> {
+        if !self.redundant
+            && let Some(primary) = self.sinks.first_mut()
+        {
+            operation(primary.as_mut())?;
+        }
+
+        let mut index = usize::from(!self.redundant);
+        while index < self.sinks.len() {
+            if let Err(error) = operation(self.sinks[index].as_mut()) {
+                if self.sinks.len() == 1 {
+                    return Err(error);
+                }
+                warn!(%error, "Recording sink failed, dropping it");
+                journal::record(
+                    "error",
+                    json!({
+                        "message": "Recording sink failed, dropping it",
+                        "error": error.to_string(),
+                    }),
+                );
+                hooks::on_error(&format!("Recording sink failed, dropping it: {error}"));
+                self.sinks.remove(index);
+            } else {
+                index += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Write for FanOut {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.each(|sink| sink.write_all(buf))?;
+        self.position += buf.len() as u64;
+        self.len = self.len.max(self.position);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.each(|sink| sink.flush())
+    }
+}
+
+impl Seek for FanOut {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        }
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid seek"))?;
+
+        if position != self.position {
+            self.each(|sink| sink.seek(position))?;
+            self.position = position;
+        }
+        Ok(position)
+    }
+}
+
+impl Sink for File {
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        Write::write_all(self, buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Write::flush(self)
+    }
+
+    fn seek(&mut self, position: u64) -> io::Result<()> {
+        Seek::seek(self, SeekFrom::Start(position)).map(|_| ())
+    }
+}
+
+/// Pipes the recordings, stdout cannot seek
+pub struct Stdout(pub io::Stdout);
+
+impl Sink for Stdout {
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.0.write_all(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+
+    fn seek(&mut self, _position: u64) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Stdout cannot seek",
+        ))
+    }
+}
+
+/// Keeps the recording in memory, shared with the test reading it back
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub struct Memory(pub std::sync::Arc<std::sync::Mutex<io::Cursor<Vec<u8>>>>);
+
+#[cfg(test)]
+impl Sink for Memory {
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.0.lock().unwrap().write_all(buf)
+    }
+
+    fn seek(&mut self, position: u64) -> io::Result<()> {
+        self.0
+            .lock()
+            .unwrap()
+            .seek(SeekFrom::Start(position))
+            .map(|_| ())
+    }
+}
@@ -0,0 +1,90 @@
+use std::{process::Stdio, time::Duration};
+
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio_graceful_shutdown::SubsystemHandle;
+use tracing::*;
+
+use super::{SourceMessage, SourceSender};
+
+const RESTART_DELAY: Duration = Duration::from_secs(5);
+
+/// External command whose stdout lines are recorded as JSON messages, an escape hatch for
+/// sensors without zenoh or MQTT integration
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CommandSource {
+    /// Channel holding the command output
+    pub topic: String,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+/// Runs the commands until shutdown, restarting them when they exit
+#[instrument(skip_all)]
+pub async fn run(
+    commands: Vec<CommandSource>,
+    sender: SourceSender,
+    subsystem: &mut SubsystemHandle,
+) -> anyhow::Result<()> {
+    let mut tasks = tokio::task::JoinSet::new();
+    for command in commands {
+        let sender = sender.clone();
+        tasks.spawn(async move {
+            loop {
+                if let Err(error) = capture(&command, &sender).await {
+                    warn!(%error, command = %command.command, "External command failed");
+                }
+                if sender.is_closed() {
+                    break;
+                }
+                tokio::time::sleep(RESTART_DELAY).await;
+            }
+        });
+    }
+
+    // Dropping the set aborts the tasks, killing the commands
+    subsystem.on_shutdown_requested().await;
+    Ok(())
+}
+
+#[instrument(skip_all, fields(topic = %source.topic, command = %source.command))]
+async fn capture(source: &CommandSource, sender: &SourceSender) -> anyhow::Result<()> {
+    let mut child = tokio::process::Command::new(&source.command)
+        .args(&source.args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    info!("External command started");
+
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| anyhow::anyhow!("Command stdout not available"))?;
+    let mut lines = BufReader::new(stdout).lines();
+    while let Some(line) = lines.next_line().await? {
+        let value = match serde_json::from_str::<Value>(&line) {
+            Ok(value) if value.is_object() => value,
+            Ok(value) => json!({ "value": value }),
+            Err(error) => {
+                debug!(%error, line, "Skipping non JSON line");
+                continue;
+            }
+        };
+
+        let message = SourceMessage::Json {
+            topic: source.topic.clone(),
+            value,
+        };
+        if sender.send(message).await.is_err() {
+            return Ok(());
+        }
+    }
+
+    let status = child.wait().await?;
+    info!(%status, "External command exited");
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use ::mavlink::{MavConnection, MavHeader, ardupilotmega::MavMessage};
+use tokio_graceful_shutdown::SubsystemHandle;
+use tracing::*;
+
+use super::{SourceMessage, SourceSender};
+
+/// Delay before reconnecting after the connection failed
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Reads MAVLink from a serial port or UDP endpoint, e.g: `serial:/dev/ttyACM0:115200` or
+/// `udpin:0.0.0.0:14550`, for setups without the zenoh MAVLink bridge
+#[instrument(skip(sender, subsystem))]
+pub async fn run(
+    address: String,
+    sender: SourceSender,
+    subsystem: &mut SubsystemHandle,
+) -> anyhow::Result<()> {
+    // The connection is blocking, a detached thread avoids holding the runtime on shutdown
+    std::thread::Builder::new()
+        .name("mavlink-input".to_owned())
+        .spawn(move || read_loop(&address, &sender))?;
+
+    subsystem.on_shutdown_requested().await;
+    Ok(())
+}
+
+fn read_loop(address: &str, sender: &SourceSender) {
+    loop {
+        let connection = match ::mavlink::connect::<MavMessage>(address) {
+            Ok(connection) => connection,
+            Err(error) => {
+                warn!(%error, address, "Failed to open MAVLink connection");
+                std::thread::sleep(RECONNECT_DELAY);
+                continue;
+            }
+        };
+        info!(address, "Reading MAVLink");
+
+        if !forward(connection.as_ref(), sender) {
+            return;
+        }
+        std::thread::sleep(RECONNECT_DELAY);
+    }
+}
+
+/// Forwards messages until the connection fails, returns false once the recorder is gone
+fn forward(
+    connection: &(dyn MavConnection<MavMessage> + Send + Sync),
+    sender: &SourceSender,
+) -> bool {
+    loop {
+        let (header, message): (MavHeader, MavMessage) = match connection.recv() {
+            Ok(packet) => packet,
+            Err(::mavlink::error::MessageReadError::Io(error)) => {
+                warn!(%error, "MAVLink connection failed");
+                return true;
+            }
+            Err(error) => {
+                debug!("Failed decoding MAVLink message: {error:?}");
+                continue;
+            }
+        };
+
+        if sender
+            .blocking_send(SourceMessage::Mavlink {
+                header,
+                message: Box::new(message),
+            })
+            .is_err()
+        {
+            return false;
+        }
+    }
+}
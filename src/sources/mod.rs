@@ -0,0 +1,51 @@
+pub mod command;
+pub mod mavlink;
+pub mod mqtt;
+pub mod nmea;
+pub mod ping;
+pub mod queries;
+pub mod rest;
+
+use ::mavlink::{MavHeader, ardupilotmega::MavMessage};
+use serde_json::Value;
+use tokio::sync::mpsc;
+use zenoh::bytes::Encoding;
+
+/// Number of messages from secondary sources waiting to be recorded
+const SOURCE_QUEUE_SIZE: usize = 256;
+
+/// A message produced by a secondary source. The sources never hold the writer, their messages
+/// are queued to the recording service which registers the channels and writes them in the order
+/// received
+#[derive(Debug)]
+pub enum SourceMessage {
+    /// Recorded as a JSON channel
+    Json { topic: String, value: Value },
+    /// Recorded as a JSON channel with a known schema, e.g: Foxglove well-known types
+    JsonWithSchema {
+        topic: String,
+        schema_name: &'static str,
+        schema: fn() -> Value,
+        value: Value,
+    },
+    /// Recorded as is, the channel and its schema are resolved from the encoding as for zenoh
+    /// samples, e.g: video frames. `publish_time` is the capture time in nanoseconds
+    Encoded {
+        topic: String,
+        encoding: Encoding,
+        publish_time: u64,
+        payload: Vec<u8>,
+    },
+    /// Recorded on the per-field JSON `mavlink/...` topics, as done by the zenoh MAVLink bridge
+    Mavlink {
+        header: MavHeader,
+        message: Box<MavMessage>,
+    },
+}
+
+pub type SourceSender = mpsc::Sender<SourceMessage>;
+pub type SourceReceiver = mpsc::Receiver<SourceMessage>;
+
+pub fn channel() -> (SourceSender, SourceReceiver) {
+    mpsc::channel(SOURCE_QUEUE_SIZE)
+}
@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio_graceful_shutdown::SubsystemHandle;
+use tracing::*;
+
+use super::{SourceMessage, SourceSender};
+
+/// Prefix of the channels holding the MQTT messages, followed by the MQTT topic
+const MQTT_TOPIC_PREFIX: &str = "mqtt";
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+const REQUEST_QUEUE_SIZE: usize = 16;
+
+/// MQTT broker whose messages are recorded as JSON channels
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MqttSource {
+    /// Broker URL, e.g: `mqtt://192.168.2.10:1883?client_id=blueos-recorder`
+    pub url: String,
+    /// Topic filters to subscribe to, e.g: `sensors/#`
+    pub topics: Vec<String>,
+}
+
+/// Records the messages of the subscribed topics until shutdown
+#[instrument(skip_all, fields(url = %source.url))]
+pub async fn run(
+    source: MqttSource,
+    sender: SourceSender,
+    subsystem: &mut SubsystemHandle,
+) -> anyhow::Result<()> {
+    let options = MqttOptions::parse_url(&source.url)?;
+    let (client, mut event_loop) = AsyncClient::new(options, REQUEST_QUEUE_SIZE);
+
+    loop {
+        let event = tokio::select! {
+            event = event_loop.poll() => event,
+            () = subsystem.on_shutdown_requested() => break,
+        };
+
+        match event {
+            // Subscriptions are not kept by the broker between clean sessions
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                info!("Connected to MQTT broker");
+                for topic in &source.topics {
+                    if let Err(error) = client.try_subscribe(topic, QoS::AtMostOnce) {
+                        warn!(%error, topic, "Failed to subscribe to MQTT topic");
+                    }
+                }
+            }
+            Ok(Event::Incoming(Packet::Publish(publish))) => {
+                let message = SourceMessage::Json {
+                    topic: format!("{MQTT_TOPIC_PREFIX}/{}", publish.topic),
+                    value: to_json(&publish.payload),
+                };
+                if sender.send(message).await.is_err() {
+                    break;
+                }
+            }
+            Ok(_) => {}
+            Err(error) => {
+                warn!(%error, "MQTT connection failed");
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses a payload as a JSON object, other values and non-JSON payloads are wrapped in `value`
+fn to_json(payload: &[u8]) -> Value {
+    match serde_json5::from_str::<Value>(&String::from_utf8_lossy(payload)) {
+        Ok(value) if value.is_object() => value,
+        Ok(value) => json!({ "value": value }),
+        Err(_) => json!({ "value": String::from_utf8_lossy(payload) }),
+    }
+}
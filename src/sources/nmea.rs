@@ -0,0 +1,217 @@
+use std::{
+    io::{BufRead, BufReader, Read},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result, anyhow};
+use serde_json::json;
+use tokio_graceful_shutdown::SubsystemHandle;
+use tracing::*;
+
+use super::{SourceMessage, SourceSender};
+use crate::foxglove_schemas;
+
+/// Channel holding the raw NMEA sentences
+const RAW_TOPIC: &str = "nmea/raw";
+/// Channel holding the position of GGA and RMC sentences as foxglove.LocationFix
+const LOCATION_FIX_TOPIC: &str = "nmea/location_fix";
+/// Channel holding the heading of HDT and HDG sentences
+const HEADING_TOPIC: &str = "nmea/heading";
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+const SERIAL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Reads NMEA 0183 sentences from a serial port or TCP endpoint, e.g: `serial:/dev/ttyUSB0:4800`
+/// or `tcp:192.168.2.10:10110`, for standalone GPS and compass units
+#[instrument(skip(sender, subsystem))]
+pub async fn run(
+    address: String,
+    sender: SourceSender,
+    subsystem: &mut SubsystemHandle,
+) -> anyhow::Result<()> {
+    // The readers are blocking, a detached thread avoids holding the runtime on shutdown
+    std::thread::Builder::new()
+        .name("nmea-input".to_owned())
+        .spawn(move || read_loop(&address, &sender))?;
+
+    subsystem.on_shutdown_requested().await;
+    Ok(())
+}
+
+fn read_loop(address: &str, sender: &SourceSender) {
+    loop {
+        let reader = match open(address) {
+            Ok(reader) => reader,
+            Err(error) => {
+                warn!(%error, address, "Failed to open NMEA input");
+                std::thread::sleep(RECONNECT_DELAY);
+                continue;
+            }
+        };
+        info!(address, "Reading NMEA");
+
+        for line in BufReader::new(reader).lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(error) => {
+                    warn!(%error, "NMEA input failed");
+                    break;
+                }
+            };
+
+            for message in messages(line.trim()) {
+                if sender.blocking_send(message).is_err() {
+                    return;
+                }
+            }
+        }
+        std::thread::sleep(RECONNECT_DELAY);
+    }
+}
+
+fn open(address: &str) -> Result<Box<dyn Read + Send>> {
+    if let Some(address) = address.strip_prefix("tcp:") {
+        let stream = std::net::TcpStream::connect(address).context("Failed to connect")?;
+        return Ok(Box::new(stream));
+    }
+
+    if let Some(address) = address.strip_prefix("serial:") {
+        let (path, baud_rate) = address
+            .rsplit_once(':')
+            .ok_or_else(|| anyhow!("Missing baud rate, expected serial:<PORT>:<BAUDRATE>"))?;
+        let port = serialport::new(path, baud_rate.parse().context("Invalid baud rate")?)
+            .timeout(SERIAL_TIMEOUT)
+            .open()
+            .context("Failed to open serial port")?;
+        return Ok(Box::new(port));
+    }
+
+    Err(anyhow!(
+        "Unknown NMEA address, expected serial:<PORT>:<BAUDRATE> or tcp:<HOST>:<PORT>"
+    ))
+}
+
+/// Messages to record for a sentence: the raw sentence and its parsed position or heading
+fn messages(sentence: &str) -> Vec<SourceMessage> {
+    let Some(fields) = parse(sentence) else {
+        trace!(sentence, "Skipping invalid NMEA sentence");
+        return Vec::new();
+    };
+
+    let mut messages = vec![SourceMessage::Json {
+        topic: RAW_TOPIC.to_owned(),
+        value: json!({ "sentence": sentence }),
+    }];
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64;
+    let talker = &fields[0][..2];
+    match &fields[0][2..] {
+        "GGA" | "RMC" => {
+            if let Some((latitude, longitude, altitude)) = position(&fields) {
+                messages.push(SourceMessage::JsonWithSchema {
+                    topic: LOCATION_FIX_TOPIC.to_owned(),
+                    schema_name: foxglove_schemas::LOCATION_FIX,
+                    schema: foxglove_schemas::location_fix_schema,
+                    value: foxglove_schemas::location_fix(
+                        timestamp, talker, latitude, longitude, altitude,
+                    ),
+                });
+            }
+        }
+        kind @ ("HDT" | "HDG") => {
+            if let Some(heading) = fields.get(1).and_then(|field| field.parse::<f64>().ok()) {
+                messages.push(SourceMessage::Json {
+                    topic: HEADING_TOPIC.to_owned(),
+                    value: json!({
+                        "talker": talker,
+                        "heading_deg": heading,
+                        "true_north": kind == "HDT",
+                    }),
+                });
+            }
+        }
+        _ => {}
+    }
+    messages
+}
+
+/// Splits a sentence into its fields after validating its checksum, the first field is the
+/// address, e.g: `GPGGA`
+fn parse(sentence: &str) -> Option<Vec<&str>> {
+    let body = sentence.strip_prefix('$')?;
+    let (body, checksum) = match body.split_once('*') {
+        Some((body, checksum)) => (body, Some(u8::from_str_radix(checksum, 16).ok()?)),
+        None => (body, None),
+    };
+
+    if let Some(checksum) = checksum
+        && body.bytes().fold(0, |acc, byte| acc ^ byte) != checksum
+    {
+        return None;
+    }
+
+    let fields: Vec<&str> = body.split(',').collect();
+    (fields[0].len() == 5 && fields[0].is_ascii()).then_some(fields)
+}
+
+/// Latitude, longitude in degrees and altitude in meters of a GGA or RMC sentence
+fn position(fields: &[&str]) -> Option<(f64, f64, f64)> {
+    let (latitude, longitude, altitude) = match &fields[0][2..] {
+        // Fix quality 0 means the position is invalid
+        "GGA" if fields.get(6).is_some_and(|quality| *quality != "0") => (
+            coordinate(fields.get(2)?, fields.get(3)?)?,
+            coordinate(fields.get(4)?, fields.get(5)?)?,
+            fields.get(9)?.parse().unwrap_or(0.0),
+        ),
+        // Status V means the position is invalid
+        "RMC" if fields.get(2) == Some(&"A") => (
+            coordinate(fields.get(3)?, fields.get(4)?)?,
+            coordinate(fields.get(5)?, fields.get(6)?)?,
+            0.0,
+        ),
+        _ => return None,
+    };
+    Some((latitude, longitude, altitude))
+}
+
+/// Converts a `(d)ddmm.mmmm` coordinate and its hemisphere into signed degrees
+fn coordinate(value: &str, hemisphere: &str) -> Option<f64> {
+    let dot = value.find('.').unwrap_or(value.len());
+    let degrees: f64 = value.get(..dot.checked_sub(2)?)?.parse().ok()?;
+    let minutes: f64 = value.get(dot - 2..)?.parse().ok()?;
+    let coordinate = degrees + minutes / 60.0;
+    match hemisphere {
+        "N" | "E" => Some(coordinate),
+        "S" | "W" => Some(-coordinate),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nmea() {
+        let gga = "$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47";
+        let fields = parse(gga).unwrap();
+        let (latitude, longitude, altitude) = position(&fields).unwrap();
+        assert!((latitude - 48.1173).abs() < 1e-6);
+        assert!((longitude - 11.516_666).abs() < 1e-6);
+        assert_eq!(altitude, 545.4);
+
+        let rmc = "$GPRMC,123519,A,4807.038,S,01131.000,W,022.4,084.4,230394,003.1,W*65";
+        let (latitude, longitude, _) = position(&parse(rmc).unwrap()).unwrap();
+        assert!(latitude < 0.0 && longitude < 0.0);
+
+        // Corrupted checksum
+        assert!(
+            parse("$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*48").is_none()
+        );
+        // No fix
+        let no_fix = "$GPGGA,123519,,,,,0,00,,,M,,M,,";
+        assert!(position(&parse(no_fix).unwrap()).is_none());
+    }
+}
@@ -0,0 +1,81 @@
+use std::time::{Duration, Instant};
+
+use serde_json::{Value, json};
+use tokio_graceful_shutdown::SubsystemHandle;
+use tracing::*;
+
+use super::{SourceMessage, SourceSender};
+
+/// Channel holding the round-trip times of the tether probe
+const PING_TOPIC: &str = "recorder/ping";
+const PING_SCHEMA: &str = "blueos_recorder.Ping";
+/// Shortest time between two probes, keeping a mistyped interval from flooding the tether
+const MIN_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Probes the topside computer over the tether every `interval` and records the round-trip time,
+/// so telemetry dropouts can be told apart from tether issues. The probe times a TCP connection
+/// to `address`, e.g: `192.168.2.1:22`, since ICMP needs raw sockets. A refused connection still
+/// measures the round trip, only a timeout or an unreachable host marks the probe as lost
+#[instrument(skip(sender, subsystem))]
+pub async fn run(
+    address: String,
+    interval: Duration,
+    sender: SourceSender,
+    subsystem: &mut SubsystemHandle,
+) -> anyhow::Result<()> {
+    let interval = interval.max(MIN_INTERVAL);
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {},
+            () = subsystem.on_shutdown_requested() => break,
+        }
+
+        let message = SourceMessage::JsonWithSchema {
+            topic: PING_TOPIC.to_owned(),
+            schema_name: PING_SCHEMA,
+            schema: ping_schema,
+            value: probe(&address, interval).await,
+        };
+        if sender.send(message).await.is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+async fn probe(address: &str, timeout: Duration) -> Value {
+    let start = Instant::now();
+    let result = tokio::time::timeout(timeout, tokio::net::TcpStream::connect(address)).await;
+    let rtt_ms = start.elapsed().as_secs_f64() * 1e3;
+    let error = match result {
+        Ok(Ok(_)) => None,
+        Ok(Err(error)) if error.kind() == std::io::ErrorKind::ConnectionRefused => None,
+        Ok(Err(error)) => Some(error.to_string()),
+        Err(_) => Some("timed out".to_owned()),
+    };
+    if let Some(error) = &error {
+        debug!(%error, address, "Tether probe lost");
+    }
+
+    json!({
+        "target": address,
+        "reachable": error.is_none(),
+        "rtt_ms": error.is_none().then_some(rtt_ms),
+        "error": error,
+    })
+}
+
+fn ping_schema() -> Value {
+    json!({
+        "title": PING_SCHEMA,
+        "type": "object",
+        "properties": {
+            "target": { "type": "string" },
+            "reachable": { "type": "boolean" },
+            "rtt_ms": { "type": ["number", "null"] },
+            "error": { "type": ["string", "null"] },
+        },
+    })
+}
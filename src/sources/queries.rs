@@ -0,0 +1,60 @@
+use serde_json::{Value, json};
+use tokio_graceful_shutdown::SubsystemHandle;
+use tracing::*;
+use zenoh::{Session, key_expr::OwnedKeyExpr, query::Query};
+
+use super::{SourceMessage, SourceSender};
+
+/// Channel holding the queries observed on the bus
+const QUERIES_TOPIC: &str = "recorder/queries";
+
+/// Records the queries reaching the recorder through a non-complete queryable declared on
+/// `key_expr`. The queryable never replies, and as being incomplete, only receives queries
+/// targeting all queryables or with no complete queryable matching.
+///
+/// The replies are out of scope: zenoh routes them to the querier only, and issuing the query
+/// again to observe them would repeat its side effects, e.g: on the recorder control keys
+#[instrument(skip(session, sender, subsystem))]
+pub async fn run(
+    session: Session,
+    key_expr: OwnedKeyExpr,
+    sender: SourceSender,
+    subsystem: &mut SubsystemHandle,
+) -> anyhow::Result<()> {
+    let queryable = session
+        .declare_queryable(key_expr)
+        .complete(false)
+        .await
+        .map_err(|error| anyhow::anyhow!("Failed to declare query observer: {error}"))?;
+
+    loop {
+        tokio::select! {
+            Ok(query) = queryable.recv_async() => {
+                // Dropping the query without replying finalizes it for the querier
+                if sender.send(message(&query)).await.is_err() {
+                    break;
+                }
+            }
+            _ = subsystem.on_shutdown_requested() => break,
+        }
+    }
+    Ok(())
+}
+
+fn message(query: &Query) -> SourceMessage {
+    let payload = query.payload().map(|payload| {
+        let text = String::from_utf8_lossy(&payload.to_bytes()).into_owned();
+        // Non JSON payloads are kept as text
+        serde_json::from_str(&text).unwrap_or(Value::String(text))
+    });
+
+    SourceMessage::Json {
+        topic: QUERIES_TOPIC.to_owned(),
+        value: json!({
+            "key": query.key_expr().as_str(),
+            "parameters": query.parameters().as_str(),
+            "encoding": query.encoding().map(|encoding| encoding.to_string()),
+            "payload": payload,
+        }),
+    }
+}
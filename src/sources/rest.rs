@@ -0,0 +1,106 @@
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio_graceful_shutdown::SubsystemHandle;
+use tracing::*;
+
+use super::{SourceMessage, SourceSender};
+
+/// Prefix of the channels holding the polled responses, followed by the endpoint name
+const REST_TOPIC_PREFIX: &str = "recorder/rest";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A REST endpoint polled periodically, e.g: a BlueOS service health or status route
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RestEndpoint {
+    /// Name of the endpoint, the response is recorded on `recorder/rest/<name>`
+    pub name: String,
+    pub url: String,
+    /// Polling interval in seconds
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+}
+
+fn default_interval() -> u64 {
+    10
+}
+
+/// Polls the endpoints until shutdown, forwarding every response, or failure, to the recorder
+#[instrument(skip_all)]
+pub async fn run(
+    endpoints: Vec<RestEndpoint>,
+    sender: SourceSender,
+    subsystem: &mut SubsystemHandle,
+) -> anyhow::Result<()> {
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()?;
+
+    let mut pollers = tokio::task::JoinSet::new();
+    for endpoint in endpoints {
+        let client = client.clone();
+        let sender = sender.clone();
+        pollers.spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(endpoint.interval.max(1)));
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                let message = SourceMessage::Json {
+                    topic: format!("{REST_TOPIC_PREFIX}/{}", endpoint.name),
+                    value: poll(&client, &endpoint).await,
+                };
+                if sender.send(message).await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    // Dropping the set aborts the pollers
+    subsystem.on_shutdown_requested().await;
+    Ok(())
+}
+
+#[instrument(skip(client, endpoint), fields(name = %endpoint.name))]
+async fn poll(client: &reqwest::Client, endpoint: &RestEndpoint) -> Value {
+    let start = Instant::now();
+    let result = async {
+        let response = client.get(&endpoint.url).send().await?;
+        let status = response.status().as_u16();
+        let body = response.text().await?;
+        Ok::<_, reqwest::Error>((status, body))
+    }
+    .await;
+    let latency_ms = start.elapsed().as_secs_f64() * 1e3;
+
+    match result {
+        Ok((status, body)) => {
+            if !(200..300).contains(&status) {
+                warn!(status, "Endpoint replied with an error status");
+            }
+            // Non JSON bodies are kept as text
+            let response = serde_json::from_str(&body).unwrap_or(Value::String(body));
+            json!({
+                "url": endpoint.url,
+                "ok": (200..300).contains(&status),
+                "status": status,
+                "latency_ms": latency_ms,
+                "error": "",
+                "response": response,
+            })
+        }
+        Err(error) => {
+            warn!(%error, "Failed to poll endpoint");
+            json!({
+                "url": endpoint.url,
+                "ok": false,
+                "status": 0,
+                "latency_ms": latency_ms,
+                "error": error.to_string(),
+                "response": Value::Null,
+            })
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::{Value, json};
+
+/// Number of topics listed in the progress report, sorted by bandwidth
+const TOP_TOPICS: usize = 5;
+
+struct TopicStats {
+    messages: u64,
+    bytes: u64,
+}
+
+/// Write statistics, accumulated over the whole recording and per reporting window
+pub struct Stats {
+    window_start: Instant,
+    window: HashMap<String, TopicStats>,
+    total_messages: u64,
+    total_bytes: u64,
+    queue_high_watermark: usize,
+    dropped: u64,
+    gaps: u64,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self {
+            window_start: Instant::now(),
+            window: HashMap::new(),
+            total_messages: 0,
+            total_bytes: 0,
+            queue_high_watermark: 0,
+            dropped: 0,
+            gaps: 0,
+        }
+    }
+
+    pub fn record(&mut self, topic: &str, bytes: usize) {
+        let bytes = bytes as u64;
+        self.total_messages += 1;
+        self.total_bytes += bytes;
+
+        if let Some(topic_stats) = self.window.get_mut(topic) {
+            topic_stats.messages += 1;
+            topic_stats.bytes += bytes;
+        } else {
+            self.window
+                .insert(topic.to_owned(), TopicStats { messages: 1, bytes });
+        }
+    }
+
+    /// Bytes written since the recorder started
+    pub fn total_bytes(&self) -> u64 {
+        self.total_bytes
+    }
+
+    /// Counts a sample dropped to shed load during the window
+    pub fn record_drop(&mut self) {
+        self.dropped += 1;
+    }
+
+    /// Counts a gap detected on a topic during the window
+    pub fn record_gap(&mut self) {
+        self.gaps += 1;
+    }
+
+    /// Tracks the highest number of samples waiting in the subscriber queue during the window
+    pub fn observe_queue(&mut self, depth: usize) {
+        self.queue_high_watermark = self.queue_high_watermark.max(depth);
+    }
+
+    /// Builds the progress report of the current window and starts a new one
+    pub fn report(&mut self, queue_depth: usize, queue_capacity: Option<usize>) -> Value {
+        let elapsed = self.window_start.elapsed().as_secs_f64().max(f64::EPSILON);
+        let window = std::mem::take(&mut self.window);
+        self.window_start = Instant::now();
+        let queue_high_watermark = std::mem::take(&mut self.queue_high_watermark).max(queue_depth);
+
+        let (messages, bytes) = window
+            .values()
+            .fold((0, 0), |(messages, bytes), topic_stats| {
+                (messages + topic_stats.messages, bytes + topic_stats.bytes)
+            });
+
+        let mut topics: Vec<_> = window.into_iter().collect();
+        topics.sort_unstable_by_key(|(_, topic_stats)| std::cmp::Reverse(topic_stats.bytes));
+        let top_topics: Vec<_> = topics
+            .into_iter()
+            .take(TOP_TOPICS)
+            .map(|(topic, topic_stats)| {
+                json!({
+                    "topic": topic,
+                    "messages_per_second": topic_stats.messages as f64 / elapsed,
+                    "bytes_per_second": topic_stats.bytes as f64 / elapsed,
+                })
+            })
+            .collect();
+
+        json!({
+            "messages_per_second": messages as f64 / elapsed,
+            "bytes_per_second": bytes as f64 / elapsed,
+            "messages_written": self.total_messages,
+            "megabytes_written": self.total_bytes as f64 / 1e6,
+            "top_topics": top_topics,
+            "queue_depth": queue_depth,
+            "queue_high_watermark": queue_high_watermark,
+            "queue_capacity": queue_capacity,
+            "messages_dropped": std::mem::take(&mut self.dropped),
+            "gaps": std::mem::take(&mut self.gaps),
+        })
+    }
+}
+
+/// Health of the storage writes, a dying SD card shows as slow flushes and I/O errors before the
+/// recording fails
+#[derive(Default)]
+pub struct WriteHealth {
+    /// Durations of the flushes during the window
+    flush_durations: Vec<Duration>,
+    /// Time and size of the file at the last report, for the write throughput
+    last_file_bytes: Option<(Instant, u64)>,
+    /// Time in nanoseconds and message of the last I/O error
+    last_error: Option<(u64, String)>,
+}
+
+impl WriteHealth {
+    pub fn observe_flush(&mut self, duration: Duration) {
+        self.flush_durations.push(duration);
+    }
+
+    pub fn record_error(&mut self, error: &anyhow::Error) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64;
+        self.last_error = Some((timestamp, format!("{error:#}")));
+    }
+
+    /// Builds the write report of the current window and starts a new one, `message_bytes` and
+    /// `file_bytes` are the payload and stored sizes of the file being written
+    pub fn report(&mut self, message_bytes: u64, file_bytes: Option<u64>) -> Value {
+        let mut durations = std::mem::take(&mut self.flush_durations);
+        durations.sort_unstable();
+        let percentile = |quantile: f64| {
+            let index = (durations.len().saturating_sub(1) as f64 * quantile).round() as usize;
+            durations
+                .get(index)
+                .map(|duration| duration.as_secs_f64() * 1e3)
+        };
+
+        let now = Instant::now();
+        let file_bytes_per_second = file_bytes.and_then(|file_bytes| {
+            let (since, last) = self.last_file_bytes.replace((now, file_bytes))?;
+            let elapsed = now.duration_since(since).as_secs_f64().max(f64::EPSILON);
+            // A smaller file is the next part of the recording, written from scratch
+            let written = file_bytes.checked_sub(last).unwrap_or(file_bytes);
+            Some(written as f64 / elapsed)
+        });
+        if file_bytes.is_none() {
+            self.last_file_bytes = None;
+        }
+
+        json!({
+            "message_bytes": message_bytes,
+            "file_bytes": file_bytes,
+            "compression_ratio": file_bytes
+                .filter(|_| message_bytes > 0)
+                .map(|file_bytes| file_bytes as f64 / message_bytes as f64),
+            "file_bytes_per_second": file_bytes_per_second,
+            "flushes": durations.len(),
+            "flush_ms": {
+                "p50": percentile(0.5),
+                "p90": percentile(0.9),
+                "p99": percentile(0.99),
+                "max": durations.last().map(|duration| duration.as_secs_f64() * 1e3),
+            },
+            "last_error": self.last_error.as_ref().map(|(timestamp, message)| {
+                json!({ "timestamp": timestamp, "message": message })
+            }),
+        })
+    }
+}
@@ -0,0 +1,28 @@
+use std::io;
+
+/// Tmpfs directory receiving the recording when the recorder path stops accepting writes
+pub const DEFAULT_EMERGENCY_PATH: &str = "/dev/shm/blueos-recorder";
+
+/// What the recorder does once its path becomes read-only, e.g: an SD card remounted read-only
+/// after errors
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ReadOnlyPolicy {
+    /// Continue the recording in the emergency path, lost on reboot unless copied out
+    #[default]
+    Buffer,
+    /// Stop the recording until the recorder restarts
+    Stop,
+}
+
+/// Returns true for the write errors of a storage that no longer accepts writes
+pub fn is_read_only(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .filter_map(|cause| cause.downcast_ref::<io::Error>())
+        .any(|error| {
+            matches!(
+                error.kind(),
+                io::ErrorKind::ReadOnlyFilesystem | io::ErrorKind::PermissionDenied
+            )
+        })
+}
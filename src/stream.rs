@@ -0,0 +1,109 @@
+use std::{
+    fs::File,
+    io::{self, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::{
+        Mutex,
+        mpsc::{Receiver, SyncSender, sync_channel},
+    },
+};
+
+use anyhow::{Context, Result, anyhow};
+use tracing::*;
+
+use crate::sink::Sink;
+
+/// Clients connected to the stream, attached to the file being written on its next write
+static PENDING: Mutex<Vec<TcpStream>> = Mutex::new(Vec::new());
+/// Writes queued for a client before it is considered too slow and disconnected
+const CLIENT_QUEUE: usize = 1024;
+
+/// Accepts the stream clients on `tcp://<IP>:<PORT>`. Each connection mirrors the file being
+/// written from its beginning and is closed once the file is finished, clients reconnect to
+/// receive the next one
+#[instrument]
+pub fn listen(address: &str) -> Result<()> {
+    let address = address
+        .strip_prefix("tcp://")
+        .ok_or_else(|| anyhow!("Unknown stream address, expected tcp://<IP>:<PORT>"))?;
+    let listener = TcpListener::bind(address).context("Failed to bind stream address")?;
+    info!("Streaming recordings");
+
+    std::thread::Builder::new()
+        .name("mcap-stream".to_owned())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        info!(peer = ?stream.peer_addr().ok(), "Stream client connected");
+                        PENDING.lock().unwrap().push(stream);
+                    }
+                    Err(error) => warn!(%error, "Failed to accept stream client"),
+                }
+            }
+        })?;
+    Ok(())
+}
+
+/// Duplicates the bytes written to a recording file to the stream clients
+pub struct Tee {
+    path: PathBuf,
+    /// Bytes written to the file so far
+    position: u64,
+    clients: Vec<SyncSender<Vec<u8>>>,
+}
+
+impl Tee {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_owned(),
+            position: 0,
+            clients: Vec::new(),
+        }
+    }
+}
+
+impl Sink for Tee {
+    /// Forwards bytes just written to the file, the clients connected since the last write
+    /// first receive what the file already holds
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        for stream in std::mem::take(&mut *PENDING.lock().unwrap()) {
+            let (sender, receiver) = sync_channel(CLIENT_QUEUE);
+            let path = self.path.clone();
+            let catch_up = self.position;
+            std::thread::spawn(move || {
+                if let Err(error) = serve(stream, &path, catch_up, receiver) {
+                    info!(%error, "Stream client disconnected");
+                }
+            });
+            self.clients.push(sender);
+        }
+
+        self.position += buf.len() as u64;
+        // A slow client never blocks the recording, it is dropped instead
+        self.clients
+            .retain(|client| client.try_send(buf.to_vec()).is_ok());
+        Ok(())
+    }
+
+    fn seek(&mut self, _position: u64) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Streamed bytes cannot be rewritten",
+        ))
+    }
+}
+
+fn serve(
+    mut stream: TcpStream,
+    path: &Path,
+    catch_up: u64,
+    receiver: Receiver<Vec<u8>>,
+) -> io::Result<()> {
+    io::copy(&mut File::open(path)?.take(catch_up), &mut stream)?;
+    for bytes in receiver {
+        stream.write_all(&bytes)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use serde::Deserialize;
+use zenoh::sample::Locality;
+
+/// Origin of the samples received by the recorder subscriber
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Origin {
+    /// Samples from any publisher
+    #[default]
+    Any,
+    /// Only samples from other sessions, skipping what the recorder publishes itself
+    Remote,
+    /// Only samples published by the recorder session
+    SessionLocal,
+}
+
+/// Options of the global subscriber. Reliability and express delivery are decided by the
+/// publishers in zenoh, the subscriber only filters the origin of the samples
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SubscriberOptions {
+    pub allowed_origin: Origin,
+}
+
+impl From<Origin> for Locality {
+    fn from(origin: Origin) -> Self {
+        match origin {
+            Origin::Any => Locality::Any,
+            Origin::Remote => Locality::Remote,
+            Origin::SessionLocal => Locality::SessionLocal,
+        }
+    }
+}
@@ -0,0 +1,45 @@
+//! systemd service notifications, no-ops when not running under systemd or on non-unix targets
+
+use std::time::Duration;
+
+#[cfg(unix)]
+use tracing::*;
+
+/// Tells systemd that the service finished starting up
+pub fn notify_ready() {
+    #[cfg(unix)]
+    notify(&[sd_notify::NotifyState::Ready]);
+}
+
+/// Tells systemd that the service is shutting down
+pub fn notify_stopping() {
+    #[cfg(unix)]
+    notify(&[sd_notify::NotifyState::Stopping]);
+}
+
+/// Sends a watchdog keep-alive
+pub fn notify_watchdog() {
+    #[cfg(unix)]
+    notify(&[sd_notify::NotifyState::Watchdog]);
+}
+
+/// Returns the interval at which keep-alives should be sent, if the watchdog is enabled
+pub fn watchdog_interval() -> Option<Duration> {
+    #[cfg(unix)]
+    {
+        let mut usec = 0;
+        if sd_notify::watchdog_enabled(false, &mut usec) {
+            // Ping at half of the timeout, as recommended by sd_watchdog_enabled(3)
+            return Some(Duration::from_micros(usec / 2));
+        }
+    }
+
+    None
+}
+
+#[cfg(unix)]
+fn notify(state: &[sd_notify::NotifyState]) {
+    if let Err(error) = sd_notify::notify(false, state) {
+        warn!(%error, "Failed to notify systemd");
+    }
+}
@@ -0,0 +1,145 @@
+use std::{collections::BTreeMap, time::Instant};
+
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+/// Channel correlating the companion clocks with the autopilot and GPS clocks
+pub const TIME_SYNC_TOPIC: &str = "recorder/time_sync";
+/// Name of the MCAP metadata record holding the last computed offsets
+pub const TIME_SYNC_METADATA: &str = "time_sync";
+
+/// GPS_RAW_INT.time_usec may be either UNIX time or time since boot, values
+/// above this threshold (2001-09-09) are considered UNIX time
+const UNIX_TIME_THRESHOLD_USEC: u64 = 1_000_000_000_000_000;
+
+/// Source of the timestamp of the recorded samples, listed in priority order in the `clock` of the
+/// `[topics]` table, the first one available for a sample is used
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClockSource {
+    /// Zenoh HLC timestamp of the sample, set by the publishing session or its router
+    Hlc,
+    /// `time_boot_ms` of a MAVLink message, mapped to the wall clock by the SYSTEM_TIME offset
+    Mavlink,
+    /// Reception time of the sample
+    Local,
+}
+
+impl ClockSource {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Hlc => "hlc",
+            Self::Mavlink => "mavlink",
+            Self::Local => "local",
+        }
+    }
+}
+
+/// Clock sources of the topics without a `clock` setting
+pub const DEFAULT_CLOCK: &[ClockSource] = &[ClockSource::Hlc, ClockSource::Local];
+
+/// Tracks the mapping between companion monotonic time, wall clock and autopilot/GPS time
+pub struct TimeSync {
+    monotonic_start: Instant,
+    offsets: BTreeMap<&'static str, i128>,
+}
+
+impl TimeSync {
+    pub fn new() -> Self {
+        Self {
+            monotonic_start: Instant::now(),
+            offsets: BTreeMap::new(),
+        }
+    }
+
+    /// Returns true for the per-field JSON topics used as clock sources
+    pub fn is_source_topic(topic: &str) -> bool {
+        topic.starts_with("mavlink/")
+            && (topic.ends_with("/SYSTEM_TIME") || topic.ends_with("/GPS_RAW_INT"))
+    }
+
+    /// Updates the offsets from a SYSTEM_TIME or GPS_RAW_INT message, returning the clock mapping
+    pub fn update(&mut self, value: &Value, wall_clock_ns: u64) -> Option<Value> {
+        let message = value.get("message").unwrap_or(value);
+        let monotonic_ns = self.monotonic_start.elapsed().as_nanos();
+        let wall_clock_ns = i128::from(wall_clock_ns);
+
+        let mut mapping = json!({
+            "monotonic_ns": monotonic_ns as u64,
+            "wall_clock_ns": wall_clock_ns as u64,
+        });
+
+        let mut updated = false;
+        let mut set = |mapping: &mut Value, field: &'static str, offset: &'static str, ns: i128| {
+            mapping[field] = json!(ns as u64);
+            mapping[offset] = json!((wall_clock_ns - ns) as i64);
+            self.offsets.insert(offset, wall_clock_ns - ns);
+            updated = true;
+        };
+
+        // SYSTEM_TIME
+        if let Some(time_unix_usec) = message.get("time_unix_usec").and_then(Value::as_u64)
+            && time_unix_usec > 0
+        {
+            let ns = i128::from(time_unix_usec) * 1_000;
+            set(
+                &mut mapping,
+                "autopilot_unix_ns",
+                "autopilot_unix_offset_ns",
+                ns,
+            );
+        }
+        if let Some(time_boot_ms) = message.get("time_boot_ms").and_then(Value::as_u64) {
+            let ns = i128::from(time_boot_ms) * 1_000_000;
+            set(
+                &mut mapping,
+                "autopilot_boot_ns",
+                "autopilot_boot_offset_ns",
+                ns,
+            );
+        }
+
+        // GPS_RAW_INT
+        if let Some(time_usec) = message.get("time_usec").and_then(Value::as_u64)
+            && time_usec > UNIX_TIME_THRESHOLD_USEC
+            && message.get("fix_type").is_some()
+        {
+            let ns = i128::from(time_usec) * 1_000;
+            set(&mut mapping, "gps_unix_ns", "gps_unix_offset_ns", ns);
+        }
+
+        if !updated {
+            return None;
+        }
+
+        self.offsets
+            .insert("monotonic_offset_ns", wall_clock_ns - monotonic_ns as i128);
+        mapping["monotonic_offset_ns"] = json!((wall_clock_ns - monotonic_ns as i128) as i64);
+        Some(mapping)
+    }
+
+    /// Offset of the wall clock from the UNIX time of the GPS, or of the autopilot
+    pub fn unix_offset_ns(&self) -> Option<i128> {
+        self.offsets
+            .get("gps_unix_offset_ns")
+            .or_else(|| self.offsets.get("autopilot_unix_offset_ns"))
+            .copied()
+    }
+
+    /// Wall clock time of a MAVLink message from its `time_boot_ms`, once the autopilot boot
+    /// offset is known
+    pub fn autopilot_time_ns(&self, value: &Value) -> Option<u64> {
+        let message = value.get("message").unwrap_or(value);
+        let time_boot_ms = message.get("time_boot_ms")?.as_u64()?;
+        let offset = self.offsets.get("autopilot_boot_offset_ns")?;
+        u64::try_from(i128::from(time_boot_ms) * 1_000_000 + offset).ok()
+    }
+
+    /// Last computed offsets (wall clock minus source clock), as MCAP metadata
+    pub fn metadata(&self) -> BTreeMap<String, String> {
+        self.offsets
+            .iter()
+            .map(|(name, offset)| (name.to_string(), offset.to_string()))
+            .collect()
+    }
+}
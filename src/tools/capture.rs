@@ -0,0 +1,161 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    path::Path,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result, anyhow};
+use tracing::*;
+use zenoh::{key_expr::OwnedKeyExpr, sample::Sample};
+
+use crate::{channel_descriptor::ChannelDescriptor, cli, mcap::Mcap};
+
+/// Traffic of a captured topic
+#[derive(Default)]
+struct TopicStats {
+    messages: u64,
+    bytes: u64,
+    /// Samples that could not be recorded, e.g: non-object JSON or a missing CDR schema
+    skipped: u64,
+}
+
+/// Records the samples of `topics` into `output` for `duration`, or until interrupted, then
+/// prints a summary of the captured topics, e.g: to grab a snapshot of the bus without running
+/// the recorder service
+#[instrument(skip_all, fields(output = %output.display()))]
+pub async fn run(duration: Duration, topics: &[OwnedKeyExpr], output: &Path) -> Result<()> {
+    let session = super::open_session().await?;
+    let topics = distinct_topics(topics);
+    // The subscribers feed a single queue, keeping the samples of every topic in arrival order
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel::<(usize, Sample)>();
+    let mut subscribers = Vec::new();
+    for (index, topic) in topics.iter().enumerate() {
+        let sender = sender.clone();
+        let subscriber = session
+            .declare_subscriber(*topic)
+            .callback(move |sample| {
+                let _ = sender.send((index, sample));
+            })
+            .await
+            .map_err(|error| anyhow!("Failed to declare subscriber on {topic}: {error}"))?;
+        subscribers.push(subscriber);
+    }
+
+    let mut options = cli::mcap_options();
+    options.sinks.clear();
+    let mut mcap = Mcap::try_new(output, &options, &[])?;
+    let schema_path = cli::schema_path();
+    let mut stats = BTreeMap::<String, TopicStats>::new();
+
+    info!(?duration, ?topics, "Capturing");
+    let start = Instant::now();
+    let deadline = tokio::time::sleep(duration);
+    tokio::pin!(deadline);
+    loop {
+        let sample = tokio::select! {
+            () = &mut deadline => break,
+            result = tokio::signal::ctrl_c() => {
+                if let Err(error) = result {
+                    warn!(%error, "Failed to listen for the interruption");
+                }
+                info!("Capture interrupted");
+                break;
+            },
+            Some((index, sample)) = receiver.recv() => {
+                // Overlapping expressions deliver the sample once per subscriber, only the first
+                // matching one records it
+                if topics[..index]
+                    .iter()
+                    .any(|topic| topic.includes(sample.key_expr()))
+                {
+                    continue;
+                }
+                sample
+            },
+        };
+
+        let topic = sample.key_expr().as_str();
+        let payload = sample.payload().to_bytes();
+        let topic_stats = stats.entry(topic.to_owned()).or_default();
+        let new_channel = if mcap.has_channel(topic) {
+            None
+        } else {
+            match ChannelDescriptor::new(topic, sample.encoding(), &payload, schema_path.as_ref()) {
+                Ok(descriptor) => Some(descriptor),
+                Err(error) => {
+                    if topic_stats.skipped == 0 {
+                        warn!(%error, topic, "Skipping topic");
+                    }
+                    topic_stats.skipped += 1;
+                    continue;
+                }
+            }
+        };
+        let log_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64;
+        let publish_time = sample
+            .timestamp()
+            .map_or(log_time, |timestamp| timestamp.get_time().as_nanos());
+        mcap.write_message(topic, log_time, publish_time, None, &payload, new_channel)
+            .with_context(|| format!("Failed to record {topic}"))?;
+        topic_stats.messages += 1;
+        topic_stats.bytes += payload.len() as u64;
+    }
+    let elapsed = start.elapsed();
+    drop(subscribers);
+    let _ = session.close().await;
+    mcap.finish()?;
+
+    print_summary(output, elapsed, &stats);
+    if stats.values().all(|topic_stats| topic_stats.messages == 0) {
+        return Err(anyhow!(
+            "No samples captured, check the --topics and the --connect endpoints"
+        ));
+    }
+    Ok(())
+}
+
+/// Skips the expressions included in another one, e.g: `mavlink/**` when `**` is captured
+fn distinct_topics(topics: &[OwnedKeyExpr]) -> Vec<&OwnedKeyExpr> {
+    topics
+        .iter()
+        .enumerate()
+        .filter(|(index, topic)| {
+            !topics.iter().enumerate().any(|(other_index, other)| {
+                other_index != *index
+                    && other.includes(topic)
+                    // Keeps the first of equivalent expressions
+                    && (!topic.includes(other) || other_index < *index)
+            })
+        })
+        .map(|(_, topic)| topic)
+        .collect()
+}
+
+fn print_summary(output: &Path, elapsed: Duration, stats: &BTreeMap<String, TopicStats>) {
+    let seconds = elapsed.as_secs_f64().max(f64::EPSILON);
+    let messages: u64 = stats.values().map(|topic_stats| topic_stats.messages).sum();
+    let file_bytes = std::fs::metadata(output).map_or(0, |metadata| metadata.len());
+    println!(
+        "Captured {messages} messages on {} topics in {:.1}s, {:.1} kB written to {}",
+        stats.len(),
+        elapsed.as_secs_f64(),
+        file_bytes as f64 / 1e3,
+        output.display()
+    );
+    for (topic, topic_stats) in stats {
+        let mut line = format!(
+            "  {topic}: {} messages, {:.1} Hz, {:.1} kB",
+            topic_stats.messages,
+            topic_stats.messages as f64 / seconds,
+            topic_stats.bytes as f64 / 1e3
+        );
+        if topic_stats.skipped > 0 {
+            let _ = write!(line, ", {} skipped", topic_stats.skipped);
+        }
+        println!("{line}");
+    }
+}
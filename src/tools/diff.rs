@@ -0,0 +1,110 @@
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::{Context, Result};
+
+/// Relative rate change reported, smaller ones are jitter
+const RATE_TOLERANCE: f64 = 0.1;
+
+/// Channel of a recording, as compared between recordings
+struct ChannelStats {
+    schema_name: Option<String>,
+    schema_data: Vec<u8>,
+    message_encoding: String,
+    messages: u64,
+}
+
+struct RecordingStats {
+    channels: BTreeMap<String, ChannelStats>,
+    /// Time covered by the messages, in seconds
+    duration: f64,
+}
+
+impl RecordingStats {
+    fn rate(&self, channel: &ChannelStats) -> f64 {
+        channel.messages as f64 / self.duration.max(f64::EPSILON)
+    }
+}
+
+/// Compares the channels of two recordings, e.g: before and after a firmware update, printing the
+/// missing channels and the changed schemas and rates
+pub fn run(a: &Path, b: &Path) -> Result<()> {
+    let (stats_a, stats_b) = (stats(a)?, stats(b)?);
+    println!("--- {} ({:.0}s)", a.display(), stats_a.duration);
+    println!("+++ {} ({:.0}s)", b.display(), stats_b.duration);
+
+    let mut unchanged = 0;
+    for (topic, channel) in &stats_a.channels {
+        if !stats_b.channels.contains_key(topic) {
+            println!("- {topic}: missing, was {:.1} Hz", stats_a.rate(channel));
+        }
+    }
+    for (topic, channel_b) in &stats_b.channels {
+        let Some(channel_a) = stats_a.channels.get(topic) else {
+            println!("+ {topic}: new, {:.1} Hz", stats_b.rate(channel_b));
+            continue;
+        };
+
+        let mut changes = Vec::new();
+        if channel_a.schema_name != channel_b.schema_name {
+            changes.push(format!(
+                "schema {} -> {}",
+                channel_a.schema_name.as_deref().unwrap_or("none"),
+                channel_b.schema_name.as_deref().unwrap_or("none")
+            ));
+        } else if channel_a.schema_data != channel_b.schema_data {
+            changes.push("schema content changed".to_owned());
+        }
+        if channel_a.message_encoding != channel_b.message_encoding {
+            changes.push(format!(
+                "encoding {} -> {}",
+                channel_a.message_encoding, channel_b.message_encoding
+            ));
+        }
+        let (rate_a, rate_b) = (stats_a.rate(channel_a), stats_b.rate(channel_b));
+        if (rate_b - rate_a).abs() > rate_a * RATE_TOLERANCE {
+            changes.push(format!(
+                "rate {rate_a:.1} Hz -> {rate_b:.1} Hz ({} -> {} messages)",
+                channel_a.messages, channel_b.messages
+            ));
+        }
+
+        if changes.is_empty() {
+            unchanged += 1;
+        } else {
+            println!("~ {topic}: {}", changes.join(", "));
+        }
+    }
+    println!("{unchanged} channels unchanged");
+    Ok(())
+}
+
+fn stats(path: &Path) -> Result<RecordingStats> {
+    let bytes = std::fs::read(path)
+        .with_context(|| format!("Failed to read MCAP file {}", path.display()))?;
+    let mut channels = BTreeMap::new();
+    let (mut start, mut end) = (u64::MAX, 0);
+    for message in mcap::MessageStream::new(&bytes)? {
+        let message = message.context("Failed to read MCAP message")?;
+        start = start.min(message.log_time);
+        end = end.max(message.log_time);
+        let channel = &message.channel;
+        channels
+            .entry(channel.topic.clone())
+            .or_insert_with(|| ChannelStats {
+                schema_name: channel.schema.as_ref().map(|schema| schema.name.clone()),
+                schema_data: channel
+                    .schema
+                    .as_ref()
+                    .map(|schema| schema.data.to_vec())
+                    .unwrap_or_default(),
+                message_encoding: channel.message_encoding.clone(),
+                messages: 0,
+            })
+            .messages += 1;
+    }
+
+    Ok(RecordingStats {
+        channels,
+        duration: end.saturating_sub(start) as f64 / 1e9,
+    })
+}
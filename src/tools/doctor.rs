@@ -0,0 +1,133 @@
+use std::{path::Path, time::Duration};
+
+use anyhow::{Result, anyhow};
+
+use crate::cli;
+
+/// Free space below which the recorder path is reported, a few minutes of video
+const MIN_FREE_BYTES: u64 = 1024 * 1024 * 1024;
+/// Time given to the router to answer, the session keeps retrying otherwise
+const ZENOH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Outcome of a check, the error is what to fix
+type Check = Result<String, String>;
+
+/// Runs every check and prints the results, fails if any of them did
+pub async fn run() -> Result<()> {
+    let mut failed = 0;
+    let mut report = |name: &str, check: Check| match check {
+        Ok(details) => println!("[ OK ] {name}: {details}"),
+        Err(fix) => {
+            failed += 1;
+            println!("[FAIL] {name}: {fix}");
+        }
+    };
+
+    let recorder_path = Path::new(cli::recorder_path_arg());
+    if cli::is_stdout_output() {
+        report("Recorder path", Ok("writing to stdout".to_owned()));
+    } else {
+        report("Recorder path", check_writable(recorder_path));
+        report("Free space", check_free_space(recorder_path));
+    }
+    report("Schema path", check_schema_path());
+
+    match tokio::time::timeout(ZENOH_TIMEOUT, zenoh::open(crate::zenoh_config(false))).await {
+        Ok(Ok(session)) => {
+            report("Zenoh router", check_router(&session).await);
+            report("Zenoh adminspace", check_adminspace(&session).await);
+            let _ = session.close().await;
+        }
+        Ok(Err(error)) => report(
+            "Zenoh router",
+            Err(format!(
+                "failed to open a session ({error}), check that zenohd runs and the --connect endpoints"
+            )),
+        ),
+        Err(_) => report(
+            "Zenoh router",
+            Err("no answer, check that zenohd runs and the --connect endpoints".to_owned()),
+        ),
+    }
+
+    if failed > 0 {
+        return Err(anyhow!("{failed} checks failed"));
+    }
+    Ok(())
+}
+
+fn check_writable(path: &Path) -> Check {
+    std::fs::create_dir_all(path).map_err(|error| {
+        format!(
+            "cannot create {} ({error}), check --recorder-path",
+            path.display()
+        )
+    })?;
+    let probe = path.join(".doctor");
+    std::fs::write(&probe, b"probe").map_err(|error| {
+        format!(
+            "cannot write in {} ({error}), check its permissions or if the storage is read-only",
+            path.display()
+        )
+    })?;
+    let _ = std::fs::remove_file(probe);
+    Ok(format!("{} is writable", path.display()))
+}
+
+#[cfg(unix)]
+fn check_free_space(path: &Path) -> Check {
+    let stats = nix::sys::statvfs::statvfs(path)
+        .map_err(|error| format!("cannot read the free space of {} ({error})", path.display()))?;
+    // The block counts and sizes are 32 bits wide on some targets, e.g: armv7
+    #[allow(clippy::unnecessary_cast)]
+    let available = stats.blocks_available() as u64 * stats.fragment_size() as u64;
+    let details = format!("{:.1} GB available", available as f64 / 1e9);
+    if available < MIN_FREE_BYTES {
+        return Err(format!("only {details}, delete or offload recordings"));
+    }
+    Ok(details)
+}
+
+#[cfg(not(unix))]
+fn check_free_space(_path: &Path) -> Check {
+    Ok("not checked on this platform".to_owned())
+}
+
+fn check_schema_path() -> Check {
+    let Some(schema_path) = cli::schema_path_arg() else {
+        return Ok("using the embedded schemas".to_owned());
+    };
+    if !Path::new(schema_path).is_dir() {
+        return Err(format!(
+            "{schema_path} is not a directory, check --schema-path"
+        ));
+    }
+    Ok(format!("{schema_path} exists"))
+}
+
+async fn check_router(session: &zenoh::Session) -> Check {
+    let routers = session.info().routers_zid().await.count();
+    if routers == 0 {
+        return Err(
+            "the session is not connected to any router, check the --connect endpoints".to_owned(),
+        );
+    }
+    Ok(format!("connected to {routers} router(s)"))
+}
+
+async fn check_adminspace(session: &zenoh::Session) -> Check {
+    let replies = session
+        .get("@/*/router")
+        .timeout(ZENOH_TIMEOUT)
+        .await
+        .map_err(|error| format!("failed to query the adminspace ({error})"))?;
+    while let Ok(reply) = replies.recv_async().await {
+        if reply.result().is_ok() {
+            return Ok("router state readable".to_owned());
+        }
+    }
+    Err(
+        "no reply, enable the router adminspace read permission: adminspace/permissions/read"
+            .to_owned(),
+    )
+}
@@ -0,0 +1,88 @@
+use std::{
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use mavlink::{MavHeader, ardupilotmega::MavMessage};
+use serde_json::Value;
+use tracing::*;
+
+use crate::{channel_descriptor::MessageEncoding, ros_names};
+
+/// Reconstructs a MAVLink telemetry log from a recording
+///
+/// A tlog is a sequence of MAVLink frames, each prefixed by its big-endian timestamp in
+/// microseconds since the epoch. Raw MAVLink channels are used when available, otherwise the
+/// per-field JSON `mavlink/**` channels are re-encoded as MAVLink 2 frames.
+#[instrument(skip_all, fields(input = %input.display()))]
+pub fn run(input: &Path, output: Option<&Path>) -> Result<()> {
+    let output = output
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| input.with_extension("tlog"));
+
+    let bytes = std::fs::read(input).context("Failed to read MCAP file")?;
+
+    let mut frames = raw_frames(&bytes)?;
+    if frames.is_empty() {
+        info!("No raw MAVLink channels found, re-encoding JSON MAVLink channels");
+        frames = json_frames(&bytes)?;
+    }
+    frames.sort_by_key(|(timestamp, _)| *timestamp);
+
+    let file = std::fs::File::create(&output).context("Failed to create tlog file")?;
+    let mut writer = BufWriter::new(file);
+    for (timestamp, frame) in &frames {
+        writer.write_all(&(timestamp / 1_000).to_be_bytes())?;
+        writer.write_all(frame)?;
+    }
+    writer.flush().context("Failed to write tlog file")?;
+
+    info!(output = %output.display(), frames = frames.len(), "Exported tlog");
+    Ok(())
+}
+
+fn raw_frames(bytes: &[u8]) -> Result<Vec<(u64, Vec<u8>)>> {
+    let mut frames = Vec::new();
+    for message in mcap::MessageStream::new(bytes)? {
+        let message = message.context("Failed to read MCAP message")?;
+        if message.channel.message_encoding == MessageEncoding::Mavlink.as_str() {
+            frames.push((message.log_time, message.data.into_owned()));
+        }
+    }
+    Ok(frames)
+}
+
+fn json_frames(bytes: &[u8]) -> Result<Vec<(u64, Vec<u8>)>> {
+    let mut frames = Vec::new();
+    let mut skipped = 0usize;
+    for message in mcap::MessageStream::new(bytes)? {
+        let message = message.context("Failed to read MCAP message")?;
+        if message.channel.message_encoding != MessageEncoding::Json.as_str()
+            || !ros_names::key_expr(&message.channel).starts_with("mavlink/")
+        {
+            continue;
+        }
+
+        match json_frame(&message.data) {
+            Some(frame) => frames.push((message.log_time, frame)),
+            None => skipped += 1,
+        }
+    }
+
+    if skipped > 0 {
+        warn!(
+            skipped,
+            "Skipped JSON messages that could not be re-encoded"
+        );
+    }
+    Ok(frames)
+}
+
+fn json_frame(data: &[u8]) -> Option<Vec<u8>> {
+    let mut value: Value = serde_json::from_slice(data).ok()?;
+    let header: MavHeader = serde_json::from_value(value.get_mut("header")?.take()).ok()?;
+    let message: MavMessage = serde_json::from_value(value.get_mut("message")?.take()).ok()?;
+    let frame = crate::mavlink::encode(header, &message);
+    (!frame.is_empty()).then_some(frame)
+}
@@ -0,0 +1,112 @@
+use std::{
+    io::BufWriter,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, Result, anyhow};
+use serde_json::Value;
+use tracing::*;
+
+use crate::events;
+
+/// Cuts the recording around each occurrence of an event of the `recorder/events` channel, e.g:
+/// `arm` or `leak`, into small files named after the input, the event and the occurrence.
+/// Overlapping windows are merged into one file
+#[instrument(skip_all, fields(input = %input.display(), event = %event))]
+pub fn run(
+    input: &Path,
+    event: &str,
+    before: Duration,
+    after: Duration,
+    output: Option<&Path>,
+) -> Result<()> {
+    let bytes = std::fs::read(input).context("Failed to read MCAP file")?;
+    let windows = windows(&bytes, event, before, after)?;
+    if windows.is_empty() {
+        return Err(anyhow!("No {event:?} event in the recording"));
+    }
+
+    let directory = output
+        .or_else(|| input.parent())
+        .unwrap_or(Path::new("."))
+        .to_path_buf();
+    let stem = input
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut extracts = windows
+        .into_iter()
+        .enumerate()
+        .map(|(index, window)| {
+            let path = directory.join(format!("{stem}_{event}_{:02}.mcap", index + 1));
+            let file = std::fs::File::create(&path)
+                .with_context(|| format!("Failed to create {}", path.display()))?;
+            let writer =
+                mcap::Writer::new(BufWriter::new(file)).context("Failed to create writer")?;
+            Ok((window, path, writer))
+        })
+        .collect::<Result<Vec<(RangeInclusive<u64>, PathBuf, _)>>>()?;
+
+    for message in mcap::MessageStream::new(&bytes)? {
+        let message = message.context("Failed to read MCAP message")?;
+        for (window, _, writer) in &mut extracts {
+            if window.contains(&message.log_time) {
+                writer
+                    .write(&message)
+                    .context("Failed to write MCAP message")?;
+            }
+        }
+    }
+
+    let summary = mcap::Summary::read(&bytes)?;
+    for (window, path, mut writer) in extracts {
+        if let Some(summary) = &summary {
+            for index in &summary.metadata_indexes {
+                writer.write_metadata(&mcap::read::metadata(&bytes, index)?)?;
+            }
+            for index in &summary.attachment_indexes {
+                if window.contains(&index.log_time) {
+                    writer.attach(&mcap::read::attachment(&bytes, index)?)?;
+                }
+            }
+        }
+        writer.finish().context("Failed to finish extracted file")?;
+        info!(path = %path.display(), "Extracted event window");
+    }
+    Ok(())
+}
+
+/// Log time windows around the occurrences of the event, merged when they overlap
+fn windows(
+    bytes: &[u8],
+    event: &str,
+    before: Duration,
+    after: Duration,
+) -> Result<Vec<RangeInclusive<u64>>> {
+    let (before, after) = (before.as_nanos() as u64, after.as_nanos() as u64);
+    let mut occurrences = Vec::new();
+    for message in mcap::MessageStream::new(bytes)? {
+        let message = message.context("Failed to read MCAP message")?;
+        if events::is_events_channel(&message.channel)
+            && serde_json::from_slice::<Value>(&message.data)
+                .is_ok_and(|value| value["event"] == event)
+        {
+            occurrences.push(message.log_time);
+        }
+    }
+    occurrences.sort_unstable();
+
+    let mut windows: Vec<RangeInclusive<u64>> = Vec::new();
+    for occurrence in occurrences {
+        let window = occurrence.saturating_sub(before)..=occurrence.saturating_add(after);
+        match windows.last_mut() {
+            Some(last) if window.start() <= last.end() => {
+                *last = *last.start()..=*window.end();
+            }
+            _ => windows.push(window),
+        }
+    }
+    Ok(windows)
+}
@@ -0,0 +1,34 @@
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+
+use crate::health;
+
+/// Time given to the recorder to answer
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Queries the health of the running recorder, the exit code gates container health checks
+pub async fn run() -> Result<()> {
+    let session = super::open_session().await?;
+    let replies = session
+        .get(health::key())
+        .timeout(TIMEOUT)
+        .await
+        .map_err(|error| anyhow!("Failed to query the recorder health: {error}"))?;
+    let reply = replies
+        .recv_async()
+        .await
+        .map_err(|_| anyhow!("The recorder did not answer"))?;
+    let _ = session.close().await;
+
+    match reply.result() {
+        Ok(sample) => {
+            println!("{}", sample.payload().try_to_string()?);
+            Ok(())
+        }
+        Err(error) => Err(anyhow!(
+            "Recorder unhealthy: {}",
+            error.payload().try_to_string()?
+        )),
+    }
+}
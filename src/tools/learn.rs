@@ -0,0 +1,126 @@
+use std::{collections::BTreeMap, fmt::Write, path::Path, time::Duration};
+
+use anyhow::{Context, Result, anyhow};
+use tracing::*;
+
+/// Bandwidth above which a group is suggested as low priority, e.g: video streams
+const LOW_PRIORITY_BYTES_PER_SECOND: f64 = 1e6;
+/// Rate limit suggested, commented out, for the groups with faster topics
+const SUGGESTED_RATE_LIMIT: f64 = 10.0;
+
+/// Traffic of a topic during the observation
+#[derive(Default)]
+struct TopicStats {
+    messages: u64,
+    bytes: u64,
+    encoding: String,
+}
+
+/// Traffic of the topics sharing a parent key, e.g: the messages of a MAVLink component
+#[derive(Default)]
+struct Group<'a> {
+    topics: Vec<(&'a str, &'a TopicStats)>,
+    messages: u64,
+    bytes: u64,
+}
+
+/// Observes the bus for a while and writes a `[topics]` table grouping the seen topics, with
+/// their rates in comments and the high bandwidth groups as low priority
+#[instrument(skip_all, fields(output = %output.display()))]
+pub async fn run(duration: Duration, output: &Path) -> Result<()> {
+    let session = super::open_session().await?;
+    let subscriber = session
+        .declare_subscriber("**")
+        .await
+        .map_err(|error| anyhow!("Failed to declare subscriber: {error}"))?;
+
+    info!(?duration, "Observing the bus");
+    let mut topics = BTreeMap::<String, TopicStats>::new();
+    let deadline = tokio::time::sleep(duration);
+    tokio::pin!(deadline);
+    loop {
+        tokio::select! {
+            () = &mut deadline => break,
+            sample = subscriber.recv_async() => {
+                let Ok(sample) = sample else {
+                    break;
+                };
+                let stats = topics.entry(sample.key_expr().as_str().to_owned()).or_default();
+                stats.messages += 1;
+                stats.bytes += sample.payload().len() as u64;
+                stats.encoding = sample.encoding().to_string();
+            },
+        }
+    }
+    let _ = session.close().await;
+
+    if topics.is_empty() {
+        return Err(anyhow!(
+            "No samples received, check the --connect endpoints"
+        ));
+    }
+    std::fs::write(output, suggest(&topics, duration))
+        .with_context(|| format!("Failed to write {}", output.display()))?;
+    info!(topics = topics.len(), "Wrote suggested configuration");
+    Ok(())
+}
+
+/// Groups the topics by parent key, e.g: `mavlink/1/1/*`
+fn groups(topics: &BTreeMap<String, TopicStats>) -> BTreeMap<String, Group<'_>> {
+    let mut groups = BTreeMap::<String, Group>::new();
+    for (topic, stats) in topics {
+        let key = match topic.rsplit_once('/') {
+            Some((parent, _)) => format!("{parent}/*"),
+            None => topic.clone(),
+        };
+        let group = groups.entry(key).or_default();
+        group.topics.push((topic.as_str(), stats));
+        group.messages += stats.messages;
+        group.bytes += stats.bytes;
+    }
+    groups
+}
+
+fn suggest(topics: &BTreeMap<String, TopicStats>, duration: Duration) -> String {
+    let seconds = duration.as_secs_f64().max(f64::EPSILON);
+    let rates = |messages: u64, bytes: u64| {
+        format!(
+            "{:.1} messages/s, {:.1} kB/s",
+            messages as f64 / seconds,
+            bytes as f64 / seconds / 1e3
+        )
+    };
+    let groups = groups(topics);
+
+    let mut content = format!(
+        "# Suggested by `blueos-recorder learn` after observing the bus for {}s\n\
+         # Edit it and pass it with --config\n",
+        duration.as_secs()
+    );
+    for (key, group) in &groups {
+        let _ = writeln!(
+            content,
+            "\n[topics.\"{key}\"]\n# {} topics, {}",
+            group.topics.len(),
+            rates(group.messages, group.bytes)
+        );
+        let mut fastest = 0.0f64;
+        for (topic, stats) in &group.topics {
+            fastest = fastest.max(stats.messages as f64 / seconds);
+            let _ = writeln!(
+                content,
+                "#   {topic}: {}, {}",
+                rates(stats.messages, stats.bytes),
+                stats.encoding
+            );
+        }
+        let _ = writeln!(content, "enabled = true");
+        if fastest > SUGGESTED_RATE_LIMIT {
+            let _ = writeln!(content, "# rate_limit = {SUGGESTED_RATE_LIMIT:.1}");
+        }
+        if group.bytes as f64 / seconds >= LOW_PRIORITY_BYTES_PER_SECOND {
+            let _ = writeln!(content, "priority = \"low\"");
+        }
+    }
+    content
+}
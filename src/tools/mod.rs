@@ -0,0 +1,75 @@
+pub mod capture;
+pub mod diff;
+pub mod doctor;
+pub mod export_tlog;
+pub mod extract_event;
+pub mod healthcheck;
+pub mod learn;
+pub mod recover;
+pub mod redact;
+pub mod report;
+pub mod selftest;
+pub mod suggest_compression;
+pub mod watch_export;
+
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use zenoh::Session;
+
+use crate::cli::Command;
+
+/// Time given to the tools to reach the router
+const SESSION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Runs a one-shot subcommand instead of the recorder service
+pub async fn run(command: &Command) -> Result<()> {
+    match command {
+        Command::Capture {
+            duration,
+            topics,
+            output,
+        } => capture::run(*duration, topics, output).await,
+        Command::Diff { a, b } => diff::run(a, b),
+        Command::Doctor => doctor::run().await,
+        Command::Healthcheck => healthcheck::run().await,
+        Command::Learn { duration, output } => learn::run(*duration, output).await,
+        Command::ExportTlog { input, output } => export_tlog::run(input, output.as_deref()),
+        Command::ExtractEvent {
+            input,
+            event,
+            before,
+            after,
+            output,
+        } => extract_event::run(input, event, *before, *after, output.as_deref()),
+        Command::Report {
+            input,
+            output,
+            fields,
+        } => report::run(input, output.as_deref(), fields),
+        Command::Recover { input, output } => recover::run(input, output),
+        Command::Redact {
+            input,
+            output,
+            drop_gps,
+            drop_topics,
+            drop_fields,
+        } => redact::run(input, output, *drop_gps, drop_topics, drop_fields),
+        Command::Selftest { output, timeout } => selftest::run(output.as_deref(), *timeout).await,
+        Command::WatchExport {
+            interval,
+            concurrency,
+        } => watch_export::run(*interval, *concurrency).await,
+        Command::SuggestCompression { inputs } => suggest_compression::run(inputs),
+    }
+}
+
+/// Opens the zenoh session of a tool, failing instead of waiting for an unreachable router
+pub async fn open_session() -> Result<Session> {
+    match tokio::time::timeout(SESSION_TIMEOUT, zenoh::open(crate::zenoh_config(false))).await {
+        Ok(session) => session.map_err(|error| anyhow!("Failed to open zenoh session: {error}")),
+        Err(_) => Err(anyhow!(
+            "No answer from the router, check that zenohd runs and the --connect endpoints"
+        )),
+    }
+}
@@ -0,0 +1,74 @@
+use std::{collections::HashMap, io::BufWriter, path::Path};
+
+use anyhow::{Context, Result};
+use mcap::{read::Options, records::Record};
+use tracing::*;
+
+/// Writes a finished copy of a recording cut short, e.g: by a power loss before its summary was
+/// written, so it is indexed and seekable again. The records are copied up to the first truncated
+/// or corrupted one
+#[instrument(skip_all, fields(input = %input.display(), output = %output.display()))]
+pub fn run(input: &Path, output: &Path) -> Result<()> {
+    let bytes = std::fs::read(input).context("Failed to read MCAP file")?;
+    let file = std::fs::File::create(output).context("Failed to create recovered file")?;
+    let mut writer = mcap::Writer::new(BufWriter::new(file)).context("Failed to create writer")?;
+
+    // Ids in the recovered file, by their id in the input
+    let mut schemas = HashMap::new();
+    let mut channels = HashMap::new();
+    let mut messages = 0usize;
+    let records =
+        mcap::read::ChunkFlattener::new_with_options(&bytes, Options::IgnoreEndMagic.into())?;
+    for record in records {
+        let record = match record {
+            Ok(record) => record,
+            Err(error) => {
+                warn!(%error, "Stopping at a damaged record");
+                break;
+            }
+        };
+        match record {
+            Record::Schema { header, data } => {
+                let id = writer.add_schema(&header.name, &header.encoding, &data)?;
+                schemas.insert(header.id, id);
+            }
+            Record::Channel(channel) => {
+                // Schemaless channels have the schema id 0
+                let schema_id = schemas.get(&channel.schema_id).copied().unwrap_or(0);
+                let id = writer.add_channel(
+                    schema_id,
+                    &channel.topic,
+                    &channel.message_encoding,
+                    &channel.metadata,
+                )?;
+                channels.insert(channel.id, id);
+            }
+            Record::Message { header, data } => {
+                let Some(&channel_id) = channels.get(&header.channel_id) else {
+                    continue;
+                };
+                let header = mcap::records::MessageHeader {
+                    channel_id,
+                    ..header
+                };
+                writer.write_to_known_channel(&header, &data)?;
+                messages += 1;
+            }
+            Record::Metadata(metadata) => writer.write_metadata(&metadata)?,
+            Record::Attachment { header, data, .. } => writer.attach(&mcap::Attachment {
+                log_time: header.log_time,
+                create_time: header.create_time,
+                name: header.name,
+                media_type: header.media_type,
+                data,
+            })?,
+            // The summary of a finished input repeats the schemas and channels
+            Record::DataEnd(_) => break,
+            _ => {}
+        }
+    }
+    writer.finish().context("Failed to finish recovered file")?;
+
+    info!(messages, "Recovered recording");
+    Ok(())
+}
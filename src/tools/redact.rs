@@ -0,0 +1,172 @@
+use std::{borrow::Cow, collections::HashMap, io::BufWriter, path::Path};
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use tracing::*;
+use zenoh::key_expr::{OwnedKeyExpr, keyexpr};
+
+use crate::{
+    channel_descriptor::MessageEncoding,
+    foxglove_schemas,
+    mavlink::position::{self, LAUNCH_POSITION_METADATA},
+    mcap::LATEST_TOPIC,
+    ros_names,
+};
+
+/// Writes a copy of a recording without the dropped channels and fields, the other messages,
+/// metadata and attachments are kept as is
+#[instrument(skip_all, fields(input = %input.display(), output = %output.display()))]
+pub fn run(
+    input: &Path,
+    output: &Path,
+    drop_gps: bool,
+    drop_topics: &[OwnedKeyExpr],
+    drop_fields: &[String],
+) -> Result<()> {
+    let bytes = std::fs::read(input).context("Failed to read MCAP file")?;
+    let file = std::fs::File::create(output).context("Failed to create redacted file")?;
+    let mut writer = mcap::Writer::new(BufWriter::new(file)).context("Failed to create writer")?;
+
+    let mut dropped_channels = HashMap::new();
+    // Decisions by zenoh key, for the entries of the latest channel
+    let mut dropped_keys = HashMap::new();
+    let mut written = 0usize;
+    for message in mcap::MessageStream::new(&bytes)? {
+        let message = message.context("Failed to read MCAP message")?;
+        let channel = &message.channel;
+        let dropped = *dropped_channels.entry(channel.id).or_insert_with(|| {
+            let schema_name = channel.schema.as_ref().map(|schema| schema.name.as_str());
+            let key_expr = ros_names::key_expr(channel);
+            let dropped = is_dropped(
+                key_expr,
+                &channel.message_encoding,
+                schema_name,
+                drop_gps,
+                drop_topics,
+            );
+            if dropped {
+                info!(topic = %channel.topic, "Dropping channel");
+            }
+            dropped_keys.insert(key_expr.to_owned(), dropped);
+            dropped
+        });
+        if dropped {
+            continue;
+        }
+
+        // The latest channel repeats the last value of every channel
+        let data = if ros_names::key_expr(channel) == LATEST_TOPIC {
+            let Some(data) = redact_latest(
+                &message.data,
+                &dropped_keys,
+                drop_gps,
+                drop_topics,
+                drop_fields,
+            ) else {
+                continue;
+            };
+            Cow::Owned(data)
+        } else if !drop_fields.is_empty()
+            && channel.message_encoding == MessageEncoding::Json.as_str()
+        {
+            redact_fields(&message.data, drop_fields).map_or(message.data.clone(), Cow::Owned)
+        } else {
+            message.data.clone()
+        };
+        writer
+            .write(&mcap::Message { data, ..message })
+            .context("Failed to write MCAP message")?;
+        written += 1;
+    }
+
+    if let Some(summary) = mcap::Summary::read(&bytes)? {
+        for index in &summary.metadata_indexes {
+            if drop_gps && index.name == LAUNCH_POSITION_METADATA {
+                continue;
+            }
+            let metadata = mcap::read::metadata(&bytes, index)?;
+            writer.write_metadata(&metadata)?;
+        }
+        for index in &summary.attachment_indexes {
+            let attachment = mcap::read::attachment(&bytes, index)?;
+            writer.attach(&attachment)?;
+        }
+    }
+    writer.finish().context("Failed to finish redacted file")?;
+
+    info!(messages = written, "Redacted recording");
+    Ok(())
+}
+
+fn is_dropped(
+    topic: &str,
+    message_encoding: &str,
+    schema_name: Option<&str>,
+    drop_gps: bool,
+    drop_topics: &[OwnedKeyExpr],
+) -> bool {
+    let location = position::is_location_topic(topic)
+        || topic.starts_with("nmea/")
+        || message_encoding == MessageEncoding::Mavlink.as_str()
+        || schema_name == Some(foxglove_schemas::LOCATION_FIX);
+    (drop_gps && location)
+        || keyexpr::new(topic)
+            .is_ok_and(|topic| drop_topics.iter().any(|expr| expr.includes(topic)))
+}
+
+/// Redacts an entry of the latest channel as the channel it holds the value of, `None` when the
+/// entry is dropped or is not valid JSON
+fn redact_latest(
+    data: &[u8],
+    dropped_keys: &HashMap<String, bool>,
+    drop_gps: bool,
+    drop_topics: &[OwnedKeyExpr],
+    drop_fields: &[String],
+) -> Option<Vec<u8>> {
+    let mut latest: Value = serde_json::from_slice(data).ok()?;
+    let topic = latest.get("topic")?.as_str()?;
+    let dropped = dropped_keys.get(topic).copied().unwrap_or_else(|| {
+        is_dropped(
+            topic,
+            MessageEncoding::Json.as_str(),
+            None,
+            drop_gps,
+            drop_topics,
+        )
+    });
+    if dropped {
+        return None;
+    }
+    if let Some(value) = latest.get_mut("value") {
+        remove_fields(value, drop_fields);
+    }
+    serde_json::to_vec(&latest).ok()
+}
+
+/// Removes the fields from a JSON message, `None` when the message is not valid JSON
+fn redact_fields(data: &[u8], fields: &[String]) -> Option<Vec<u8>> {
+    let mut value: Value = serde_json::from_slice(data).ok()?;
+    remove_fields(&mut value, fields);
+    serde_json::to_vec(&value).ok()
+}
+
+fn remove_fields(value: &mut Value, fields: &[String]) {
+    for pointer in fields {
+        let Some((parent, key)) = pointer.rsplit_once('/') else {
+            continue;
+        };
+        match value.pointer_mut(parent) {
+            Some(Value::Object(object)) => {
+                object.remove(key);
+            }
+            Some(Value::Array(array)) => {
+                if let Ok(index) = key.parse::<usize>()
+                    && index < array.len()
+                {
+                    array.remove(index);
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use std::{collections::BTreeMap, fmt::Write, path::Path};
+
+use anyhow::{Context, Result, anyhow};
+use serde_json::Value;
+use tracing::*;
+use zenoh::key_expr::{OwnedKeyExpr, keyexpr};
+
+use crate::{channel_descriptor::MessageEncoding, events, ros_names};
+
+/// Plotted when no field is given: the vehicle altitude, i.e: depth, and battery voltage
+const DEFAULT_FIELDS: [&str; 2] = [
+    "mavlink/**/VFR_HUD:/message/alt",
+    "mavlink/**/SYS_STATUS:/message/voltage_battery",
+];
+/// Points kept per sparkline, samples are decimated above it
+const SPARKLINE_POINTS: usize = 500;
+const SPARKLINE_WIDTH: f64 = 600.0;
+const SPARKLINE_HEIGHT: f64 = 60.0;
+
+/// Numeric JSON field plotted in the report, e.g: `mavlink/**/VFR_HUD:/message/alt`
+struct Field {
+    topic: OwnedKeyExpr,
+    pointer: String,
+}
+
+impl Field {
+    fn parse(field: &str) -> Result<Self> {
+        let (topic, pointer) = field
+            .rsplit_once(':')
+            .ok_or_else(|| anyhow!("Invalid field {field:?}, expected KEY_EXPR:POINTER"))?;
+        Ok(Self {
+            topic: OwnedKeyExpr::autocanonize(topic.to_owned())
+                .map_err(|error| anyhow!("Invalid field key expression {topic:?}: {error}"))?,
+            pointer: pointer.to_owned(),
+        })
+    }
+}
+
+#[derive(Default)]
+struct TopicStats {
+    schema: String,
+    messages: u64,
+    bytes: u64,
+}
+
+/// Renders a standalone HTML summary of a recording: duration, metadata, topics, sparklines of
+/// numeric fields and events, readable in any browser
+#[instrument(skip_all, fields(input = %input.display()))]
+pub fn run(input: &Path, output: Option<&Path>, fields: &[String]) -> Result<()> {
+    let output = output
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| input.with_extension("html"));
+    let fields = if fields.is_empty() {
+        DEFAULT_FIELDS
+            .iter()
+            .map(|field| Field::parse(field))
+            .collect()
+    } else {
+        fields
+            .iter()
+            .map(|field| Field::parse(field))
+            .collect::<Result<Vec<_>>>()
+    }?;
+
+    let bytes = std::fs::read(input).context("Failed to read MCAP file")?;
+    let mut topics = BTreeMap::<String, TopicStats>::new();
+    let mut series = BTreeMap::<String, Vec<(u64, f64)>>::new();
+    let mut events = Vec::new();
+    let (mut start, mut end) = (u64::MAX, 0);
+    for message in mcap::MessageStream::new(&bytes)? {
+        let message = message.context("Failed to read MCAP message")?;
+        let channel = &message.channel;
+        start = start.min(message.log_time);
+        end = end.max(message.log_time);
+
+        let stats = topics.entry(channel.topic.clone()).or_default();
+        if stats.messages == 0 {
+            stats.schema = channel
+                .schema
+                .as_ref()
+                .map(|schema| schema.name.clone())
+                .unwrap_or_default();
+        }
+        stats.messages += 1;
+        stats.bytes += message.data.len() as u64;
+
+        if channel.message_encoding != MessageEncoding::Json.as_str() {
+            continue;
+        }
+        if events::is_events_channel(channel) {
+            if let Ok(event) = serde_json::from_slice::<Value>(&message.data) {
+                events.push((message.log_time, event));
+            }
+            continue;
+        }
+        let Ok(topic) = keyexpr::new(ros_names::key_expr(channel)) else {
+            continue;
+        };
+        let matching: Vec<_> = fields
+            .iter()
+            .filter(|field| field.topic.includes(topic))
+            .collect();
+        if matching.is_empty() {
+            continue;
+        }
+        let Ok(value) = serde_json::from_slice::<Value>(&message.data) else {
+            continue;
+        };
+        for field in matching {
+            if let Some(number) = value.pointer(&field.pointer).and_then(Value::as_f64) {
+                series
+                    .entry(format!("{}{}", channel.topic, field.pointer))
+                    .or_default()
+                    .push((message.log_time, number));
+            }
+        }
+    }
+    if topics.is_empty() {
+        return Err(anyhow!("The recording holds no message"));
+    }
+
+    let metadata = match mcap::Summary::read(&bytes)? {
+        Some(summary) => summary
+            .metadata_indexes
+            .iter()
+            .map(|index| mcap::read::metadata(&bytes, index))
+            .collect::<Result<Vec<_>, _>>()?,
+        None => Vec::new(),
+    };
+
+    let duration = end.saturating_sub(start) as f64 / 1e9;
+    let title = input
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let rfc3339 = |ns: u64| chrono::DateTime::from_timestamp_nanos(ns as i64).to_rfc3339();
+
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{title}</title><style>\
+         body {{ font-family: sans-serif; margin: 2em; }} \
+         table {{ border-collapse: collapse; }} \
+         td, th {{ border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: left; }} \
+         svg {{ background: #f6f8fa; }}\
+         </style></head><body>\n<h1>{title}</h1>\n<p>From {} to {}, {:.0} seconds</p>\n",
+        rfc3339(start),
+        rfc3339(end),
+        duration,
+        title = escape(&title),
+    );
+
+    for metadata in &metadata {
+        let _ = writeln!(html, "<h2>{}</h2>\n<table>", escape(&metadata.name));
+        for (key, value) in &metadata.metadata {
+            let _ = writeln!(
+                html,
+                "<tr><th>{}</th><td>{}</td></tr>",
+                escape(key),
+                escape(value)
+            );
+        }
+        html.push_str("</table>\n");
+    }
+
+    html.push_str("<h2>Fields</h2>\n");
+    if series.is_empty() {
+        html.push_str("<p>None of the fields was recorded</p>\n");
+    }
+    for (name, points) in &series {
+        let _ = writeln!(html, "<h3>{}</h3>\n{}", escape(name), sparkline(points));
+    }
+
+    html.push_str(
+        "<h2>Events</h2>\n<table>\n<tr><th>Time</th><th>Event</th><th>Message</th></tr>\n",
+    );
+    for (log_time, event) in &events {
+        let _ = writeln!(
+            html,
+            "<tr><td>+{:.1}s</td><td>{}</td><td>{}</td></tr>",
+            log_time.saturating_sub(start) as f64 / 1e9,
+            escape(event["event"].as_str().unwrap_or_default()),
+            escape(event["message"].as_str().unwrap_or_default()),
+        );
+    }
+    html.push_str("</table>\n");
+
+    html.push_str(
+        "<h2>Topics</h2>\n<table>\n<tr><th>Topic</th><th>Schema</th><th>Messages</th>\
+         <th>Rate</th><th>Size</th></tr>\n",
+    );
+    for (topic, stats) in &topics {
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{:.1} Hz</td><td>{:.1} kB</td></tr>",
+            escape(topic),
+            escape(&stats.schema),
+            stats.messages,
+            stats.messages as f64 / duration.max(f64::EPSILON),
+            stats.bytes as f64 / 1e3,
+        );
+    }
+    html.push_str("</table>\n</body></html>\n");
+
+    std::fs::write(&output, html).context("Failed to write report")?;
+    info!(output = %output.display(), "Rendered report");
+    Ok(())
+}
+
+/// Inline SVG polyline of the values, with their range
+fn sparkline(points: &[(u64, f64)]) -> String {
+    // Messages are read in file order, which other writers do not keep sorted by log time
+    let mut sorted = points.to_vec();
+    sorted.sort_by_key(|(log_time, _)| *log_time);
+    let step = sorted.len().div_ceil(SPARKLINE_POINTS).max(1);
+    let points: Vec<_> = sorted.iter().step_by(step).collect();
+    let (first, last) = (points[0].0, points[points.len() - 1].0);
+    let (min, max) = points.iter().fold(
+        (f64::INFINITY, f64::NEG_INFINITY),
+        |(min, max), (_, value)| (min.min(*value), max.max(*value)),
+    );
+    let time_span = last.saturating_sub(first).max(1) as f64;
+    let value_span = (max - min).max(f64::EPSILON);
+
+    let mut polyline = String::new();
+    for (log_time, value) in points {
+        let x = (log_time - first) as f64 / time_span * SPARKLINE_WIDTH;
+        let y = SPARKLINE_HEIGHT - (value - min) / value_span * SPARKLINE_HEIGHT;
+        let _ = write!(polyline, "{x:.1},{y:.1} ");
+    }
+    format!(
+        "<svg width=\"{SPARKLINE_WIDTH}\" height=\"{SPARKLINE_HEIGHT}\">\
+         <polyline fill=\"none\" stroke=\"#1f6feb\" points=\"{}\"/></svg> {min:.2} to {max:.2}",
+        polyline.trim_end()
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,275 @@
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result, anyhow};
+use serde_json::json;
+use tracing::*;
+use zenoh::{
+    Session, bytes::Encoding, handlers::FifoChannelHandler, pubsub::Subscriber, sample::Sample,
+};
+
+use crate::{
+    cdr::CdrDecoder,
+    channel_descriptor::{ChannelDescriptor, MessageEncoding},
+    cli,
+    mcap::Mcap,
+};
+
+/// Samples published on each of the test topics
+const SAMPLES: u32 = 10;
+/// Period of the probes sent until the router routes them back
+const PROBE_INTERVAL: Duration = Duration::from_millis(100);
+const CDR_SCHEMA: &str = "std_msgs.String";
+
+/// Sample published by the self-test, compared with the recorded one
+struct Expected {
+    topic: String,
+    encoding: Encoding,
+    payload: Vec<u8>,
+}
+
+/// Publishes known JSON, CDR and synthetic HEARTBEAT samples through the connected router, records
+/// them with the recorder writer and verifies the file, printing the result of every topic.
+/// Only the routing, the channel registration and the writer are checked, not the sample handling
+/// of the service, see [`record`]
+#[instrument(skip_all)]
+pub async fn run(output: Option<&Path>, timeout: Duration) -> Result<()> {
+    let output = output
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| cli::recorder_path().join("selftest.mcap"));
+    let prefix = format!("selftest/{}", std::process::id());
+
+    // Publishing from another session makes the samples go through the router, instead of being
+    // delivered within the session
+    let subscriber_session = super::open_session().await?;
+    let publisher_session = super::open_session().await?;
+    let subscriber = subscriber_session
+        .declare_subscriber(format!("{prefix}/**"))
+        .await
+        .map_err(|error| anyhow!("Failed to declare subscriber: {error}"))?;
+
+    wait_for_route(&publisher_session, &subscriber, &prefix, timeout).await?;
+    let expected = expected(&prefix);
+    for sample in &expected {
+        publisher_session
+            .put(&sample.topic, sample.payload.clone())
+            .encoding(sample.encoding.clone())
+            .await
+            .map_err(|error| anyhow!("Failed to publish {}: {error}", sample.topic))?;
+    }
+    let received = receive(&subscriber, &prefix, expected.len(), timeout).await;
+    let _ = publisher_session.close().await;
+    let _ = subscriber_session.close().await;
+
+    record(&output, &received)?;
+    println!(
+        "Recorded {} of {} samples in {}",
+        received.len(),
+        expected.len(),
+        output.display()
+    );
+    verify(&output, &expected)
+}
+
+/// Sends probes until one comes back, the subscriber is only known to the router after a while
+async fn wait_for_route(
+    session: &Session,
+    subscriber: &Subscriber<FifoChannelHandler<Sample>>,
+    prefix: &str,
+    timeout: Duration,
+) -> Result<()> {
+    let start = Instant::now();
+    while start.elapsed() < timeout {
+        session
+            .put(format!("{prefix}/probe"), "probe")
+            .await
+            .map_err(|error| anyhow!("Failed to publish probe: {error}"))?;
+        if tokio::time::timeout(PROBE_INTERVAL, subscriber.recv_async())
+            .await
+            .is_ok_and(|sample| sample.is_ok())
+        {
+            return Ok(());
+        }
+    }
+    Err(anyhow!(
+        "No sample came back through the router, check that zenohd runs and the --connect endpoints"
+    ))
+}
+
+/// Collects the test samples, the late probes are skipped
+async fn receive(
+    subscriber: &Subscriber<FifoChannelHandler<Sample>>,
+    prefix: &str,
+    count: usize,
+    timeout: Duration,
+) -> Vec<Sample> {
+    let probe = format!("{prefix}/probe");
+    let mut received = Vec::new();
+    let deadline = tokio::time::sleep(timeout);
+    tokio::pin!(deadline);
+    while received.len() < count {
+        tokio::select! {
+            () = &mut deadline => break,
+            sample = subscriber.recv_async() => {
+                let Ok(sample) = sample else {
+                    break;
+                };
+                if sample.key_expr().as_str() != probe {
+                    received.push(sample);
+                }
+            },
+        }
+    }
+    received
+}
+
+fn expected(prefix: &str) -> Vec<Expected> {
+    (0..SAMPLES)
+        .flat_map(|sequence| {
+            let heartbeat = json!({
+                "header": { "system_id": 255, "component_id": 190, "sequence": sequence },
+                "message": {
+                    "type": "HEARTBEAT",
+                    "custom_mode": 0,
+                    "mavtype": { "type": "MAV_TYPE_GCS" },
+                    "autopilot": { "type": "MAV_AUTOPILOT_INVALID" },
+                    "base_mode": { "bits": 0 },
+                    "system_status": { "type": "MAV_STATE_ACTIVE" },
+                    "mavlink_version": 3,
+                },
+            });
+            let json = json!({ "sequence": sequence, "depth": sequence as f64 * 0.5 });
+            [
+                Expected {
+                    topic: format!("{prefix}/json"),
+                    encoding: Encoding::APPLICATION_JSON,
+                    payload: json.to_string().into_bytes(),
+                },
+                Expected {
+                    topic: format!("{prefix}/cdr"),
+                    encoding: Encoding::APPLICATION_CDR.with_schema(CDR_SCHEMA),
+                    payload: cdr_string(&cdr_text(sequence)),
+                },
+                Expected {
+                    topic: format!("{prefix}/mavlink/255/190/HEARTBEAT"),
+                    encoding: Encoding::APPLICATION_JSON,
+                    payload: heartbeat.to_string().into_bytes(),
+                },
+            ]
+        })
+        .collect()
+}
+
+fn cdr_text(sequence: u32) -> String {
+    format!("selftest {sequence}")
+}
+
+/// Serializes a `std_msgs/String` in little-endian CDR
+fn cdr_string(text: &str) -> Vec<u8> {
+    let mut payload = vec![0x00, 0x01, 0x00, 0x00];
+    // The length counts the terminating NUL
+    payload.extend_from_slice(&(text.len() as u32 + 1).to_le_bytes());
+    payload.extend_from_slice(text.as_bytes());
+    payload.push(0);
+    payload
+}
+
+/// Writes the received samples with the recorder writer, registering the channels from their
+/// encoding and payload. The samples are recorded as they are, the topics configuration,
+/// transforms, CDR to JSON conversion and channel policies of the service do not apply
+fn record(output: &Path, received: &[Sample]) -> Result<()> {
+    let mut options = cli::mcap_options();
+    options.sinks.clear();
+    let schema_path = cli::schema_path();
+    let mut mcap = Mcap::try_new(
+        output,
+        &options,
+        &[MessageEncoding::Json, MessageEncoding::Cdr],
+    )?;
+    for sample in received {
+        let topic = sample.key_expr().as_str();
+        let payload = sample.payload().to_bytes();
+        let new_channel = if mcap.has_channel(topic) {
+            None
+        } else {
+            Some(ChannelDescriptor::new(
+                topic,
+                sample.encoding(),
+                &payload,
+                schema_path.as_ref(),
+            )?)
+        };
+        let log_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64;
+        mcap.write_message(topic, log_time, log_time, None, &payload, new_channel)
+            .with_context(|| format!("Failed to record {topic}"))?;
+    }
+    mcap.finish()
+}
+
+/// Reads the file back and compares every topic with what was published
+fn verify(output: &Path, expected: &[Expected]) -> Result<()> {
+    let bytes = std::fs::read(output)
+        .with_context(|| format!("Failed to read MCAP file {}", output.display()))?;
+    let mut recorded = BTreeMap::<String, Vec<Vec<u8>>>::new();
+    for message in mcap::MessageStream::new(&bytes)? {
+        let message = message.context("Failed to read MCAP message")?;
+        recorded
+            .entry(message.channel.topic.clone())
+            .or_default()
+            .push(message.data.into_owned());
+    }
+
+    let mut published = BTreeMap::<&str, Vec<&[u8]>>::new();
+    for sample in expected {
+        published
+            .entry(&sample.topic)
+            .or_default()
+            .push(&sample.payload);
+    }
+
+    let mut decoder = CdrDecoder::new(cli::schema_path());
+    let mut failed = 0;
+    for (topic, payloads) in &published {
+        let messages = recorded.get(*topic).map(Vec::as_slice).unwrap_or_default();
+        let differing = payloads
+            .iter()
+            .zip(messages)
+            .filter(|(published, recorded)| **published != recorded.as_slice())
+            .count();
+        // The CDR messages must also decode with the schema stored for them
+        let mut undecodable = 0;
+        if topic.ends_with("/cdr") {
+            undecodable = messages
+                .iter()
+                .enumerate()
+                .filter(|(sequence, payload)| {
+                    decoder.decode(CDR_SCHEMA, payload).ok()
+                        != Some(json!({ "data": cdr_text(*sequence as u32) }))
+                })
+                .count();
+        }
+
+        if messages.len() == payloads.len() && differing == 0 && undecodable == 0 {
+            println!("[ OK ] {topic}: {} samples", messages.len());
+        } else {
+            failed += 1;
+            println!(
+                "[FAIL] {topic}: {} of {} samples recorded, {differing} differ, {undecodable} fail \
+                 to decode",
+                messages.len(),
+                payloads.len()
+            );
+        }
+    }
+
+    if failed > 0 {
+        return Err(anyhow!("{failed} topics failed the self-test"));
+    }
+    Ok(())
+}
@@ -0,0 +1,119 @@
+use std::{
+    collections::HashSet,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow};
+use mcap::{
+    records::Record,
+    sans_io::{LinearReadEvent, LinearReader},
+};
+use tracing::*;
+
+use crate::channel_descriptor::MessageEncoding;
+
+/// Plain zstd settings MCAP readers support, from the cheapest
+const LEVELS: [i32; 4] = [3, 9, 15, 19];
+const CHUNK_SIZES: [usize; 3] = [768 * 1024, 4 * 1024 * 1024, 16 * 1024 * 1024];
+/// JSON messages kept for the measure, bounding the memory used on the vehicle computer
+const MAX_SAMPLES_SIZE: usize = 64 * 1024 * 1024;
+/// Share of the best ratio a cheaper setting has to reach to be suggested
+const RATIO_TOLERANCE: f64 = 0.95;
+
+/// Measures the compression of the JSON messages of the recordings by the zstd levels and chunk
+/// sizes MCAP readers support, then suggests the cheapest setting close to the best ratio
+#[instrument(skip_all)]
+pub fn run(inputs: &[PathBuf]) -> Result<()> {
+    let mut samples = Vec::new();
+    let mut bytes = 0;
+    for input in inputs {
+        let complete = read_json_messages(input, &mut samples, &mut bytes)
+            .with_context(|| format!("Failed to read MCAP file {}", input.display()))?;
+        if !complete {
+            warn!("Measure limited to the first {MAX_SAMPLES_SIZE} bytes of JSON messages");
+            break;
+        }
+    }
+    if samples.is_empty() {
+        return Err(anyhow!("No JSON messages to measure"));
+    }
+    info!(messages = samples.len(), bytes, "Measuring zstd settings");
+
+    let mut results = Vec::new();
+    for chunk_size in CHUNK_SIZES {
+        let chunks = chunks(&samples, chunk_size);
+        for level in LEVELS {
+            let mut compressor = zstd::bulk::Compressor::new(level)?;
+            let mut compressed = 0;
+            for chunk in &chunks {
+                compressed += compressor.compress(chunk)?.len();
+            }
+            let ratio = bytes as f64 / compressed as f64;
+            info!(chunk_size, level, compressed, ratio, "Plain zstd");
+            results.push((chunk_size, level, ratio));
+        }
+    }
+    let best_ratio = results
+        .iter()
+        .map(|(_, _, ratio)| *ratio)
+        .fold(0.0, f64::max);
+    if let Some((chunk_size, level, ratio)) = results
+        .into_iter()
+        .find(|(_, _, ratio)| *ratio >= best_ratio * RATIO_TOLERANCE)
+    {
+        info!(
+            ratio,
+            best_ratio,
+            "Suggested settings: --mcap-chunk-size {chunk_size} --mcap-compression-level {level}"
+        );
+    }
+    Ok(())
+}
+
+/// Streams a recording, collecting its JSON messages until they reach [`MAX_SAMPLES_SIZE`] bytes,
+/// returns false when some were left out. The recordings are not loaded whole, the video can be
+/// larger than the memory of the vehicle computer
+fn read_json_messages(input: &Path, samples: &mut Vec<Vec<u8>>, size: &mut usize) -> Result<bool> {
+    let mut file = std::fs::File::open(input)?;
+    let mut reader = LinearReader::new();
+    let mut json_channels = HashSet::new();
+    while let Some(event) = reader.next_event() {
+        match event? {
+            LinearReadEvent::ReadRequest(need) => {
+                let read = file.read(reader.insert(need))?;
+                reader.notify_read(read);
+            }
+            LinearReadEvent::Record { opcode, data } => match mcap::parse_record(opcode, data)? {
+                Record::Channel(channel)
+                    if channel.message_encoding == MessageEncoding::Json.as_str() =>
+                {
+                    json_channels.insert(channel.id);
+                }
+                Record::Message { header, data } if json_channels.contains(&header.channel_id) => {
+                    if *size + data.len() > MAX_SAMPLES_SIZE {
+                        return Ok(false);
+                    }
+                    *size += data.len();
+                    samples.push(data.into_owned());
+                }
+                _ => {}
+            },
+        }
+    }
+    Ok(true)
+}
+
+/// Concatenates the messages in chunks of `chunk_size` bytes
+fn chunks(samples: &[Vec<u8>], chunk_size: usize) -> Vec<Vec<u8>> {
+    let mut chunks = vec![Vec::with_capacity(chunk_size)];
+    for sample in samples {
+        let chunk = chunks.last_mut().unwrap();
+        chunk.extend_from_slice(sample);
+        if chunk.len() >= chunk_size {
+            chunks.push(Vec::with_capacity(chunk_size));
+        }
+    }
+    chunks.retain(|chunk| !chunk.is_empty());
+    chunks
+}
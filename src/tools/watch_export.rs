@@ -0,0 +1,133 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::Duration,
+};
+
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use tokio::task::JoinSet;
+use tracing::*;
+
+use crate::{
+    catalog::{self, Manifest},
+    cli, config,
+    tools::{export_tlog, report},
+};
+
+/// Step of the pipeline run on every finished recording, configured in the `exports` table
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Export {
+    /// HTML summary next to the recording, as the `report` subcommand
+    Report,
+    /// MAVLink telemetry log next to the recording, as the `export-tlog` subcommand
+    #[serde(rename = "export_tlog")]
+    Tlog,
+    /// External command with the recording path in `RECORDER_FILE`, e.g: a CSV conversion or an
+    /// upload script
+    Command {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+}
+
+/// Watches the recorder path and runs the configured exports on every finished recording, at most
+/// `concurrency` recordings at once. Exported recordings are marked in their manifest, a failed
+/// one is retried on the next start
+pub async fn run(interval: Duration, concurrency: usize) -> Result<()> {
+    let exports = &config::get().exports;
+    if exports.is_empty() {
+        return Err(anyhow!(
+            "No exports configured, add [[exports]] to the configuration"
+        ));
+    }
+    let directory = cli::recorder_path();
+    info!(directory = %directory.display(), concurrency, "Watching for finished recordings");
+
+    let mut started = BTreeSet::new();
+    let mut tasks = JoinSet::new();
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                for manifest in catalog::manifests(&directory) {
+                    if tasks.len() >= concurrency.max(1) {
+                        break;
+                    }
+                    if manifest.end.is_none()
+                        || manifest.exported
+                        || started.contains(&manifest.file)
+                    {
+                        continue;
+                    }
+                    started.insert(manifest.file.clone());
+                    let recording = directory.join(&manifest.file);
+                    tasks.spawn(async move {
+                        let result = export(&recording, exports).await;
+                        (recording, result)
+                    });
+                }
+            },
+            Some(joined) = tasks.join_next() => {
+                match joined {
+                    Ok((recording, Ok(()))) => {
+                        let result = Manifest::load(&recording).and_then(|mut manifest| {
+                            manifest.exported = true;
+                            manifest.save(&recording)
+                        });
+                        match result {
+                            Ok(()) => info!(recording = %recording.display(), "Recording exported"),
+                            Err(error) => warn!(%error, "Failed to mark the recording exported"),
+                        }
+                    }
+                    Ok((recording, Err(error))) => warn!(
+                        %error,
+                        recording = %recording.display(),
+                        "Failed to export recording"
+                    ),
+                    Err(error) => warn!(%error, "Export task failed"),
+                }
+            },
+        }
+    }
+}
+
+/// Runs the pipeline on a recording, stopping at the first failing step
+async fn export(recording: &Path, exports: &[Export]) -> Result<()> {
+    for step in exports {
+        debug!(recording = %recording.display(), ?step, "Running export");
+        match step {
+            Export::Report => {
+                blocking(recording, |recording| report::run(&recording, None, &[])).await?
+            }
+            Export::Tlog => {
+                blocking(recording, |recording| export_tlog::run(&recording, None)).await?
+            }
+            Export::Command { command, args } => {
+                let status = tokio::process::Command::new(command)
+                    .args(args)
+                    .env("RECORDER_FILE", recording)
+                    .stdin(Stdio::null())
+                    .status()
+                    .await
+                    .with_context(|| format!("Failed to run {command}"))?;
+                if !status.success() {
+                    return Err(anyhow!("{command} failed: {status}"));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs a file-reading export off the runtime threads
+async fn blocking(
+    recording: &Path,
+    export: impl FnOnce(PathBuf) -> Result<()> + Send + 'static,
+) -> Result<()> {
+    let recording = recording.to_path_buf();
+    tokio::task::spawn_blocking(move || export(recording)).await?
+}
@@ -0,0 +1,205 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use zenoh::key_expr::keyexpr;
+
+use crate::{
+    config::Config,
+    priority::Priority,
+    time_sync::{ClockSource, DEFAULT_CLOCK},
+    transform::{self, Transform},
+};
+
+/// Settings of the topics matching a key of the `[topics]` table, e.g:
+/// `[topics."mavlink/**/HEARTBEAT"]` with `rate_limit = 1.0` and `priority = "low"`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TopicConfig {
+    /// `false` stops recording the topics, they still drive the triggers
+    pub enabled: Option<bool>,
+    /// Maximum recorded messages per second of each topic
+    pub rate_limit: Option<f64>,
+    pub priority: Option<Priority>,
+    /// Channel of the topics in the recording, instead of their key
+    pub rename: Option<String>,
+    pub transform: Option<Transform>,
+    /// Timestamp sources in priority order, e.g: `["mavlink", "local"]` for a bridge that does
+    /// not stamp its samples, defaults to the zenoh HLC then the reception time
+    pub clock: Option<Vec<ClockSource>>,
+    /// Not supported, all the topics are recorded in the same file
+    #[serde(alias = "target")]
+    pub file: Option<toml::Value>,
+}
+
+/// Settings of a topic, the `[topics]` table taking precedence over the `priorities` and
+/// `transforms` rules
+pub struct TopicSettings<'a> {
+    pub enabled: bool,
+    pub rate_limit: Option<f64>,
+    pub priority: Priority,
+    pub rename: Option<&'a str>,
+    /// Key expression the transform is configured for, and the transform
+    pub transform: Option<(&'a str, &'a Transform)>,
+    pub clock: &'a [ClockSource],
+}
+
+/// Checks the keys of the `[topics]` table are key expressions, their rate limits positive,
+/// their clock sources set and no target file is asked for
+pub fn validate(topics: &BTreeMap<String, TopicConfig>) -> Result<()> {
+    for (key, topic) in topics {
+        keyexpr::new(key.as_str())
+            .map_err(|error| anyhow!("Invalid topics key expression {key:?}: {error}"))?;
+        if let Some(rate_limit) = topic.rate_limit
+            && !(rate_limit.is_finite() && rate_limit > 0.0)
+        {
+            return Err(anyhow!("Invalid rate limit of {key:?}: {rate_limit}"));
+        }
+        if topic.clock.as_ref().is_some_and(Vec::is_empty) {
+            return Err(anyhow!("Empty clock sources of {key:?}"));
+        }
+        if topic.file.is_some() {
+            return Err(anyhow!(
+                "Unsupported target file of {key:?}, all the topics are recorded in the same file"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Resolves the settings of a topic. When several entries of the `[topics]` table set a field,
+/// the one with the most literal chunks wins, e.g: `mavlink/*/1/HEARTBEAT` over `mavlink/**`
+pub fn resolve<'a>(config: &'a Config, topic: &str) -> TopicSettings<'a> {
+    let mut matching: Vec<(&str, &TopicConfig)> = match keyexpr::new(topic) {
+        Ok(topic) => config
+            .topics
+            .iter()
+            .filter(|(key, _)| keyexpr::new(key.as_str()).is_ok_and(|key| key.includes(topic)))
+            .map(|(key, settings)| (key.as_str(), settings))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    matching.sort_by_key(|(key, _)| std::cmp::Reverse(literal_chunks(key)));
+
+    TopicSettings {
+        enabled: matching
+            .iter()
+            .find_map(|(_, settings)| settings.enabled)
+            .unwrap_or(true),
+        rate_limit: matching
+            .iter()
+            .find_map(|(_, settings)| settings.rate_limit),
+        priority: matching
+            .iter()
+            .find_map(|(_, settings)| settings.priority)
+            .unwrap_or_else(|| Priority::of(&config.priorities, topic)),
+        rename: matching
+            .iter()
+            .find_map(|(_, settings)| settings.rename.as_deref()),
+        transform: matching
+            .iter()
+            .find_map(|(key, settings)| Some((*key, settings.transform.as_ref()?)))
+            .or_else(|| {
+                transform::find(&config.transforms, topic)
+                    .map(|rule| (rule.topic.as_str(), &rule.transform))
+            }),
+        clock: matching
+            .iter()
+            .find_map(|(_, settings)| settings.clock.as_deref())
+            .unwrap_or(DEFAULT_CLOCK),
+    }
+}
+
+fn literal_chunks(key: &str) -> usize {
+    key.split('/').filter(|chunk| !chunk.contains('*')).count()
+}
+
+/// Drops the samples of a topic arriving faster than its rate limit
+#[derive(Default)]
+pub struct RateLimiter {
+    last_recorded: HashMap<String, Instant>,
+}
+
+impl RateLimiter {
+    pub fn should_record(&mut self, topic: &str, rate_limit: Option<f64>) -> bool {
+        let Some(rate_limit) = rate_limit else {
+            return true;
+        };
+
+        let now = Instant::now();
+        match self.last_recorded.get_mut(topic) {
+            Some(last_recorded)
+                if now.duration_since(*last_recorded)
+                    < Duration::from_secs_f64(1.0 / rate_limit) =>
+            {
+                false
+            }
+            Some(last_recorded) => {
+                *last_recorded = now;
+                true
+            }
+            None => {
+                self.last_recorded.insert(topic.to_owned(), now);
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topic_settings() {
+        let config: Config = toml::from_str(
+            r#"
+            [topics."mavlink/**"]
+            rate_limit = 10.0
+            priority = "low"
+
+            [topics."mavlink/*/1/HEARTBEAT"]
+            enabled = false
+            rename = "heartbeat"
+
+            [topics."mavlink/*/1/SCALED_PRESSURE"]
+            transform = { select = ["/message/time_boot_ms", "/message/press_abs"] }
+            clock = ["mavlink", "local"]
+            "#,
+        )
+        .unwrap();
+        validate(&config.topics).unwrap();
+
+        let heartbeat = resolve(&config, "mavlink/1/1/HEARTBEAT");
+        assert!(!heartbeat.enabled);
+        assert_eq!(heartbeat.rate_limit, Some(10.0));
+        assert_eq!(heartbeat.priority, Priority::Low);
+        assert_eq!(heartbeat.rename, Some("heartbeat"));
+        assert!(heartbeat.transform.is_none());
+
+        let pressure = resolve(&config, "mavlink/1/1/SCALED_PRESSURE");
+        assert!(pressure.enabled);
+        assert_eq!(
+            pressure.transform.map(|(key, _)| key),
+            Some("mavlink/*/1/SCALED_PRESSURE")
+        );
+        assert_eq!(pressure.clock, [ClockSource::Mavlink, ClockSource::Local]);
+        assert_eq!(heartbeat.clock, DEFAULT_CLOCK);
+
+        let other = resolve(&config, "video/camera");
+        assert!(other.enabled && other.rate_limit.is_none());
+        assert_eq!(other.priority, Priority::Normal);
+
+        let config: Config = toml::from_str(
+            r#"
+            [topics."video/**"]
+            target = "video.mcap"
+            "#,
+        )
+        .unwrap();
+        assert!(validate(&config.topics).is_err());
+    }
+}
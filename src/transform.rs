@@ -0,0 +1,378 @@
+use std::{borrow::Cow, collections::BTreeMap};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tracing::*;
+use zenoh::key_expr::OwnedKeyExpr;
+
+/// A transform applied to JSON payloads of every topic matching `topic`
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TransformRule {
+    /// Key expression of the topics this rule applies to
+    pub topic: OwnedKeyExpr,
+    #[serde(flatten)]
+    pub transform: Transform,
+}
+
+/// Steps of a JSON transform
+///
+/// Fields are addressed by JSON pointers (RFC 6901), e.g. `/message/roll`.
+/// Steps are applied in the following order: scale, select, rename.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Transform {
+    /// Fields to keep, everything else is dropped. Empty keeps all fields
+    pub select: Vec<String>,
+    /// Fields to move, from the original pointer to the new one
+    pub rename: BTreeMap<String, String>,
+    /// Numeric fields to scale, either by a factor or a known unit conversion
+    pub scale: BTreeMap<String, Scale>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Scale {
+    Factor(f64),
+    Unit(UnitConversion),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnitConversion {
+    RadToDeg,
+    DegToRad,
+    MmToM,
+    CmToM,
+    MilliToUnit,
+    CentiToUnit,
+}
+
+impl Scale {
+    pub fn factor(self) -> f64 {
+        match self {
+            Self::Factor(factor) => factor,
+            Self::Unit(UnitConversion::RadToDeg) => 180.0 / std::f64::consts::PI,
+            Self::Unit(UnitConversion::DegToRad) => std::f64::consts::PI / 180.0,
+            Self::Unit(UnitConversion::MmToM | UnitConversion::MilliToUnit) => 1e-3,
+            Self::Unit(UnitConversion::CmToM | UnitConversion::CentiToUnit) => 1e-2,
+        }
+    }
+}
+
+impl TransformRule {
+    pub fn matches(&self, topic: &str) -> bool {
+        zenoh::key_expr::keyexpr::new(topic).is_ok_and(|topic| self.topic.includes(topic))
+    }
+}
+
+impl Transform {
+    pub fn apply(&self, mut value: Value) -> Value {
+        for (pointer, scale) in &self.scale {
+            if let Some(field) = value.pointer_mut(pointer) {
+                scale_value(field, scale.factor());
+            }
+        }
+
+        if !self.select.is_empty() {
+            let mut selected = Value::Object(Map::new());
+            for pointer in &self.select {
+                if let Some(field) = value.pointer(pointer) {
+                    insert_pointer(&mut selected, pointer, field.clone());
+                }
+            }
+            value = selected;
+        }
+
+        for (from, to) in &self.rename {
+            if let Some(field) = remove_pointer(&mut value, from) {
+                insert_pointer(&mut value, to, field);
+            }
+        }
+
+        value
+    }
+}
+
+/// How JSON payloads that are not objects, e.g: a bare depth or a status string, are recorded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum NonObjectJson {
+    /// Do not record them, Foxglove does not support non-object messages
+    #[default]
+    Drop,
+    /// Record them wrapped as `{"value": ...}`, the schema is inferred from the wrapped object
+    Wrap,
+}
+
+/// Returns the transform rule applied to a topic, the first one matching it
+pub fn find<'a>(rules: &'a [TransformRule], topic: &str) -> Option<&'a TransformRule> {
+    rules.iter().find(|rule| rule.matches(topic))
+}
+
+/// Applies the transform of a topic to a JSON payload
+/// Payloads that are not valid JSON, or topics without a transform, are returned untouched
+#[instrument(skip_all, level = "trace")]
+pub fn apply<'a>(transform: Option<&Transform>, payload: Cow<'a, [u8]>) -> Cow<'a, [u8]> {
+    let Some(transform) = transform else {
+        return payload;
+    };
+
+    let value = match serde_json5::from_str::<Value>(&String::from_utf8_lossy(&payload)) {
+        Ok(value) => value,
+        Err(error) => {
+            warn!(%error, "Failed to parse payload for transform, keeping it untouched");
+            return payload;
+        }
+    };
+
+    match serde_json::to_vec(&transform.apply(value)) {
+        Ok(bytes) => Cow::Owned(bytes),
+        Err(error) => {
+            warn!(%error, "Failed to serialize transformed payload, keeping it untouched");
+            payload
+        }
+    }
+}
+
+/// Wraps a JSON payload that is not an object as `{"value": ...}`
+/// Objects, and payloads that are not valid JSON, are returned untouched
+#[instrument(skip_all, level = "trace")]
+pub fn wrap_non_object(payload: Cow<'_, [u8]>) -> Cow<'_, [u8]> {
+    let value = match serde_json5::from_str::<Value>(&String::from_utf8_lossy(&payload)) {
+        Ok(Value::Object(_)) | Err(_) => return payload,
+        Ok(value) => value,
+    };
+
+    match serde_json::to_vec(&serde_json::json!({ "value": value })) {
+        Ok(bytes) => Cow::Owned(bytes),
+        Err(error) => {
+            warn!(%error, "Failed to serialize wrapped payload, keeping it untouched");
+            payload
+        }
+    }
+}
+
+/// Turns the integers marked with an int64 or uint64 format in an inferred JSON `schema` into
+/// strings keeping their format, returns true if any was found
+pub fn stringify_schema(schema: &mut Value) -> bool {
+    match schema.get("type").and_then(Value::as_str) {
+        Some("integer") if is_wide_integer(schema) => {
+            if let Some(schema) = schema.as_object_mut() {
+                schema.insert("type".to_owned(), Value::from("string"));
+                schema.remove("minimum");
+            }
+            true
+        }
+        Some("array") => schema.get_mut("items").is_some_and(stringify_schema),
+        Some("object") => schema
+            .get_mut("properties")
+            .and_then(Value::as_object_mut)
+            .is_some_and(|properties| {
+                properties
+                    .values_mut()
+                    .fold(false, |changed, schema| stringify_schema(schema) | changed)
+            }),
+        _ => false,
+    }
+}
+
+/// Rewrites the integers of the fields marked int64 or uint64 in the channel `schema` as decimal
+/// strings, e.g: u64 counters or nanosecond timestamps, which JavaScript readers would otherwise
+/// round. Following the schema keeps the type of a field even when a value is small
+/// Payloads without such fields, or that are not plain JSON, are returned untouched
+#[instrument(skip_all, level = "trace")]
+pub fn stringify_large_integers<'a>(payload: Cow<'a, [u8]>, schema: &Value) -> Cow<'a, [u8]> {
+    // JSON5 parsing loses the u64 above i64::MAX, these payloads are plain JSON in practice
+    let Ok(mut value) = serde_json::from_slice::<Value>(&payload) else {
+        return payload;
+    };
+    if !stringify_value(&mut value, schema) {
+        return payload;
+    }
+
+    match serde_json::to_vec(&value) {
+        Ok(bytes) => Cow::Owned(bytes),
+        Err(error) => {
+            warn!(%error, "Failed to serialize coerced payload, keeping it untouched");
+            payload
+        }
+    }
+}
+
+fn is_wide_integer(schema: &Value) -> bool {
+    matches!(
+        schema.get("format").and_then(Value::as_str),
+        Some("int64" | "uint64")
+    )
+}
+
+/// Returns true if any integer was rewritten
+fn stringify_value(value: &mut Value, schema: &Value) -> bool {
+    match value {
+        Value::Number(number) if !number.is_f64() && is_wide_integer(schema) => {
+            *value = Value::String(number.to_string());
+            true
+        }
+        Value::Array(values) => schema.get("items").is_some_and(|items| {
+            values.iter_mut().fold(false, |changed, value| {
+                stringify_value(value, items) | changed
+            })
+        }),
+        Value::Object(map) => schema.get("properties").is_some_and(|properties| {
+            map.iter_mut().fold(false, |changed, (key, value)| {
+                properties
+                    .get(key)
+                    .is_some_and(|schema| stringify_value(value, schema))
+                    | changed
+            })
+        }),
+        _ => false,
+    }
+}
+
+fn scale_value(value: &mut Value, factor: f64) {
+    match value {
+        Value::Number(number) => {
+            if let Some(scaled) = number
+                .as_f64()
+                .and_then(|number| serde_json::Number::from_f64(number * factor))
+            {
+                *number = scaled;
+            }
+        }
+        Value::Array(values) => values
+            .iter_mut()
+            .for_each(|value| scale_value(value, factor)),
+        _ => {}
+    }
+}
+
+fn pointer_tokens(pointer: &str) -> impl Iterator<Item = String> + '_ {
+    pointer
+        .split('/')
+        .skip(1)
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+}
+
+fn insert_pointer(root: &mut Value, pointer: &str, field: Value) {
+    let mut current = root;
+    for token in pointer_tokens(pointer) {
+        if !current.is_object() {
+            *current = Value::Object(Map::new());
+        }
+        let Value::Object(map) = current else {
+            unreachable!()
+        };
+        current = map.entry(token).or_insert(Value::Null);
+    }
+    *current = field;
+}
+
+fn remove_pointer(root: &mut Value, pointer: &str) -> Option<Value> {
+    let (parent, last) = pointer.rsplit_once('/')?;
+    let last = last.replace("~1", "/").replace("~0", "~");
+    match root.pointer_mut(parent)? {
+        Value::Object(map) => map.remove(&last),
+        Value::Array(values) => {
+            let index = last.parse::<usize>().ok()?;
+            (index < values.len()).then(|| values.remove(index))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel_descriptor::ChannelDescriptor;
+    use serde_json::json;
+
+    #[test]
+    fn test_transform_rule() {
+        let rule: TransformRule = toml::from_str(
+            r#"
+            topic = "mavlink/**/ATTITUDE"
+            select = ["/message/roll", "/message/pitch"]
+            rename = { "/message/roll" = "/roll_deg" }
+            scale = { "/message/roll" = "rad_to_deg", "/message/pitch" = 2.0 }
+            "#,
+        )
+        .unwrap();
+
+        assert!(rule.matches("mavlink/1/1/ATTITUDE"));
+        assert!(!rule.matches("mavlink/1/1/HEARTBEAT"));
+
+        let value = rule.transform.apply(json!({
+            "header": { "system_id": 1 },
+            "message": { "roll": std::f64::consts::PI, "pitch": 1.5, "yaw": 0.1 },
+        }));
+        assert_eq!(
+            value,
+            json!({ "roll_deg": 180.0, "message": { "pitch": 3.0 } })
+        );
+    }
+
+    #[test]
+    fn test_wrap_non_object() {
+        let wrap = |payload: &'static [u8]| {
+            serde_json::from_slice::<Value>(&wrap_non_object(Cow::Borrowed(payload))).unwrap()
+        };
+        assert_eq!(wrap(b"12.5"), json!({ "value": 12.5 }));
+        assert_eq!(wrap(br#""armed""#), json!({ "value": "armed" }));
+        assert_eq!(wrap(b"[1, 2]"), json!({ "value": [1, 2] }));
+        assert_eq!(wrap(br#"{"depth": 12.5}"#), json!({ "depth": 12.5 }));
+    }
+
+    #[test]
+    fn test_stringify_large_integers() {
+        let first = json!({
+            "uptime_ns": 18446744073709551615u64,
+            "offset": -9007199254740993i64,
+            "values": [9007199254740993u64],
+            "counter": 1,
+            "depth": 1.5
+        });
+        let mut schema: Value = serde_json::from_str(
+            &ChannelDescriptor::from_json("test", None, &first)
+                .unwrap()
+                .schema_content,
+        )
+        .unwrap();
+        assert!(stringify_schema(&mut schema));
+        assert_eq!(
+            schema["properties"]["uptime_ns"],
+            json!({ "type": "string", "format": "uint64" })
+        );
+        assert_eq!(
+            schema["properties"]["counter"],
+            json!({ "type": "integer" })
+        );
+
+        let coerced =
+            stringify_large_integers(Cow::Owned(serde_json::to_vec(&first).unwrap()), &schema);
+        assert_eq!(
+            serde_json::from_slice::<Value>(&coerced).unwrap(),
+            json!({
+                "uptime_ns": "18446744073709551615",
+                "offset": "-9007199254740993",
+                "values": ["9007199254740993"],
+                "counter": 1,
+                "depth": 1.5,
+            })
+        );
+
+        // The fields keep their type whatever the size of the later values
+        let payload = br#"{"uptime_ns":5,"counter":9007199254740993}"#;
+        let coerced = stringify_large_integers(Cow::Borrowed(payload), &schema);
+        assert_eq!(
+            serde_json::from_slice::<Value>(&coerced).unwrap(),
+            json!({ "uptime_ns": "5", "counter": 9007199254740993u64 })
+        );
+
+        let payload = br#"{"counter":9007199254740993}"#;
+        assert!(matches!(
+            stringify_large_integers(Cow::Borrowed(payload), &schema),
+            Cow::Borrowed(_)
+        ));
+    }
+}
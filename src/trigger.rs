@@ -0,0 +1,373 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use serde_json::Value;
+use zenoh::key_expr::OwnedKeyExpr;
+
+use crate::config::Config;
+
+/// Condition on a field of a JSON topic, e.g: `/value == true` on `blueos/leak_sensor`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Condition {
+    /// Key expression of the topics the condition is evaluated on
+    pub topic: OwnedKeyExpr,
+    /// JSON pointer of the compared field, empty compares the whole payload
+    #[serde(default)]
+    pub pointer: String,
+    pub operator: Operator,
+    pub value: Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Operator {
+    #[serde(rename = "==")]
+    Equal,
+    #[serde(rename = "!=")]
+    NotEqual,
+    #[serde(rename = ">")]
+    Greater,
+    #[serde(rename = ">=")]
+    GreaterOrEqual,
+    #[serde(rename = "<")]
+    Less,
+    #[serde(rename = "<=")]
+    LessOrEqual,
+}
+
+impl Condition {
+    pub fn matches(&self, topic: &str) -> bool {
+        zenoh::key_expr::keyexpr::new(topic).is_ok_and(|topic| self.topic.includes(topic))
+    }
+
+    /// Evaluates the condition on a payload, `None` when the field is missing or not comparable
+    pub fn evaluate(&self, value: &Value) -> Option<bool> {
+        let field = value.pointer(&self.pointer)?;
+        let ordering = || field.as_f64()?.partial_cmp(&self.value.as_f64()?);
+
+        Some(match self.operator {
+            Operator::Equal => equal(field, &self.value),
+            Operator::NotEqual => !equal(field, &self.value),
+            Operator::Greater => ordering()?.is_gt(),
+            Operator::GreaterOrEqual => ordering()?.is_ge(),
+            Operator::Less => ordering()?.is_lt(),
+            Operator::LessOrEqual => ordering()?.is_le(),
+        })
+    }
+}
+
+/// JSON equality, comparing numbers by value so `1` equals `1.0`
+fn equal(a: &Value, b: &Value) -> bool {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Boolean combination of named conditions, e.g: `(armed and depth) or manual`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Expression {
+    Variable(String),
+    Not(Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+}
+
+impl TryFrom<String> for Expression {
+    type Error = anyhow::Error;
+
+    fn try_from(expression: String) -> Result<Self> {
+        Expression::parse(&expression)
+    }
+}
+
+impl Expression {
+    /// Parses an expression of names combined with `not`, `and`, `or` and parentheses,
+    /// from the highest to the lowest precedence
+    pub fn parse(expression: &str) -> Result<Self> {
+        let tokens = tokenize(expression);
+        let mut position = 0;
+        let parsed = parse_or(&tokens, &mut position)?;
+        if let Some(token) = tokens.get(position) {
+            return Err(anyhow!("Unexpected `{token}` in `{expression}`"));
+        }
+        Ok(parsed)
+    }
+
+    fn evaluate(&self, variable: &impl Fn(&str) -> bool) -> bool {
+        match self {
+            Self::Variable(name) => variable(name),
+            Self::Not(expression) => !expression.evaluate(variable),
+            Self::And(a, b) => a.evaluate(variable) && b.evaluate(variable),
+            Self::Or(a, b) => a.evaluate(variable) || b.evaluate(variable),
+        }
+    }
+
+    fn variables(&self) -> Vec<&str> {
+        match self {
+            Self::Variable(name) => vec![name.as_str()],
+            Self::Not(expression) => expression.variables(),
+            Self::And(a, b) | Self::Or(a, b) => [a.variables(), b.variables()].concat(),
+        }
+    }
+}
+
+fn tokenize(expression: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+    while let Some(character) = chars.next() {
+        match character {
+            '(' | ')' | '!' => tokens.push(character.to_string()),
+            '&' | '|' => {
+                chars.next_if_eq(&character);
+                tokens.push(if character == '&' { "and" } else { "or" }.to_owned());
+            }
+            character if character.is_whitespace() => {}
+            character => {
+                let mut name = character.to_string();
+                while let Some(character) =
+                    chars.next_if(|character| character.is_alphanumeric() || *character == '_')
+                {
+                    name.push(character);
+                }
+                tokens.push(match name.to_lowercase().as_str() {
+                    "and" | "or" | "not" => name.to_lowercase(),
+                    _ => name,
+                });
+            }
+        }
+    }
+    tokens
+}
+
+fn parse_or(tokens: &[String], position: &mut usize) -> Result<Expression> {
+    let mut expression = parse_and(tokens, position)?;
+    while tokens.get(*position).is_some_and(|token| token == "or") {
+        *position += 1;
+        expression = Expression::Or(Box::new(expression), Box::new(parse_and(tokens, position)?));
+    }
+    Ok(expression)
+}
+
+fn parse_and(tokens: &[String], position: &mut usize) -> Result<Expression> {
+    let mut expression = parse_not(tokens, position)?;
+    while tokens.get(*position).is_some_and(|token| token == "and") {
+        *position += 1;
+        expression = Expression::And(Box::new(expression), Box::new(parse_not(tokens, position)?));
+    }
+    Ok(expression)
+}
+
+fn parse_not(tokens: &[String], position: &mut usize) -> Result<Expression> {
+    let token = tokens
+        .get(*position)
+        .ok_or_else(|| anyhow!("Unexpected end of expression"))?;
+    *position += 1;
+
+    match token.as_str() {
+        "not" | "!" => Ok(Expression::Not(Box::new(parse_not(tokens, position)?))),
+        "(" => {
+            let expression = parse_or(tokens, position)?;
+            if tokens.get(*position).is_none_or(|token| token != ")") {
+                return Err(anyhow!("Missing closing parenthesis"));
+            }
+            *position += 1;
+            Ok(expression)
+        }
+        "and" | "or" | ")" => Err(anyhow!("Unexpected `{token}`")),
+        name => Ok(Expression::Variable(name.to_owned())),
+    }
+}
+
+/// Builtin condition holding while the vehicle is armed
+pub const ARMED: &str = "armed";
+/// Builtin condition toggled by the `recorder/control/manual/{on,off}` control keys
+pub const MANUAL: &str = "manual";
+
+/// Recording trigger combining named conditions on any topic with the builtin ones
+pub struct Trigger {
+    expression: Expression,
+    conditions: BTreeMap<String, (Condition, bool)>,
+    armed: bool,
+    manual: bool,
+    active: bool,
+}
+
+impl Trigger {
+    /// Creates the trigger of the configuration, `None` when recordings follow the armed state
+    ///
+    /// A single `trigger` condition is named `trigger` and used alone unless `record_when`
+    /// refers to it.
+    pub fn from_config(config: &Config) -> Result<Option<Self>> {
+        let mut conditions = config.conditions.clone();
+        if let Some(condition) = &config.trigger {
+            conditions.insert("trigger".to_owned(), condition.clone());
+        }
+
+        let expression = match (&config.record_when, &config.trigger) {
+            (Some(expression), _) => expression.clone(),
+            (None, Some(_)) => Expression::Variable("trigger".to_owned()),
+            (None, None) => return Ok(None),
+        };
+
+        for variable in expression.variables() {
+            if variable != ARMED && variable != MANUAL && !conditions.contains_key(variable) {
+                return Err(anyhow!("Unknown condition `{variable}` in record_when"));
+            }
+        }
+
+        Ok(Some(Self {
+            expression,
+            conditions: conditions
+                .into_iter()
+                .map(|(name, condition)| (name, (condition, false)))
+                .collect(),
+            armed: false,
+            manual: false,
+            active: false,
+        }))
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Evaluates the conditions on a sample, returns the new state when it changes
+    pub fn update(&mut self, topic: &str, payload: &[u8]) -> Option<bool> {
+        let mut parsed = None;
+        for (condition, state) in self.conditions.values_mut() {
+            if !condition.matches(topic) {
+                continue;
+            }
+
+            let value = parsed.get_or_insert_with(|| {
+                std::str::from_utf8(payload)
+                    .ok()
+                    .and_then(|string| serde_json5::from_str::<Value>(string).ok())
+            });
+            if let Some(value) = value
+                && let Some(result) = condition.evaluate(value)
+            {
+                *state = result;
+            }
+        }
+
+        parsed.and_then(|_| self.evaluate())
+    }
+
+    pub fn set_armed(&mut self, armed: bool) -> Option<bool> {
+        self.armed = armed;
+        self.evaluate()
+    }
+
+    pub fn set_manual(&mut self, manual: bool) -> Option<bool> {
+        self.manual = manual;
+        self.evaluate()
+    }
+
+    fn evaluate(&mut self) -> Option<bool> {
+        let active = self.expression.evaluate(&|name| match name {
+            ARMED => self.armed,
+            MANUAL => self.manual,
+            name => self.conditions.get(name).is_some_and(|(_, state)| *state),
+        });
+        (active != self.active).then(|| {
+            self.active = active;
+            active
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trigger_condition() {
+        let config: Config = toml::from_str(
+            r#"
+            [trigger]
+            topic = "blueos/leak_sensor"
+            pointer = "/value"
+            operator = "=="
+            value = true
+            "#,
+        )
+        .unwrap();
+        let mut trigger = Trigger::from_config(&config).unwrap().unwrap();
+
+        assert_eq!(trigger.update("blueos/other", br#"{"value": true}"#), None);
+        assert_eq!(
+            trigger.update("blueos/leak_sensor", br#"{"value": true}"#),
+            Some(true)
+        );
+        assert_eq!(
+            trigger.update("blueos/leak_sensor", br#"{"value": true}"#),
+            None
+        );
+        assert_eq!(
+            trigger.update("blueos/leak_sensor", br#"{"value": false}"#),
+            Some(false)
+        );
+
+        let depth = Condition {
+            topic: "mavlink/**/VFR_HUD".parse().unwrap(),
+            pointer: "/message/alt".to_owned(),
+            operator: Operator::Less,
+            value: serde_json::json!(-0.5),
+        };
+        assert_eq!(
+            depth.evaluate(&serde_json::json!({"message": {"alt": -2}})),
+            Some(true)
+        );
+        assert_eq!(
+            depth.evaluate(&serde_json::json!({"message": {"alt": 0}})),
+            Some(false)
+        );
+        assert_eq!(depth.evaluate(&serde_json::json!({"message": {}})), None);
+    }
+
+    #[test]
+    fn test_trigger_expression() {
+        assert_eq!(
+            Expression::parse("armed AND depth || !manual").unwrap(),
+            Expression::Or(
+                Box::new(Expression::And(
+                    Box::new(Expression::Variable("armed".to_owned())),
+                    Box::new(Expression::Variable("depth".to_owned())),
+                )),
+                Box::new(Expression::Not(Box::new(Expression::Variable(
+                    "manual".to_owned()
+                )))),
+            )
+        );
+        assert!(Expression::parse("(armed and depth").is_err());
+        assert!(Expression::parse("armed depth").is_err());
+
+        let config: Config = toml::from_str(
+            r#"
+            record_when = "(armed and depth) or manual"
+
+            [conditions.depth]
+            topic = "mavlink/**/VFR_HUD"
+            pointer = "/message/alt"
+            operator = "<"
+            value = -0.5
+            "#,
+        )
+        .unwrap();
+        let mut trigger = Trigger::from_config(&config).unwrap().unwrap();
+        let deep = br#"{"message": {"alt": -2.0}}"#;
+
+        assert_eq!(trigger.update("mavlink/1/1/VFR_HUD", deep), None);
+        assert_eq!(trigger.set_armed(true), Some(true));
+        assert_eq!(trigger.set_manual(true), None);
+        assert_eq!(trigger.set_armed(false), None);
+        assert_eq!(trigger.set_manual(false), Some(false));
+
+        let config: Config = toml::from_str(r#"record_when = "armed and unknown""#).unwrap();
+        assert!(Trigger::from_config(&config).is_err());
+    }
+}
@@ -0,0 +1,93 @@
+use std::{
+    collections::VecDeque,
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use serde_json::{Value, json};
+use tracing::*;
+
+use crate::cli;
+
+/// Key of the queryable replying with the trigger history under the instance prefix, accepts
+/// `?limit=<N>`
+const HISTORY_TOPIC: &str = "trigger/history";
+/// File persisting the history in the recorder directory, one JSON transition per line
+const HISTORY_FILE: &str = "trigger_history.jsonl";
+/// Number of transitions kept, older ones are dropped
+const MAX_ENTRIES: usize = 1000;
+
+/// Key expression of the trigger history queryable, e.g: `recorder/trigger/history`
+pub fn key() -> String {
+    format!("{}/{HISTORY_TOPIC}", cli::instance_prefix())
+}
+
+/// History of the recording state transitions, answering "why did the recorder stop?"
+pub struct TriggerHistory {
+    path: PathBuf,
+    entries: VecDeque<Value>,
+}
+
+impl TriggerHistory {
+    /// Loads the persisted history of the recorder directory
+    #[instrument(level = "debug")]
+    pub fn load(directory: &Path) -> Self {
+        let path = directory.join(HISTORY_FILE);
+        let content = std::fs::read_to_string(&path).unwrap_or_default();
+        let lines: Vec<&str> = content.lines().collect();
+        let entries: VecDeque<Value> = lines[lines.len().saturating_sub(MAX_ENTRIES)..]
+            .iter()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
+
+        let history = Self { path, entries };
+        // Compacts the file once it holds more than the kept transitions
+        if lines.len() > MAX_ENTRIES {
+            history.rewrite();
+        }
+        history
+    }
+
+    /// Stores a transition, `cause` is the topic or builtin condition that changed the state
+    pub fn push(&mut self, timestamp: u64, active: bool, cause: &str, session_id: &str) {
+        let entry = json!({
+            "timestamp": chrono::DateTime::from_timestamp_nanos(timestamp as i64).to_rfc3339(),
+            "timestamp_ns": timestamp,
+            "active": active,
+            "cause": cause,
+            "session_id": session_id,
+        });
+
+        if let Err(error) = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| writeln!(file, "{entry}"))
+        {
+            warn!(%error, path = %self.path.display(), "Failed to persist trigger history");
+        }
+
+        if self.entries.len() == MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// Last `limit` transitions, oldest first
+    pub fn to_json(&self, limit: Option<usize>) -> Value {
+        let skip = limit.map_or(0, |limit| self.entries.len().saturating_sub(limit));
+        Value::Array(self.entries.iter().skip(skip).cloned().collect())
+    }
+
+    fn rewrite(&self) {
+        let content: String = self
+            .entries
+            .iter()
+            .map(|entry| format!("{entry}\n"))
+            .collect();
+        if let Err(error) = std::fs::write(&self.path, content) {
+            warn!(%error, path = %self.path.display(), "Failed to compact trigger history");
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use std::collections::HashMap;
+
+use serde_json::{Value, json};
+use tracing::*;
+
+/// Warnings repeated for every sample of a topic, e.g: an unknown encoding at 50 Hz. Only the
+/// 1st, 2nd, 4th, 8th... occurrences of a warning of a topic are logged, the others are counted
+/// and summarized with the progress report
+#[derive(Default)]
+pub struct RepeatedWarnings {
+    /// Occurrences and occurrences suppressed since the last summary, by topic and warning kind
+    warnings: HashMap<(String, &'static str), (u64, u64)>,
+}
+
+impl RepeatedWarnings {
+    /// Counts an occurrence of a warning, returns the number of occurrences when it is logged
+    pub fn record(&mut self, topic: &str, kind: &'static str) -> Option<u64> {
+        let (occurrences, suppressed) = self.warnings.entry((topic.to_owned(), kind)).or_default();
+        *occurrences += 1;
+        if occurrences.is_power_of_two() {
+            return Some(*occurrences);
+        }
+        *suppressed += 1;
+        None
+    }
+
+    /// Logs the warnings suppressed since the last summary, returns the occurrences by kind
+    pub fn summarize(&mut self) -> Value {
+        let mut totals = HashMap::<&str, u64>::new();
+        for ((topic, kind), (occurrences, suppressed)) in &mut self.warnings {
+            if *suppressed > 0 {
+                warn!(
+                    %topic,
+                    kind,
+                    suppressed = *suppressed,
+                    occurrences = *occurrences,
+                    "Repeated warnings suppressed"
+                );
+                *suppressed = 0;
+            }
+            *totals.entry(kind).or_default() += *occurrences;
+        }
+        json!(totals)
+    }
+}